@@ -0,0 +1,182 @@
+//! Compares matching every rule against every element with matching through
+//! `RuleMap` buckets and the ancestor Bloom filter, on a synthetic page with
+//! thousands of elements and rules, and times whole style passes, serial
+//! and parallel.
+//!
+//! Run with `cargo bench --bench selector_matching`.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use ukechug::css::{Parser, Stylesheet, MediaEnvironment};
+use ukechug::dom::{Node, ElementRef};
+use ukechug::style::{RuleMap, AncestorFilter, MatchedRule, matching_rules, indexed_matching_rules, style_tree_with_statistics};
+
+const SECTIONS: usize = 40;
+const ARTICLES_PER_SECTION: usize = 10;
+const ITEMS_PER_ARTICLE: usize = 12;
+const CLASSES: usize = 300;
+const RULES: usize = 3000;
+const RUNS: u32 = 5;
+
+/// A deterministic pseudo-random sequence, so every run sees the same page.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, bound: usize) -> usize {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((self.0 >> 33) as usize) % bound
+    }
+}
+
+fn element(tag: &str, attributes: &[(&str, String)], children: Vec<Node>) -> Node {
+    let attributes: HashMap<String, String> = attributes.iter()
+        .map(|(name, value)| (name.to_string(), value.clone()))
+        .collect();
+    Node::element(tag.to_string(), attributes, children)
+}
+
+fn page(random: &mut Lcg) -> Node {
+    let mut element_count = 0;
+    let class = |random: &mut Lcg| format!("c{} c{}", random.next(CLASSES), random.next(CLASSES));
+    let sections = (0..SECTIONS).map(|s| {
+        let articles = (0..ARTICLES_PER_SECTION).map(|a| {
+            let items = (0..ITEMS_PER_ARTICLE).map(|i| {
+                element_count += 3;
+                let span = element("span", &[("class", class(random))], vec![Node::text("item".to_string())]);
+                let paragraph = element("p", &[("class", class(random))], vec![span]);
+                element("li", &[("id", format!("item-{}-{}-{}", s, a, i))], vec![paragraph])
+            }).collect();
+            element_count += 3;
+            let heading = element("h2", &[("class", class(random))], vec![Node::text("title".to_string())]);
+            let list = element("ul", &[("class", class(random))], items);
+            element("article", &[("class", format!("a{} {}", a, class(random)))], vec![heading, list])
+        }).collect();
+        element_count += 1;
+        element("div", &[("class", format!("section s{}", s)), ("id", format!("section-{}", s))], articles)
+    }).collect();
+    println!("Synthetic page: {} elements", element_count + 2);
+    element("html", &[], vec![element("body", &[], sections)])
+}
+
+fn stylesheet(random: &mut Lcg) -> Stylesheet {
+    let mut css = String::new();
+    for index in 0..RULES {
+        let c = random.next(CLASSES);
+        let d = random.next(CLASSES);
+        let s = random.next(SECTIONS);
+        let selector = match index % 8 {
+            0 => format!(".c{}", c),
+            1 => format!("p.c{}", c),
+            2 => format!(".s{} .c{}", s, c),
+            3 => format!("article.c{} span", c),
+            4 => format!("ul > li#item-{}-{}-{}", s, random.next(ARTICLES_PER_SECTION), random.next(ITEMS_PER_ARTICLE)),
+            5 => format!(".c{} .c{} span", c, d),
+            6 => format!("#section-{} h2", s),
+            _ => format!("div.c{} > article li", c),
+        };
+        css.push_str(&format!("{} {{ margin: {}px; }}\n", selector, index % 7));
+    }
+    css.push_str("* { padding: 0; }\nli:nth-child(2n+1) { color: gray; }\n");
+    Parser::new(css).parse_stylesheet()
+}
+
+const TABLE_ROWS: usize = 1000;
+const TABLE_COLUMNS: usize = 8;
+const TABLE_CSS: &str = "table { width: 100%; } tr:nth-child(2n) td { background: lightgray; } \
+    td { padding: 4px; } td + td { border-left-width: 1px; } .number { text-align: right; }";
+
+fn table() -> Node {
+    let rows = (0..TABLE_ROWS).map(|row| {
+        let cells = (0..TABLE_COLUMNS).map(|column| {
+            let class = if column == 0 { "label" } else { "number" };
+            element("td", &[("class", class.to_string())], vec![Node::text(format!("{}", row * column))])
+        }).collect();
+        element("tr", &[], cells)
+    }).collect();
+    element("html", &[], vec![element("body", &[], vec![element("table", &[], rows)])])
+}
+
+/// Visits every element with the filter of its ancestors.
+fn walk(element: &ElementRef, ancestors: &AncestorFilter, visit: &mut dyn FnMut(&ElementRef, &AncestorFilter)) {
+    let Some(elem) = element.element() else {
+        return;
+    };
+    visit(element, ancestors);
+    let mut child_ancestors = *ancestors;
+    child_ancestors.insert(elem);
+    for index in 0..element.node.children.len() {
+        walk(&element.child(index), &child_ancestors, visit);
+    }
+}
+
+fn rule_indices(rules: &[MatchedRule]) -> Vec<usize> {
+    rules.iter().map(|(index, _, _)| *index).collect()
+}
+
+/// The fastest of `RUNS` runs.
+fn time(mut run: impl FnMut() -> usize) -> (Duration, usize) {
+    let mut best = Duration::MAX;
+    let mut result = 0;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        result = run();
+        best = best.min(start.elapsed());
+    }
+    (best, result)
+}
+
+fn main() {
+    let mut random = Lcg(42);
+    let root = page(&mut random);
+    let stylesheet = stylesheet(&mut random);
+    let media = MediaEnvironment::new(800.0, 600.0);
+    println!("Stylesheet: {} rules", stylesheet.rules.len());
+    let root_ref = ElementRef::root(&root);
+
+    let (linear, linear_matches) = time(|| {
+        let mut count = 0;
+        walk(&root_ref, &AncestorFilter::new(), &mut |element, _| {
+            count += matching_rules(element, None, &stylesheet, &media).len();
+        });
+        count
+    });
+
+    let rule_map = RuleMap::new(&stylesheet, &media);
+    let (indexed, indexed_matches) = time(|| {
+        let mut count = 0;
+        walk(&root_ref, &AncestorFilter::new(), &mut |element, ancestors| {
+            count += indexed_matching_rules(element, None, &rule_map, ancestors).len();
+        });
+        count
+    });
+
+    let mut mismatches = 0;
+    walk(&root_ref, &AncestorFilter::new(), &mut |element, ancestors| {
+        let expected = rule_indices(&matching_rules(element, None, &stylesheet, &media));
+        if rule_indices(&indexed_matching_rules(element, None, &rule_map, ancestors)) != expected {
+            mismatches += 1;
+        }
+    });
+    assert_eq!(mismatches, 0, "indexed matching found different rules");
+    assert_eq!(linear_matches, indexed_matches);
+
+    let stylesheets = [stylesheet];
+    let (styling, _) = time(|| style_tree_with_statistics(&root, &stylesheets, &media, 1).0.children.len());
+
+    println!("Matched rules: {}", indexed_matches);
+    println!("Every rule against every element: {:>10.2?}", linear);
+    println!("Rule map and ancestor filter:     {:>10.2?}", indexed);
+    println!("Speedup:                          {:>9.1}x", linear.as_secs_f64() / indexed.as_secs_f64());
+    println!("Full style pass:                  {:>10.2?}", styling);
+    let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+    let (parallel_styling, _) = time(|| style_tree_with_statistics(&root, &stylesheets, &media, threads).0.children.len());
+    println!("Full style pass, threads = {:<2}:   {:>10.2?}", threads, parallel_styling);
+
+    // Rows of alike cells are what the style sharing cache is for.
+    let table = table();
+    let table_stylesheets = [Parser::new(TABLE_CSS.to_string()).parse_stylesheet()];
+    let (table_styling, _) = time(|| style_tree_with_statistics(&table, &table_stylesheets, &media, 1).0.children.len());
+    let (_, statistics) = style_tree_with_statistics(&table, &table_stylesheets, &media, 1);
+    println!("Table of {} elements:            {:>10.2?}", statistics.elements, table_styling);
+    println!("Style sharing hit rate:           {:>9.1}%", statistics.hit_rate() * 100.0);
+}
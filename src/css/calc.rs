@@ -0,0 +1,240 @@
+use super::stylesheet::{Unit, Value};
+
+/// An expression from `calc()`, `min()`, `max()` or `clamp()`.
+///
+/// Leaves are numbers, percentages and dimensions. The constructors fold
+/// operations whose operands share a unit, so `calc(2 * 20px)` is built as
+/// the leaf `40px` and only mixed units such as `100% - 40px` are left to
+/// be resolved in layout.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalcNode {
+    Leaf(Value),
+    Add(Box<CalcNode>, Box<CalcNode>),
+    Sub(Box<CalcNode>, Box<CalcNode>),
+    Mul(Box<CalcNode>, Box<CalcNode>),
+    /// The divisor is always a number.
+    Div(Box<CalcNode>, Box<CalcNode>),
+    Min(Vec<CalcNode>),
+    Max(Vec<CalcNode>),
+    /// `clamp(min, value, max)`
+    Clamp(Box<CalcNode>, Box<CalcNode>, Box<CalcNode>),
+}
+
+/// What relative lengths are resolved against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LengthContext {
+    /// The size `%` refers to, usually the width of the containing block.
+    pub percentage_base: f32,
+    pub font_size: f32,
+    pub root_font_size: f32,
+    pub viewport_width: f32,
+    pub viewport_height: f32,
+}
+
+pub const DEFAULT_FONT_SIZE: f32 = 16.0;
+
+impl LengthContext {
+    /// A context for the viewport, with percentages of its width and the
+    /// default font size.
+    pub fn new(viewport_width: f32, viewport_height: f32) -> LengthContext {
+        LengthContext {
+            percentage_base: viewport_width,
+            font_size: DEFAULT_FONT_SIZE,
+            root_font_size: DEFAULT_FONT_SIZE,
+            viewport_width,
+            viewport_height,
+        }
+    }
+}
+
+impl Value {
+    /// Resolves a number, length or math expression to px. Numbers resolve
+    /// to themselves and other values to zero.
+    pub fn resolve(&self, context: &LengthContext) -> f32 {
+        match *self {
+            Value::Number(n) => n,
+            Value::Length(n, ref unit) => match unit {
+                Unit::Px => n,
+                Unit::Em => n * context.font_size,
+                Unit::Rem => n * context.root_font_size,
+                Unit::Percent => n / 100.0 * context.percentage_base,
+                Unit::Vw => n / 100.0 * context.viewport_width,
+                Unit::Vh => n / 100.0 * context.viewport_height,
+                Unit::Vmin => n / 100.0 * context.viewport_width.min(context.viewport_height),
+                Unit::Vmax => n / 100.0 * context.viewport_width.max(context.viewport_height),
+                Unit::Dppx => n,
+                Unit::Dpi => n / 96.0,
+                Unit::Dpcm => n * 2.54 / 96.0,
+            },
+            Value::Calc(ref node) => node.evaluate(context),
+            _ => 0.0,
+        }
+    }
+
+    /// Whether the value depends on a percentage.
+    pub fn has_percentage(&self) -> bool {
+        match *self {
+            Value::Length(_, Unit::Percent) => true,
+            Value::Calc(ref node) => node.leaves().iter().any(|leaf| leaf.has_percentage()),
+            _ => false,
+        }
+    }
+}
+
+impl CalcNode {
+    pub fn plus(left: CalcNode, right: CalcNode) -> CalcNode {
+        match (&left, &right) {
+            (CalcNode::Leaf(a), CalcNode::Leaf(b)) if same_unit(a, b) => CalcNode::Leaf(with_number(a, number(a) + number(b))),
+            _ => CalcNode::Add(Box::new(left), Box::new(right)),
+        }
+    }
+
+    pub fn minus(left: CalcNode, right: CalcNode) -> CalcNode {
+        match (&left, &right) {
+            (CalcNode::Leaf(a), CalcNode::Leaf(b)) if same_unit(a, b) => CalcNode::Leaf(with_number(a, number(a) - number(b))),
+            _ => CalcNode::Sub(Box::new(left), Box::new(right)),
+        }
+    }
+
+    pub fn times(left: CalcNode, right: CalcNode) -> CalcNode {
+        match (&left, &right) {
+            (CalcNode::Leaf(Value::Number(n)), CalcNode::Leaf(value))
+            | (CalcNode::Leaf(value), CalcNode::Leaf(Value::Number(n))) => CalcNode::Leaf(with_number(value, number(value) * n)),
+            _ => CalcNode::Mul(Box::new(left), Box::new(right)),
+        }
+    }
+
+    pub fn divided_by(left: CalcNode, right: CalcNode) -> CalcNode {
+        match (&left, &right) {
+            (CalcNode::Leaf(value), CalcNode::Leaf(Value::Number(n))) => CalcNode::Leaf(with_number(value, number(value) / n)),
+            _ => CalcNode::Div(Box::new(left), Box::new(right)),
+        }
+    }
+
+    pub fn min(arguments: Vec<CalcNode>) -> CalcNode {
+        match fold_leaves(&arguments, f32::min) {
+            Some(value) => CalcNode::Leaf(value),
+            None => CalcNode::Min(arguments),
+        }
+    }
+
+    pub fn max(arguments: Vec<CalcNode>) -> CalcNode {
+        match fold_leaves(&arguments, f32::max) {
+            Some(value) => CalcNode::Leaf(value),
+            None => CalcNode::Max(arguments),
+        }
+    }
+
+    pub fn clamp(min: CalcNode, value: CalcNode, max: CalcNode) -> CalcNode {
+        match (&min, &value, &max) {
+            (CalcNode::Leaf(a), CalcNode::Leaf(b), CalcNode::Leaf(c)) if same_unit(a, b) && same_unit(b, c) => {
+                CalcNode::Leaf(with_number(b, number(b).min(number(c)).max(number(a))))
+            }
+            _ => CalcNode::Clamp(Box::new(min), Box::new(value), Box::new(max)),
+        }
+    }
+
+    /// Whether the expression is a plain number rather than a dimension or
+    /// percentage.
+    pub fn is_number(&self) -> bool {
+        match *self {
+            CalcNode::Leaf(ref value) => matches!(value, Value::Number(_)),
+            CalcNode::Add(ref left, _) | CalcNode::Sub(ref left, _) | CalcNode::Div(ref left, _) => left.is_number(),
+            CalcNode::Mul(ref left, ref right) => left.is_number() && right.is_number(),
+            CalcNode::Min(ref arguments) | CalcNode::Max(ref arguments) => arguments[0].is_number(),
+            CalcNode::Clamp(_, ref value, _) => value.is_number(),
+        }
+    }
+
+    pub fn evaluate(&self, context: &LengthContext) -> f32 {
+        match *self {
+            CalcNode::Leaf(ref value) => value.resolve(context),
+            CalcNode::Add(ref left, ref right) => left.evaluate(context) + right.evaluate(context),
+            CalcNode::Sub(ref left, ref right) => left.evaluate(context) - right.evaluate(context),
+            CalcNode::Mul(ref left, ref right) => left.evaluate(context) * right.evaluate(context),
+            CalcNode::Div(ref left, ref right) => left.evaluate(context) / right.evaluate(context),
+            CalcNode::Min(ref arguments) => arguments.iter()
+                .map(|argument| argument.evaluate(context))
+                .fold(f32::INFINITY, f32::min),
+            CalcNode::Max(ref arguments) => arguments.iter()
+                .map(|argument| argument.evaluate(context))
+                .fold(f32::NEG_INFINITY, f32::max),
+            CalcNode::Clamp(ref min, ref value, ref max) => {
+                value.evaluate(context).min(max.evaluate(context)).max(min.evaluate(context))
+            }
+        }
+    }
+
+    /// The expression with every length other than a percentage resolved
+    /// to px and simplified again, so that only percentages are left to be
+    /// resolved in layout.
+    pub fn with_absolute_lengths(&self, context: &LengthContext) -> CalcNode {
+        let absolute = |node: &CalcNode| node.with_absolute_lengths(context);
+        match *self {
+            CalcNode::Leaf(ref value @ (Value::Number(_) | Value::Length(_, Unit::Percent))) => CalcNode::Leaf(value.clone()),
+            CalcNode::Leaf(ref value) => CalcNode::Leaf(Value::Length(value.resolve(context), Unit::Px)),
+            CalcNode::Add(ref left, ref right) => CalcNode::plus(absolute(left), absolute(right)),
+            CalcNode::Sub(ref left, ref right) => CalcNode::minus(absolute(left), absolute(right)),
+            CalcNode::Mul(ref left, ref right) => CalcNode::times(absolute(left), absolute(right)),
+            CalcNode::Div(ref left, ref right) => CalcNode::divided_by(absolute(left), absolute(right)),
+            CalcNode::Min(ref arguments) => CalcNode::min(arguments.iter().map(absolute).collect()),
+            CalcNode::Max(ref arguments) => CalcNode::max(arguments.iter().map(absolute).collect()),
+            CalcNode::Clamp(ref min, ref value, ref max) => CalcNode::clamp(absolute(min), absolute(value), absolute(max)),
+        }
+    }
+
+    fn leaves(&self) -> Vec<&Value> {
+        match *self {
+            CalcNode::Leaf(ref value) => vec![value],
+            CalcNode::Add(ref left, ref right)
+            | CalcNode::Sub(ref left, ref right)
+            | CalcNode::Mul(ref left, ref right)
+            | CalcNode::Div(ref left, ref right) => left.leaves().into_iter().chain(right.leaves()).collect(),
+            CalcNode::Min(ref arguments) | CalcNode::Max(ref arguments) => {
+                arguments.iter().flat_map(CalcNode::leaves).collect()
+            }
+            CalcNode::Clamp(ref min, ref value, ref max) => {
+                [min, value, max].into_iter().flat_map(|node| node.leaves()).collect()
+            }
+        }
+    }
+}
+
+/// Folds `min()`/`max()` arguments that are all leaves of one unit.
+fn fold_leaves(arguments: &[CalcNode], pick: fn(f32, f32) -> f32) -> Option<Value> {
+    let leaves: Vec<&Value> = arguments.iter()
+        .map(|argument| match argument {
+            CalcNode::Leaf(value) => Some(value),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    let first = *leaves.first()?;
+    if !leaves.iter().all(|leaf| same_unit(first, leaf)) {
+        return None;
+    }
+    let result = leaves.iter().map(|leaf| number(leaf)).reduce(pick)?;
+    Some(with_number(first, result))
+}
+
+fn same_unit(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(_), Value::Number(_)) => true,
+        (Value::Length(_, a), Value::Length(_, b)) => a == b,
+        _ => false,
+    }
+}
+
+fn number(value: &Value) -> f32 {
+    match *value {
+        Value::Number(n) | Value::Length(n, _) => n,
+        _ => 0.0,
+    }
+}
+
+/// `value` with its numeric part replaced by `n`.
+fn with_number(value: &Value, n: f32) -> Value {
+    match *value {
+        Value::Length(_, ref unit) => Value::Length(n, unit.clone()),
+        _ => Value::Number(n),
+    }
+}
@@ -0,0 +1,204 @@
+use super::stylesheet::Value;
+use super::calc::LengthContext;
+
+/// A comma-separated list of media queries. It matches when any query
+/// matches; an empty list matches every environment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaQueryList {
+    pub queries: Vec<MediaQuery>,
+}
+
+/// `[not | only]? <media-type> [and <condition>]?`, or a bare condition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaQuery {
+    pub qualifier: Option<MediaQualifier>,
+    /// `None` stands for `all`.
+    pub media_type: Option<String>,
+    pub condition: Option<MediaCondition>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaQualifier {
+    Not,
+    Only,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaCondition {
+    Feature(MediaFeature),
+    Not(Box<MediaCondition>),
+    And(Vec<MediaCondition>),
+    Or(Vec<MediaCondition>),
+}
+
+/// A parenthesized media feature test. `min-`/`max-` prefixed features are
+/// stored as ranges, so `(min-width: 600px)` is `(width >= 600px)`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaFeature {
+    /// `(color)`
+    Boolean(String),
+    /// `(orientation: portrait)`
+    Plain(String, Value),
+    /// `(400px <= width < 800px)`, stored as comparisons with the feature on
+    /// the left: `width >= 400px`, `width < 800px`.
+    Range(String, Vec<(MediaComparison, Value)>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaComparison {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+/// The rendering environment media queries are evaluated against.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaEnvironment {
+    /// `screen` or `print`.
+    pub media_type: String,
+    pub width: f32,
+    pub height: f32,
+    /// Device pixels per CSS pixel.
+    pub resolution: f32,
+    pub color_scheme: ColorScheme,
+    pub reduced_motion: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorScheme {
+    Light,
+    Dark,
+}
+
+impl MediaEnvironment {
+    pub fn new(width: f32, height: f32) -> MediaEnvironment {
+        MediaEnvironment {
+            media_type: "screen".to_string(),
+            width,
+            height,
+            resolution: 1.0,
+            color_scheme: ColorScheme::Light,
+            reduced_motion: false,
+        }
+    }
+
+    /// The numeric value of a range feature, in px, dppx or as a ratio.
+    fn numeric_feature(&self, name: &str) -> Option<f32> {
+        match name {
+            "width" => Some(self.width),
+            "height" => Some(self.height),
+            "aspect-ratio" => Some(self.width / self.height),
+            "resolution" => Some(self.resolution),
+            _ => None,
+        }
+    }
+
+    fn keyword_feature(&self, name: &str) -> Option<&str> {
+        match name {
+            "orientation" if self.height >= self.width => Some("portrait"),
+            "orientation" => Some("landscape"),
+            "prefers-color-scheme" => Some(match self.color_scheme {
+                ColorScheme::Light => "light",
+                ColorScheme::Dark => "dark",
+            }),
+            "prefers-reduced-motion" => Some(if self.reduced_motion { "reduce" } else { "no-preference" }),
+            _ => None,
+        }
+    }
+}
+
+impl MediaQueryList {
+    pub fn matches(&self, env: &MediaEnvironment) -> bool {
+        self.queries.is_empty() || self.queries.iter().any(|query| query.matches(env))
+    }
+}
+
+impl MediaQuery {
+    pub fn matches(&self, env: &MediaEnvironment) -> bool {
+        let type_matches = match self.media_type.as_deref() {
+            None | Some("all") => true,
+            Some(media_type) => media_type == env.media_type,
+        };
+        let matches = type_matches && self.condition.as_ref().is_none_or(|c| c.matches(env));
+        match self.qualifier {
+            Some(MediaQualifier::Not) => !matches,
+            _ => matches,
+        }
+    }
+}
+
+impl MediaCondition {
+    pub fn matches(&self, env: &MediaEnvironment) -> bool {
+        match *self {
+            MediaCondition::Feature(ref feature) => feature.matches(env),
+            MediaCondition::Not(ref condition) => !condition.matches(env),
+            MediaCondition::And(ref conditions) => conditions.iter().all(|c| c.matches(env)),
+            MediaCondition::Or(ref conditions) => conditions.iter().any(|c| c.matches(env)),
+        }
+    }
+}
+
+impl MediaFeature {
+    /// Unknown features and values never match.
+    pub fn matches(&self, env: &MediaEnvironment) -> bool {
+        match *self {
+            MediaFeature::Boolean(ref name) => match env.numeric_feature(name) {
+                Some(value) => value != 0.0,
+                None => match env.keyword_feature(name) {
+                    Some(value) => value != "no-preference",
+                    None => name == "color",
+                },
+            },
+            MediaFeature::Plain(ref name, ref value) => match (env.keyword_feature(name), value) {
+                (Some(actual), Value::Keyword(expected)) => actual == expected,
+                _ => MediaFeature::Range(name.clone(), vec![(MediaComparison::Eq, value.clone())]).matches(env),
+            },
+            MediaFeature::Range(ref name, ref comparisons) => {
+                let Some(actual) = env.numeric_feature(name) else {
+                    return false;
+                };
+                comparisons.iter().all(|(comparison, value)| match media_number(value, env) {
+                    Some(expected) => comparison.test(actual, expected),
+                    None => false,
+                })
+            }
+        }
+    }
+}
+
+impl MediaComparison {
+    fn test(self, actual: f32, expected: f32) -> bool {
+        match self {
+            MediaComparison::Lt => actual < expected,
+            MediaComparison::Le => actual <= expected,
+            MediaComparison::Eq => actual == expected,
+            MediaComparison::Ge => actual >= expected,
+            MediaComparison::Gt => actual > expected,
+        }
+    }
+
+    /// The comparison seen from the other side, for `600px < width`.
+    pub fn flipped(self) -> MediaComparison {
+        match self {
+            MediaComparison::Lt => MediaComparison::Gt,
+            MediaComparison::Le => MediaComparison::Ge,
+            MediaComparison::Eq => MediaComparison::Eq,
+            MediaComparison::Ge => MediaComparison::Le,
+            MediaComparison::Gt => MediaComparison::Lt,
+        }
+    }
+}
+
+/// Converts a feature value to px, dppx or a plain number. `em` and `rem`
+/// use the initial font size, and percentages are not allowed.
+fn media_number(value: &Value, env: &MediaEnvironment) -> Option<f32> {
+    match *value {
+        _ if value.has_percentage() => None,
+        Value::Number(_) | Value::Length(..) | Value::Calc(_) => {
+            Some(value.resolve(&LengthContext::new(env.width, env.height)))
+        }
+        _ => None,
+    }
+}
@@ -1,7 +1,17 @@
 pub mod stylesheet;
 pub mod parser;
+pub mod media;
+pub mod calc;
+pub mod supports;
+pub mod serializer;
+pub mod user_agent;
 
 pub use stylesheet::*;
+pub use media::*;
+pub use calc::{CalcNode, LengthContext};
+pub use supports::SupportsCondition;
+pub use serializer::CssFormat;
+pub use user_agent::user_agent_stylesheet;
 pub use parser::Parser;
 
 pub struct CSSParser;
@@ -12,8 +22,22 @@ impl CSSParser {
     }
     
     pub fn parse(&self, css: &str) -> Stylesheet {
-        println!("  [CSS] Parsing {} bytes of CSS...", css.len());
+        eprintln!("  [CSS] Parsing {} bytes of CSS...", css.len());
         let mut parser = Parser::new(css.to_string());
         parser.parse_stylesheet()
     }
-}
\ No newline at end of file
+
+    /// Parses a stylesheet loaded from `url`, resolving its `@import`s
+    /// relative to it.
+    pub fn parse_with_url(&self, css: &str, url: url::Url) -> Stylesheet {
+        eprintln!("  [CSS] Parsing {} bytes of CSS from {}...", css.len(), url);
+        let mut parser = Parser::with_base_url(css.to_string(), url);
+        parser.parse_stylesheet()
+    }
+}
+
+impl Default for CSSParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,39 +1,825 @@
 use super::stylesheet::*;
+use super::media::*;
+use super::calc::CalcNode;
+use super::supports::SupportsCondition;
+use std::fs;
+use url::Url;
 
 pub struct Parser {
     pos: usize,
     input: String,
+    /// URL of the stylesheet being parsed; `@import` URLs resolve against it.
+    base_url: Option<Url>,
+    /// Stylesheets currently being imported, outermost first, to detect
+    /// import cycles.
+    import_chain: Vec<Url>,
+    /// Number of anonymous layers seen so far, across imports.
+    anonymous_layers: usize,
+    /// `@import` is only valid before any other rule.
+    imports_allowed: bool,
+    /// `@property` registrations seen so far, including imported ones.
+    properties: Vec<PropertyRegistration>,
+    /// The `@layer` block being parsed, if any.
+    layer: Option<LayerName>,
+    /// Layers declared so far, in order of first declaration.
+    layers: Vec<LayerName>,
+    /// Selectors of the style rule whose nested rules are being parsed,
+    /// which `&` refers to.
+    nesting_parent: Option<Vec<Selector>>,
+    /// Whether the selector being parsed contains `&`.
+    nesting_used: bool,
+    /// A position seen before and its line, from which `location` counts
+    /// on.
+    line_mark: (usize, usize),
 }
 
 impl Parser {
     pub fn new(input: String) -> Parser {
-        Parser { pos: 0, input }
+        Parser {
+            pos: 0,
+            input,
+            base_url: None,
+            import_chain: Vec::new(),
+            anonymous_layers: 0,
+            imports_allowed: true,
+            properties: Vec::new(),
+            layer: None,
+            layers: Vec::new(),
+            nesting_parent: None,
+            nesting_used: false,
+            line_mark: (0, 1),
+        }
+    }
+
+    /// A parser for the stylesheet at `url`. Without a base URL, imports
+    /// resolve against the current directory.
+    pub fn with_base_url(input: String, url: Url) -> Parser {
+        Parser {
+            import_chain: vec![url.clone()],
+            base_url: Some(url),
+            ..Parser::new(input)
+        }
     }
 
     pub fn parse_stylesheet(&mut self) -> Stylesheet {
         let mut rules = Vec::new();
+        self.parse_rules(&[], &mut rules);
+        assert!(self.eof(), "Unexpected }} in stylesheet");
+        Stylesheet {
+            properties: std::mem::take(&mut self.properties),
+            layers: std::mem::take(&mut self.layers),
+            ..Stylesheet::new(rules)
+        }
+    }
+
+    /// Parses rules up to the end of input or a closing `}`, which is left
+    /// unconsumed. `conditions` are those of the enclosing at-rules.
+    fn parse_rules(&mut self, conditions: &[RuleCondition], rules: &mut Vec<Rule>) {
+        loop {
+            self.consume_whitespace();
+            if self.eof() || self.next_char() == '}' {
+                break;
+            }
+            if self.next_char() == '@' {
+                self.parse_at_rule(conditions, rules);
+            } else {
+                self.imports_allowed = false;
+                self.parse_rule(conditions, rules);
+            }
+        }
+    }
+
+    fn parse_at_rule(&mut self, conditions: &[RuleCondition], rules: &mut Vec<Rule>) {
+        assert_eq!(self.consume_char(), '@');
+        let name = self.parse_identifier().to_ascii_lowercase();
+        self.consume_whitespace();
+        // `@layer` statements may come before `@import`; `@layer` blocks
+        // turn imports off themselves.
+        if !matches!(&*name, "import" | "charset" | "layer") {
+            self.imports_allowed = false;
+        }
+        match &*name {
+            "import" => self.parse_import(rules),
+            "media" => {
+                let queries = self.parse_media_query_list();
+                let mut conditions = conditions.to_vec();
+                conditions.push(RuleCondition::Media(queries));
+                self.parse_block(&conditions, rules);
+            }
+            "supports" => {
+                let condition = self.parse_supports_condition();
+                self.consume_whitespace();
+                let mut conditions = conditions.to_vec();
+                conditions.push(RuleCondition::Supports(condition));
+                self.parse_block(&conditions, rules);
+            }
+            "layer" => self.parse_layer_rule(conditions, rules),
+            "property" => self.parse_property_rule(),
+            _ => self.skip_at_rule(),
+        }
+    }
+
+    /// Parses `@import <url> [layer | layer(<name>)]? [supports(<condition>)]?
+    /// <media-query-list>;` and inlines the imported rules where the
+    /// `@import` stands, under the import's media queries and layer. An
+    /// import whose `supports()` condition fails is not loaded.
+    fn parse_import(&mut self, rules: &mut Vec<Rule>) {
+        let href = match or_panic(self.parse_value()) {
+            Value::String(href) | Value::Url(href) => href,
+            value => panic!("Expected a URL after @import, found {:?}", value),
+        };
+        self.consume_whitespace();
+
+        let layer = if self.starts_with_keyword("layer") {
+            self.parse_identifier();
+            let layer = if !self.eof() && self.next_char() == '(' {
+                self.consume_char();
+                self.consume_whitespace();
+                let layer = self.parse_layer_name();
+                self.consume_whitespace();
+                assert_eq!(self.consume_char(), ')');
+                layer
+            } else {
+                self.anonymous_layer()
+            };
+            self.consume_whitespace();
+            Some(layer)
+        } else {
+            None
+        };
+
+        let supports = if self.starts_with_keyword_then("supports", '(') {
+            self.parse_identifier();
+            assert_eq!(self.consume_char(), '(');
+            self.consume_whitespace();
+            let condition = if self.next_char() == '(' || self.starts_with_keyword_then("not", '(') {
+                self.parse_supports_condition()
+            } else {
+                self.parse_supports_declaration()
+            };
+            self.consume_whitespace();
+            assert_eq!(self.consume_char(), ')');
+            self.consume_whitespace();
+            Some(condition)
+        } else {
+            None
+        };
+
+        let queries = self.parse_media_query_list();
+        assert_eq!(self.consume_char(), ';');
+
+        if !self.imports_allowed {
+            eprintln!("  [CSS] Ignoring @import of {} after other rules", href);
+            return;
+        }
+        if let Some(ref layer) = layer {
+            self.declare_layer(layer);
+        }
+        if supports.as_ref().is_some_and(|condition| !condition.matches()) {
+            eprintln!("  [CSS] Skipping @import of {}: supports() condition not met", href);
+            return;
+        }
+        let Some(stylesheet) = self.load_import(&href) else {
+            return;
+        };
+        self.properties.extend(stylesheet.properties);
+        for imported in &stylesheet.layers {
+            match layer {
+                Some(ref layer) => self.declare_layer(&imported.nested_in(layer)),
+                None => self.declare_layer(imported),
+            }
+        }
+
+        for mut rule in stylesheet.rules {
+            if !queries.queries.is_empty() {
+                rule.conditions.insert(0, RuleCondition::Media(queries.clone()));
+            }
+            if let Some(ref layer) = layer {
+                rule.layer = Some(match rule.layer {
+                    Some(inner) => inner.nested_in(layer),
+                    None => layer.clone(),
+                });
+            }
+            rules.push(rule);
+        }
+    }
+
+    /// Resolves `href` against this stylesheet's URL and parses the file it
+    /// points to. Unreadable files and import cycles are reported and
+    /// skipped, as a browser would.
+    fn load_import(&mut self, href: &str) -> Option<Stylesheet> {
+        let base = match self.base_url {
+            Some(ref url) => url.clone(),
+            None => {
+                let cwd = std::env::current_dir().ok()?;
+                Url::from_directory_path(cwd).ok()?
+            }
+        };
+        let url = match base.join(href) {
+            Ok(url) => url,
+            Err(e) => {
+                eprintln!("  [CSS] Could not resolve @import {}: {}", href, e);
+                return None;
+            }
+        };
+        if self.import_chain.contains(&url) {
+            eprintln!("  [CSS] Skipping @import cycle at {}", url);
+            return None;
+        }
+        let Ok(path) = url.to_file_path() else {
+            eprintln!("  [CSS] Can only @import local files, not {}", url);
+            return None;
+        };
+        let css = match fs::read_to_string(&path) {
+            Ok(css) => css,
+            Err(e) => {
+                eprintln!("  [CSS] Could not read @import {}: {}", path.display(), e);
+                return None;
+            }
+        };
+        eprintln!("  [CSS] Importing {} ({} bytes)", url, css.len());
+
+        let mut parser = Parser::with_base_url(css, url);
+        parser.import_chain = self.import_chain.iter().chain(&parser.import_chain).cloned().collect();
+        parser.anonymous_layers = self.anonymous_layers;
+        let stylesheet = parser.parse_stylesheet();
+        self.anonymous_layers = parser.anonymous_layers;
+        Some(stylesheet)
+    }
+
+    /// Parses `@property --name { syntax: ...; inherits: ...; initial-value: ...; }`.
+    /// Registrations missing `syntax` or `inherits`, or a non-universal
+    /// syntax without an initial value, are invalid and ignored.
+    fn parse_property_rule(&mut self) {
+        let name = self.parse_identifier();
+        self.consume_whitespace();
+        assert_eq!(self.consume_char(), '{');
+        let (mut syntax, mut inherits, mut initial_value) = (None, None, None);
+        loop {
+            self.consume_whitespace();
+            if self.next_char() == '}' {
+                self.consume_char();
+                break;
+            }
+            let descriptor = self.parse_identifier().to_ascii_lowercase();
+            self.consume_whitespace();
+            assert_eq!(self.consume_char(), ':');
+            let value = self.consume_raw_value();
+            if !self.eof() && self.next_char() == ';' {
+                self.consume_char();
+            }
+            match &*descriptor {
+                "syntax" => syntax = Some(value.trim_matches(|c| c == '"' || c == '\'').trim().to_string()),
+                "inherits" => inherits = Some(value.eq_ignore_ascii_case("true")),
+                "initial-value" => initial_value = Some(value),
+                _ => {}
+            }
+        }
+
+        match (syntax, inherits) {
+            (Some(syntax), Some(inherits))
+                if name.starts_with("--") && (syntax == "*" || initial_value.is_some()) =>
+            {
+                self.properties.push(PropertyRegistration { name, syntax, inherits, initial_value });
+            }
+            _ => eprintln!("  [CSS] Ignoring invalid @property {}", name),
+        }
+    }
+
+    /// Parses `@layer <name>#;`, which only fixes the order of the layers,
+    /// or `@layer <name>? { rules }`. Names are relative to the enclosing
+    /// layer block.
+    fn parse_layer_rule(&mut self, conditions: &[RuleCondition], rules: &mut Vec<Rule>) {
+        let parent = self.layer.clone();
+        let qualify = |name: LayerName| match parent {
+            Some(ref parent) => name.nested_in(parent),
+            None => name,
+        };
+
+        if self.next_char() == '{' {
+            let layer = qualify(self.anonymous_layer());
+            self.parse_layer_block(layer, conditions, rules);
+            return;
+        }
+
+        let mut names = vec![qualify(self.parse_layer_name())];
+        self.consume_whitespace();
+        if self.next_char() == '{' {
+            self.parse_layer_block(names.pop().unwrap(), conditions, rules);
+            return;
+        }
+        while self.next_char() == ',' {
+            self.consume_char();
+            self.consume_whitespace();
+            names.push(qualify(self.parse_layer_name()));
+            self.consume_whitespace();
+        }
+        assert_eq!(self.consume_char(), ';');
+        for name in &names {
+            self.declare_layer(name);
+        }
+    }
+
+    fn parse_layer_block(&mut self, layer: LayerName, conditions: &[RuleCondition], rules: &mut Vec<Rule>) {
+        self.imports_allowed = false;
+        self.declare_layer(&layer);
+        let parent = self.layer.replace(layer);
+        self.parse_block(conditions, rules);
+        self.layer = parent;
+    }
+
+    /// Records `layer`, and any parent layers before it, unless already
+    /// declared.
+    fn declare_layer(&mut self, layer: &LayerName) {
+        for length in 1..=layer.0.len() {
+            let prefix = LayerName(layer.0[..length].to_vec());
+            if !self.layers.contains(&prefix) {
+                self.layers.push(prefix);
+            }
+        }
+    }
+
+    /// Parses `not <in-parens>` or `<in-parens>` joined by only `and` or only
+    /// `or`.
+    fn parse_supports_condition(&mut self) -> SupportsCondition {
+        if self.starts_with_keyword("not") {
+            self.parse_identifier();
+            self.consume_whitespace();
+            return SupportsCondition::Not(Box::new(self.parse_supports_in_parens()));
+        }
+
+        let first = self.parse_supports_in_parens();
+        self.consume_whitespace();
+        let operator = if self.starts_with_keyword("and") {
+            "and"
+        } else if self.starts_with_keyword("or") {
+            "or"
+        } else {
+            return first;
+        };
+
+        let mut conditions = vec![first];
+        while self.starts_with_keyword(operator) {
+            self.parse_identifier();
+            self.consume_whitespace();
+            conditions.push(self.parse_supports_in_parens());
+            self.consume_whitespace();
+        }
+        if operator == "and" {
+            SupportsCondition::And(conditions)
+        } else {
+            SupportsCondition::Or(conditions)
+        }
+    }
+
+    /// A parenthesized condition or declaration. Functions such as
+    /// `selector()` are not supported and are kept as source text.
+    fn parse_supports_in_parens(&mut self) -> SupportsCondition {
+        if self.next_char() != '(' {
+            let start = self.pos;
+            let name = self.parse_identifier();
+            assert!(!name.is_empty() && self.next_char() == '(', "Expected ( in @supports condition");
+            self.skip_parenthesized();
+            return SupportsCondition::Unknown(self.input[start..self.pos].to_string());
+        }
+
+        let start = self.pos;
+        self.consume_char();
+        self.consume_whitespace();
+        let condition = if self.next_char() == '(' || self.starts_with_keyword_then("not", '(') {
+            self.parse_supports_condition()
+        } else if self.input[self.pos..].starts_with(|c: char| valid_identifier_char(c)) {
+            self.parse_supports_declaration()
+        } else {
+            self.pos = start;
+            self.skip_parenthesized();
+            return SupportsCondition::Unknown(self.input[start..self.pos].to_string());
+        };
+        self.consume_whitespace();
+        assert_eq!(self.consume_char(), ')');
+        condition
+    }
+
+    /// `name: value`, up to the closing parenthesis.
+    fn parse_supports_declaration(&mut self) -> SupportsCondition {
+        let name = self.parse_identifier();
+        self.consume_whitespace();
+        assert_eq!(self.consume_char(), ':');
+        self.consume_whitespace();
+        let start = self.pos;
+        let tokens = self.consume_raw_value();
+        let value = if name.starts_with("--") || contains_var(&tokens) {
+            Value::Tokens(tokens)
+        } else {
+            self.pos = start;
+            or_panic(self.parse_value_list())
+        };
+        SupportsCondition::Declaration(name.to_ascii_lowercase(), value)
+    }
+
+    /// Skips a balanced `( ... )`.
+    fn skip_parenthesized(&mut self) {
+        assert_eq!(self.consume_char(), '(');
+        let mut depth = 1;
+        while depth > 0 {
+            match self.consume_char() {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                '"' | '\'' => {
+                    self.pos -= 1;
+                    or_panic(self.parse_string());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Parses a dotted layer name such as `framework.base`.
+    fn parse_layer_name(&mut self) -> LayerName {
+        let mut segments = vec![LayerSegment::Named(self.parse_identifier())];
+        while !self.eof() && self.next_char() == '.' {
+            self.consume_char();
+            segments.push(LayerSegment::Named(self.parse_identifier()));
+        }
+        LayerName(segments)
+    }
+
+    fn anonymous_layer(&mut self) -> LayerName {
+        self.anonymous_layers += 1;
+        LayerName(vec![LayerSegment::Anonymous(self.anonymous_layers)])
+    }
+
+    /// Parses `{ rules }` for a conditional group rule.
+    fn parse_block(&mut self, conditions: &[RuleCondition], rules: &mut Vec<Rule>) {
+        assert_eq!(self.consume_char(), '{');
+        self.parse_rules(conditions, rules);
+        assert_eq!(self.consume_char(), '}');
+    }
+
+    /// Skips an unsupported at-rule: its prelude and either the terminating
+    /// `;` or its whole `{}` block.
+    fn skip_at_rule(&mut self) {
+        let mut depth = 0;
+        while !self.eof() {
+            match self.consume_char() {
+                ';' if depth == 0 => return,
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                '"' | '\'' => {
+                    self.pos -= 1;
+                    or_panic(self.parse_string());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn parse_media_query_list(&mut self) -> MediaQueryList {
+        let mut queries = Vec::new();
+        loop {
+            self.consume_whitespace();
+            if self.eof() || self.next_char() == '{' || self.next_char() == ';' {
+                break;
+            }
+            queries.push(self.parse_media_query());
+            self.consume_whitespace();
+            if !self.eof() && self.next_char() == ',' {
+                self.consume_char();
+            } else {
+                break;
+            }
+        }
+        MediaQueryList { queries }
+    }
+
+    fn parse_media_query(&mut self) -> MediaQuery {
+        if self.next_char() == '(' || self.starts_with_keyword_then("not", '(') {
+            return MediaQuery {
+                qualifier: None,
+                media_type: None,
+                condition: Some(self.parse_media_condition(true)),
+            };
+        }
+
+        let mut qualifier = None;
+        let mut media_type = self.parse_identifier().to_ascii_lowercase();
+        if media_type == "not" || media_type == "only" {
+            qualifier = Some(if media_type == "not" { MediaQualifier::Not } else { MediaQualifier::Only });
+            self.consume_whitespace();
+            media_type = self.parse_identifier().to_ascii_lowercase();
+        }
+        assert!(!media_type.is_empty(), "Expected a media type");
+
+        self.consume_whitespace();
+        let condition = if self.starts_with_keyword("and") {
+            self.parse_identifier();
+            self.consume_whitespace();
+            Some(self.parse_media_condition(false))
+        } else {
+            None
+        };
+        MediaQuery { qualifier, media_type: Some(media_type), condition }
+    }
+
+    /// Parses `not <in-parens>` or `<in-parens>` joined by only `and` or
+    /// only `or` (the latter when `allow_or` is set).
+    fn parse_media_condition(&mut self, allow_or: bool) -> MediaCondition {
+        if self.starts_with_keyword("not") {
+            self.parse_identifier();
+            self.consume_whitespace();
+            return MediaCondition::Not(Box::new(self.parse_media_in_parens()));
+        }
+
+        let first = self.parse_media_in_parens();
+        self.consume_whitespace();
+        let operator = if self.starts_with_keyword("and") {
+            "and"
+        } else if allow_or && self.starts_with_keyword("or") {
+            "or"
+        } else {
+            return first;
+        };
+
+        let mut conditions = vec![first];
+        while self.starts_with_keyword(operator) {
+            self.parse_identifier();
+            self.consume_whitespace();
+            conditions.push(self.parse_media_in_parens());
+            self.consume_whitespace();
+        }
+        if operator == "and" {
+            MediaCondition::And(conditions)
+        } else {
+            MediaCondition::Or(conditions)
+        }
+    }
+
+    fn parse_media_in_parens(&mut self) -> MediaCondition {
+        assert_eq!(self.consume_char(), '(');
+        self.consume_whitespace();
+        let condition = if self.next_char() == '(' || self.starts_with_keyword_then("not", '(') {
+            self.parse_media_condition(true)
+        } else {
+            MediaCondition::Feature(self.parse_media_feature())
+        };
+        self.consume_whitespace();
+        assert_eq!(self.consume_char(), ')');
+        condition
+    }
+
+    /// Parses the inside of `( ... )`: `name`, `name: value`, or the range
+    /// forms `name op value`, `value op name` and `value op name op value`.
+    fn parse_media_feature(&mut self) -> MediaFeature {
+        if self.starts_number() {
+            let low = self.parse_media_value();
+            self.consume_whitespace();
+            let first = self.parse_media_comparison().flipped();
+            self.consume_whitespace();
+            let name = self.parse_identifier().to_ascii_lowercase();
+            self.consume_whitespace();
+            let mut comparisons = vec![(first, low)];
+            if self.next_char() != ')' {
+                let second = self.parse_media_comparison();
+                self.consume_whitespace();
+                comparisons.push((second, self.parse_media_value()));
+            }
+            return MediaFeature::Range(name, comparisons);
+        }
+
+        let name = self.parse_identifier().to_ascii_lowercase();
+        self.consume_whitespace();
+        match self.next_char() {
+            ')' => MediaFeature::Boolean(name),
+            ':' => {
+                self.consume_char();
+                self.consume_whitespace();
+                let value = self.parse_media_value();
+                if let Some(name) = name.strip_prefix("min-") {
+                    MediaFeature::Range(name.to_string(), vec![(MediaComparison::Ge, value)])
+                } else if let Some(name) = name.strip_prefix("max-") {
+                    MediaFeature::Range(name.to_string(), vec![(MediaComparison::Le, value)])
+                } else {
+                    MediaFeature::Plain(name, value)
+                }
+            }
+            _ => {
+                let comparison = self.parse_media_comparison();
+                self.consume_whitespace();
+                MediaFeature::Range(name, vec![(comparison, self.parse_media_value())])
+            }
+        }
+    }
+
+    /// A single feature value. A `<ratio>` such as `16/9` is stored as the
+    /// number it divides out to.
+    fn parse_media_value(&mut self) -> Value {
+        let value = or_panic(self.parse_value());
+        self.consume_whitespace();
+        match value {
+            Value::Number(numerator) if self.next_char() == '/' => {
+                self.consume_char();
+                self.consume_whitespace();
+                Value::Number(numerator / self.parse_float())
+            }
+            value => value,
+        }
+    }
+
+    fn parse_media_comparison(&mut self) -> MediaComparison {
+        let comparison = match self.consume_char() {
+            '=' => return MediaComparison::Eq,
+            '<' => MediaComparison::Lt,
+            '>' => MediaComparison::Gt,
+            c => panic!("Unexpected character {} in media feature", c),
+        };
+        if self.next_char() != '=' {
+            return comparison;
+        }
+        self.consume_char();
+        match comparison {
+            MediaComparison::Lt => MediaComparison::Le,
+            _ => MediaComparison::Ge,
+        }
+    }
+
+    /// Checks for an identifier equal to `keyword` (ASCII case-insensitive)
+    /// at the current position.
+    fn starts_with_keyword(&self, keyword: &str) -> bool {
+        let rest = &self.input[self.pos..];
+        rest.get(..keyword.len()).is_some_and(|s| s.eq_ignore_ascii_case(keyword))
+            && !rest[keyword.len()..].starts_with(valid_identifier_char)
+    }
+
+    /// Checks for `keyword`, optional whitespace, then `next`.
+    fn starts_with_keyword_then(&self, keyword: &str, next: char) -> bool {
+        self.starts_with_keyword(keyword)
+            && self.input[self.pos + keyword.len()..].trim_start().starts_with(next)
+    }
+
+    /// Parses the contents of a `style` attribute: a declaration list without
+    /// the surrounding braces.
+    pub fn parse_inline_declarations(&mut self) -> Vec<Declaration> {
+        let mut declarations = Vec::new();
         loop {
             self.consume_whitespace();
             if self.eof() {
                 break;
             }
-            rules.push(self.parse_rule());
+            declarations.extend(self.parse_declaration());
         }
-        Stylesheet::new(rules)
+        declarations
+    }
+
+    /// Parses a style rule and the rules nested in it, which are flattened
+    /// into `rules` after their parent. In a nested selector `&` stands for
+    /// `:is(<parent selectors>)`, and a selector without `&` is relative to
+    /// the parent, so `.card { h2 {} }` becomes `:is(.card) h2`.
+    fn parse_rule(&mut self, conditions: &[RuleCondition], rules: &mut Vec<Rule>) {
+        let location = self.location();
+        let selectors = self.parse_selectors();
+        self.parse_style_block(selectors, location, conditions, rules);
     }
 
-    fn parse_rule(&mut self) -> Rule {
-        Rule {
-            selectors: self.parse_selectors(),
-            declarations: self.parse_declarations(),
+    /// Parses the `{ ... }` of a style rule: declarations, nested style rules
+    /// and nested `@media`/`@supports` blocks. Declarations that follow
+    /// nested rules still belong to the parent rule, which is left out if it
+    /// has no declarations.
+    fn parse_style_block(
+        &mut self,
+        selectors: Vec<Selector>,
+        location: SourceLocation,
+        conditions: &[RuleCondition],
+        rules: &mut Vec<Rule>,
+    ) {
+        assert_eq!(self.consume_char(), '{');
+        let index = rules.len();
+        rules.push(Rule {
+            selectors: selectors.clone(),
+            declarations: Vec::new(),
+            conditions: conditions.to_vec(),
+            layer: self.layer.clone(),
+            location,
+        });
+        loop {
+            self.consume_whitespace();
+            if self.next_char() == '}' {
+                self.consume_char();
+                break;
+            }
+            if self.next_char() == '@' {
+                self.parse_nested_at_rule(&selectors, conditions, rules);
+            } else if self.starts_declaration() {
+                if let Some(declaration) = self.parse_declaration() {
+                    rules[index].declarations.push(declaration);
+                }
+            } else {
+                let location = self.location();
+                let nested = self.parse_nested_selectors(&selectors);
+                self.parse_style_block(nested, location, conditions, rules);
+            }
+        }
+        if rules[index].declarations.is_empty() {
+            rules.remove(index);
         }
     }
 
-    fn parse_selectors(&mut self) -> Vec<Selector> {
+    /// A conditional group rule inside a style rule. Its declarations apply
+    /// to the enclosing rule's selectors under the condition.
+    fn parse_nested_at_rule(&mut self, selectors: &[Selector], conditions: &[RuleCondition], rules: &mut Vec<Rule>) {
+        let location = self.location();
+        assert_eq!(self.consume_char(), '@');
+        let name = self.parse_identifier().to_ascii_lowercase();
+        self.consume_whitespace();
+        let condition = match &*name {
+            "media" => RuleCondition::Media(self.parse_media_query_list()),
+            "supports" => {
+                let condition = self.parse_supports_condition();
+                self.consume_whitespace();
+                RuleCondition::Supports(condition)
+            }
+            _ => {
+                self.skip_at_rule();
+                return;
+            }
+        };
+        let mut conditions = conditions.to_vec();
+        conditions.push(condition);
+        self.parse_style_block(selectors.to_vec(), location, &conditions, rules);
+    }
+
+    /// Whether a style block continues with a declaration rather than a
+    /// nested rule, which reaches a `{` before any `;` or `}`.
+    fn starts_declaration(&self) -> bool {
+        if self.starts_with("--") {
+            return true;
+        }
+        let mut depth = 0;
+        let mut quote = None;
+        for c in self.input[self.pos..].chars() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'') => quote = Some(c),
+                (None, '(' | '[') => depth += 1,
+                (None, ')' | ']') => depth -= 1,
+                (None, '{') if depth == 0 => return false,
+                (None, ';' | '}') if depth == 0 => return true,
+                _ => {}
+            }
+        }
+        true
+    }
+
+    /// Parses the selector list of a nested rule. Selectors without `&` are
+    /// joined to `&` by their leading combinator, or a descendant combinator
+    /// if they have none.
+    fn parse_nested_selectors(&mut self, parent: &[Selector]) -> Vec<Selector> {
+        let outer = self.nesting_parent.replace(parent.to_vec());
         let mut selectors = Vec::new();
         loop {
-            selectors.push(Selector::Simple(self.parse_simple_selector()));
             self.consume_whitespace();
+            let combinator = self.parse_leading_combinator();
+            self.nesting_used = false;
+            let selector = self.parse_selector();
+            selectors.push(match combinator {
+                Some(combinator) => prepend(selector, combinator, nesting_selector(parent)),
+                None if !self.nesting_used => prepend(selector, Combinator::Descendant, nesting_selector(parent)),
+                None => selector,
+            });
+            match self.next_char() {
+                ',' => {
+                    self.consume_char();
+                }
+                '{' => break,
+                c => panic!("Unexpected character {} in selector list", c),
+            }
+        }
+        self.nesting_parent = outer;
+        selectors
+    }
+
+    /// A combinator at the start of a relative selector, as in `:has(> a)`.
+    fn parse_leading_combinator(&mut self) -> Option<Combinator> {
+        let combinator = match self.next_char() {
+            '>' => Combinator::Child,
+            '+' => Combinator::NextSibling,
+            '~' => Combinator::SubsequentSibling,
+            _ => return None,
+        };
+        self.consume_char();
+        self.consume_whitespace();
+        Some(combinator)
+    }
+
+    fn parse_selectors(&mut self) -> Vec<Selector> {
+        let mut selectors = Vec::new();
+        loop {
+            selectors.push(self.parse_selector());
             match self.next_char() {
                 ',' => {
                     self.consume_char();
@@ -43,19 +829,103 @@ impl Parser {
                 c => panic!("Unexpected character {} in selector list", c),
             }
         }
-        selectors.sort_by(|a, b| b.specificity().cmp(&a.specificity()));
         selectors
     }
 
+    /// Parses the whole input as a comma-separated selector list, e.g. one
+    /// given on the command line.
+    pub fn parse_selector_list(&mut self) -> Vec<Selector> {
+        let mut selectors = Vec::new();
+        loop {
+            self.consume_whitespace();
+            selectors.push(self.parse_selector());
+            self.consume_whitespace();
+            if self.eof() {
+                return selectors;
+            }
+            assert_eq!(self.consume_char(), ',', "Unexpected character in selector list");
+        }
+    }
+
+    /// Parses a comma-separated selector list inside a functional
+    /// pseudo-class, consuming the closing `)`.
+    fn parse_selector_list_argument(&mut self) -> Vec<Selector> {
+        let mut selectors = Vec::new();
+        loop {
+            self.consume_whitespace();
+            selectors.push(self.parse_selector());
+            match self.consume_char() {
+                ',' => {}
+                ')' => break,
+                c => panic!("Unexpected character {} in selector list", c),
+            }
+        }
+        selectors
+    }
+
+    /// Parses the arguments of `:has()`, each optionally starting with a
+    /// combinator, consuming the closing `)`.
+    fn parse_relative_selector_list(&mut self) -> Vec<RelativeSelector> {
+        let mut selectors = Vec::new();
+        loop {
+            self.consume_whitespace();
+            let combinator = self.parse_leading_combinator();
+            selectors.push(RelativeSelector {
+                combinator: combinator.unwrap_or(Combinator::Descendant),
+                selector: self.parse_selector(),
+            });
+            match self.consume_char() {
+                ',' => {}
+                ')' => break,
+                c => panic!("Unexpected character {} in :has()", c),
+            }
+        }
+        selectors
+    }
+
+    /// Parses one complex selector, stopping before the `,`, `{` or `)` that
+    /// follows it, or at the end of input.
+    fn parse_selector(&mut self) -> Selector {
+        let mut selector = Selector::Simple(self.parse_simple_selector());
+        loop {
+            let start = self.pos;
+            self.consume_whitespace();
+            if self.eof() {
+                break;
+            }
+            let combinator = match self.next_char() {
+                ',' | '{' | ')' => break,
+                '>' => Combinator::Child,
+                '+' => Combinator::NextSibling,
+                '~' => Combinator::SubsequentSibling,
+                _ if self.pos > start => Combinator::Descendant,
+                c => panic!("Unexpected character {} in selector", c),
+            };
+            if combinator != Combinator::Descendant {
+                self.consume_char();
+                self.consume_whitespace();
+            }
+            let right = self.parse_simple_selector();
+            selector = Selector::Compound(Box::new(selector), combinator, right);
+        }
+        selector
+    }
+
     fn parse_simple_selector(&mut self) -> SimpleSelector {
-        let mut selector = SimpleSelector {
-            tag_name: None,
-            id: None,
-            classes: Vec::new(),
-        };
+        let start = self.pos;
+        let mut selector = SimpleSelector::default();
 
         while !self.eof() {
             match self.next_char() {
+                '&' => {
+                    // Outside a nested rule, `&` is the scoping root.
+                    self.consume_char();
+                    self.nesting_used = true;
+                    selector.pseudo_classes.push(match self.nesting_parent {
+                        Some(ref parent) => PseudoClass::Is(parent.clone()),
+                        None => PseudoClass::Root,
+                    });
+                }
                 '#' => {
                     self.consume_char();
                     selector.id = Some(self.parse_identifier());
@@ -67,88 +937,621 @@ impl Parser {
                 '*' => {
                     self.consume_char();
                 }
+                '[' => {
+                    selector.attributes.push(self.parse_attribute_selector());
+                }
+                ':' if self.starts_with("::") => {
+                    self.consume_char();
+                    self.consume_char();
+                    selector.pseudo_element = Some(self.parse_pseudo_element());
+                }
+                ':' => {
+                    let rest = &self.input[self.pos + 1..];
+                    let legacy = ["before", "after"].iter()
+                        .any(|name| rest.get(..name.len()).is_some_and(|s| s.eq_ignore_ascii_case(name)));
+                    if legacy {
+                        // CSS 2 single-colon syntax for pseudo-elements.
+                        self.consume_char();
+                        selector.pseudo_element = Some(self.parse_pseudo_element());
+                    } else {
+                        selector.pseudo_classes.push(self.parse_pseudo_class());
+                    }
+                }
                 c if valid_identifier_char(c) => {
                     selector.tag_name = Some(self.parse_identifier());
                 }
                 _ => break,
             }
         }
+        if self.pos == start {
+            panic!("Expected a selector, found {}", self.next_char());
+        }
         selector
     }
 
-    fn parse_declarations(&mut self) -> Vec<Declaration> {
-        assert_eq!(self.consume_char(), '{');
-        let mut declarations = Vec::new();
-        loop {
+    fn parse_pseudo_element(&mut self) -> PseudoElement {
+        let name = self.parse_identifier();
+        match &*name.to_ascii_lowercase() {
+            "before" => PseudoElement::Before,
+            "after" => PseudoElement::After,
+            "marker" => PseudoElement::Marker,
+            _ => panic!("Unsupported pseudo-element ::{}", name),
+        }
+    }
+
+    fn parse_pseudo_class(&mut self) -> PseudoClass {
+        assert_eq!(self.consume_char(), ':');
+        let name = self.parse_identifier().to_ascii_lowercase();
+        if self.eof() || self.next_char() != '(' {
+            return match &*name {
+                "root" => PseudoClass::Root,
+                "empty" => PseudoClass::Empty,
+                "first-child" => PseudoClass::FirstChild,
+                "last-child" => PseudoClass::LastChild,
+                "only-child" => PseudoClass::OnlyChild,
+                "first-of-type" => PseudoClass::FirstOfType,
+                "last-of-type" => PseudoClass::LastOfType,
+                "only-of-type" => PseudoClass::OnlyOfType,
+                "hover" => PseudoClass::Hover,
+                "active" => PseudoClass::Active,
+                "focus" => PseudoClass::Focus,
+                "focus-within" => PseudoClass::FocusWithin,
+                "focus-visible" => PseudoClass::FocusVisible,
+                "checked" => PseudoClass::Checked,
+                "disabled" => PseudoClass::Disabled,
+                "enabled" => PseudoClass::Enabled,
+                "target" => PseudoClass::Target,
+                "link" => PseudoClass::Link,
+                "visited" => PseudoClass::Visited,
+                _ => panic!("Unsupported pseudo-class :{}", name),
+            };
+        }
+
+        self.consume_char();
+        match &*name {
+            "not" => PseudoClass::Not(self.parse_selector_list_argument()),
+            "is" | "matches" => PseudoClass::Is(self.parse_selector_list_argument()),
+            "where" => PseudoClass::Where(self.parse_selector_list_argument()),
+            "has" => PseudoClass::Has(self.parse_relative_selector_list()),
+            "nth-child" | "nth-last-child" => {
+                let nth = self.parse_nth();
+                let of = if self.next_char() == ')' {
+                    self.consume_char();
+                    Vec::new()
+                } else {
+                    let keyword = self.parse_identifier();
+                    assert!(keyword.eq_ignore_ascii_case("of"), "Unexpected {} in :{}()", keyword, name);
+                    self.parse_selector_list_argument()
+                };
+                if name == "nth-child" {
+                    PseudoClass::NthChild(nth, of)
+                } else {
+                    PseudoClass::NthLastChild(nth, of)
+                }
+            }
+            "nth-of-type" | "nth-last-of-type" => {
+                let nth = self.parse_nth();
+                assert_eq!(self.consume_char(), ')');
+                if name == "nth-of-type" {
+                    PseudoClass::NthOfType(nth)
+                } else {
+                    PseudoClass::NthLastOfType(nth)
+                }
+            }
+            _ => panic!("Unsupported pseudo-class :{}()", name),
+        }
+    }
+
+    /// Parses the `An+B` microsyntax (`odd`, `even`, `3`, `-n+2`, `2n + 1`),
+    /// leaving the parser at the next token after it.
+    fn parse_nth(&mut self) -> Nth {
+        self.consume_whitespace();
+        if self.next_char().is_alphabetic() && !self.next_char().eq_ignore_ascii_case(&'n') {
+            let keyword = self.parse_identifier().to_ascii_lowercase();
             self.consume_whitespace();
-            if self.next_char() == '}' {
+            return match &*keyword {
+                "odd" => Nth { a: 2, b: 1 },
+                "even" => Nth { a: 2, b: 0 },
+                _ => panic!("Unexpected {} in An+B", keyword),
+            };
+        }
+
+        let sign = match self.next_char() {
+            '-' => {
                 self.consume_char();
-                break;
+                -1
+            }
+            '+' => {
+                self.consume_char();
+                1
             }
-            declarations.push(self.parse_declaration());
+            _ => 1,
+        };
+        let digits = self.consume_digits();
+        let nth = if !self.eof() && self.next_char().eq_ignore_ascii_case(&'n') {
+            self.consume_char();
+            let a = if digits.is_empty() { sign } else { sign * digits.parse::<i32>().unwrap() };
+            self.consume_whitespace();
+            let b = match self.next_char() {
+                '+' | '-' => {
+                    let b_sign = if self.consume_char() == '-' { -1 } else { 1 };
+                    self.consume_whitespace();
+                    b_sign * self.consume_digits().parse::<i32>().unwrap()
+                }
+                _ => 0,
+            };
+            Nth { a, b }
+        } else {
+            Nth { a: 0, b: sign * digits.parse::<i32>().unwrap() }
+        };
+        self.consume_whitespace();
+        nth
+    }
+
+    fn parse_attribute_selector(&mut self) -> AttributeSelector {
+        assert_eq!(self.consume_char(), '[');
+        self.consume_whitespace();
+        let name = self.parse_identifier();
+        self.consume_whitespace();
+        if self.next_char() == ']' {
+            self.consume_char();
+            return AttributeSelector { name, matcher: None };
+        }
+
+        let operator = match self.consume_char() {
+            '=' => AttributeOperator::Equals,
+            c => {
+                let operator = match c {
+                    '~' => AttributeOperator::Includes,
+                    '|' => AttributeOperator::DashMatch,
+                    '^' => AttributeOperator::Prefix,
+                    '$' => AttributeOperator::Suffix,
+                    '*' => AttributeOperator::Substring,
+                    c => panic!("Unexpected character {} in attribute selector", c),
+                };
+                assert_eq!(self.consume_char(), '=');
+                operator
+            }
+        };
+        self.consume_whitespace();
+        let value = match self.next_char() {
+            '"' | '\'' => or_panic(self.parse_string()),
+            _ => self.parse_identifier(),
+        };
+        self.consume_whitespace();
+        let case = match self.next_char() {
+            ']' => AttributeCase::Default,
+            _ => match &*self.parse_identifier().to_ascii_lowercase() {
+                "i" => AttributeCase::Insensitive,
+                "s" => AttributeCase::Sensitive,
+                flag => panic!("Unknown attribute selector flag {}", flag),
+            },
+        };
+        self.consume_whitespace();
+        assert_eq!(self.consume_char(), ']');
+
+        AttributeSelector {
+            name,
+            matcher: Some(AttributeMatcher { operator, value, case }),
         }
-        declarations
     }
 
-    fn parse_declaration(&mut self) -> Declaration {
+    /// Parses a quoted string, resolving backslash escapes of single
+    /// characters.
+    fn parse_string(&mut self) -> Result<String, String> {
+        let quote = self.consume_char();
+        assert!(quote == '"' || quote == '\'');
+        let mut value = String::new();
+        loop {
+            if self.eof() {
+                return Err("Unterminated string".to_string());
+            }
+            match self.consume_char() {
+                '\\' if !self.eof() => value.push(self.consume_char()),
+                c if c == quote => break,
+                c => value.push(c),
+            }
+        }
+        Ok(value)
+    }
+
+    /// Parses one declaration. A value that does not parse, such as a
+    /// dimension with a unit this engine does not know, makes the
+    /// declaration invalid: it is skipped and `None` returned.
+    fn parse_declaration(&mut self) -> Option<Declaration> {
         let property_name = self.parse_identifier();
         self.consume_whitespace();
         assert_eq!(self.consume_char(), ':');
         self.consume_whitespace();
-        let value = self.parse_value();
+        let start = self.pos;
+        let tokens = self.consume_raw_value();
+        let end = self.pos;
+        let value = if property_name.starts_with("--") || contains_var(&tokens) {
+            Ok(Value::Tokens(tokens.clone()))
+        } else {
+            self.pos = start;
+            self.parse_value_list()
+        };
+        if value.is_err() {
+            self.pos = end;
+        }
+        let important = self.parse_important();
+        if !self.eof() && self.next_char() != '}' {
+            assert_eq!(self.consume_char(), ';');
+        }
+
+        match value {
+            Ok(value) => Some(Declaration {
+                name: property_name,
+                value,
+                important,
+            }),
+            Err(error) => {
+                eprintln!("  [CSS] Dropping invalid declaration {}: {} ({})", property_name, tokens, error);
+                None
+            }
+        }
+    }
+
+    fn parse_important(&mut self) -> bool {
+        if self.eof() || self.next_char() != '!' {
+            return false;
+        }
+        self.consume_char();
+        self.consume_whitespace();
+        let keyword = self.parse_identifier();
+        assert!(keyword.eq_ignore_ascii_case("important"), "Unexpected !{} in declaration", keyword);
         self.consume_whitespace();
-        assert_eq!(self.consume_char(), ';');
+        true
+    }
+
+    /// Consumes the source text of a value up to the `;`, `}`, `!` or
+    /// unbalanced `)` that ends it, stepping over strings and nested blocks,
+    /// and returns it trimmed.
+    fn consume_raw_value(&mut self) -> String {
+        let start = self.pos;
+        let mut depth = 0;
+        while !self.eof() {
+            match self.next_char() {
+                ';' | '}' | '!' | ')' if depth == 0 => break,
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth -= 1,
+                '"' | '\'' => {
+                    or_panic(self.parse_string());
+                    continue;
+                }
+                _ => {}
+            }
+            self.consume_char();
+        }
+        self.input[start..self.pos].trim().to_string()
+    }
+
+    /// Parses a whole input as one value, as needed for a declaration value
+    /// after `var()` substitution. Empty input yields `None`.
+    pub fn parse_standalone_value(&mut self) -> Option<Value> {
+        self.consume_whitespace();
+        if self.eof() {
+            return None;
+        }
+        Some(or_panic(self.try_parse_standalone_value()))
+    }
 
-        Declaration {
-            name: property_name,
-            value,
+    /// Like `parse_standalone_value`, but returns an error for empty input
+    /// or input that is not a value, instead of panicking. Values built by
+    /// `var()` substitution can be anything, and must not stop styling.
+    pub fn try_parse_standalone_value(&mut self) -> Result<Value, String> {
+        let value = self.parse_value_list()?;
+        self.consume_whitespace();
+        if !self.eof() {
+            return Err(format!("Unexpected {} in value", &self.input[self.pos..]));
         }
+        Ok(value)
     }
 
-    fn parse_value(&mut self) -> Value {
+    /// Parses space-separated component values up to the `;`, `}`, `!`, `,`
+    /// or `)` that ends them. A single component is returned as is, several
+    /// as a `Value::List`.
+    fn parse_value_list(&mut self) -> Result<Value, String> {
+        let mut values = Vec::new();
+        loop {
+            self.consume_whitespace();
+            if self.eof() {
+                break;
+            }
+            match self.next_char() {
+                ';' | '}' | '!' | ',' | ')' => break,
+                _ => values.push(self.parse_value()?),
+            }
+        }
+        match values.len() {
+            0 => Err("Expected a value".to_string()),
+            1 => Ok(values.pop().unwrap()),
+            _ => Ok(Value::List(values)),
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, String> {
         match self.next_char() {
-            '0'..='9' => self.parse_length(),
+            _ if self.starts_number() => self.parse_numeric(),
             '#' => self.parse_color(),
-            _ => Value::Keyword(self.parse_identifier()),
+            '"' | '\'' => Ok(Value::String(self.parse_string()?)),
+            c => {
+                let name = self.parse_identifier();
+                if name.is_empty() {
+                    return Err(format!("Unexpected character {} in value", c));
+                }
+                if !self.eof() && self.next_char() == '(' {
+                    if name.eq_ignore_ascii_case("url") {
+                        self.parse_url()
+                    } else if is_math_function(&name) {
+                        self.parse_math_function(&name.to_ascii_lowercase())
+                    } else {
+                        self.parse_function(name)
+                    }
+                } else {
+                    Ok(Value::Keyword(name))
+                }
+            }
         }
     }
 
-    fn parse_length(&mut self) -> Value {
-        Value::Length(self.parse_float(), self.parse_unit())
+    /// Parses the rest of `url(...)`, whose argument is either a string or
+    /// an unquoted URL.
+    fn parse_url(&mut self) -> Result<Value, String> {
+        self.expect_char('(')?;
+        self.consume_whitespace();
+        let url = match self.nth_char(0) {
+            Some('"' | '\'') => self.parse_string()?,
+            _ => self.consume_while(|c| c != ')' && !c.is_whitespace()),
+        };
+        self.consume_whitespace();
+        self.expect_char(')')?;
+        Ok(Value::Url(url))
     }
 
-    fn parse_float(&mut self) -> f32 {
-        let s = self.consume_while(|c| match c {
-            '0'..='9' | '.' => true,
+    fn parse_function(&mut self, name: String) -> Result<Value, String> {
+        self.expect_char('(')?;
+        let mut arguments = Vec::new();
+        loop {
+            self.consume_whitespace();
+            if self.nth_char(0) == Some(')') {
+                self.consume_char();
+                break;
+            }
+            arguments.push(self.parse_value_list()?);
+            match self.nth_char(0) {
+                Some(',') => {}
+                Some(')') => {
+                    self.consume_char();
+                    break;
+                }
+                Some(c) => return Err(format!("Unexpected character {} in {}()", c, name)),
+                None => return Err(format!("Unclosed {}()", name)),
+            }
+            self.consume_char();
+        }
+        Ok(Value::Function(name.to_ascii_lowercase(), arguments))
+    }
+
+    /// Parses the arguments of `calc()`, `min()`, `max()` or `clamp()`. An
+    /// expression that folds to a single number or dimension is returned as
+    /// that value.
+    fn parse_math_function(&mut self, name: &str) -> Result<Value, String> {
+        Ok(match self.parse_math_arguments(name)? {
+            CalcNode::Leaf(value) => value,
+            node => Value::Calc(Box::new(node)),
+        })
+    }
+
+    fn parse_math_arguments(&mut self, name: &str) -> Result<CalcNode, String> {
+        self.expect_char('(')?;
+        let mut arguments = Vec::new();
+        loop {
+            self.consume_whitespace();
+            arguments.push(self.parse_calc_sum()?);
+            self.consume_whitespace();
+            match self.nth_char(0) {
+                Some(',') if name != "calc" => {}
+                Some(')') => {
+                    self.consume_char();
+                    break;
+                }
+                Some(c) => return Err(format!("Unexpected character {} in {}()", c, name)),
+                None => return Err(format!("Unclosed {}()", name)),
+            }
+            self.consume_char();
+        }
+        let is_number = arguments[0].is_number();
+        if !arguments.iter().all(|argument| argument.is_number() == is_number) {
+            return Err(format!("Mixed numbers and dimensions in {}()", name));
+        }
+
+        Ok(match name {
+            "calc" => arguments.pop().unwrap(),
+            "min" => CalcNode::min(arguments),
+            "max" => CalcNode::max(arguments),
+            _ => {
+                if arguments.len() != 3 {
+                    return Err("clamp() takes three arguments".to_string());
+                }
+                let max = arguments.pop().unwrap();
+                let value = arguments.pop().unwrap();
+                CalcNode::clamp(arguments.pop().unwrap(), value, max)
+            }
+        })
+    }
+
+    /// `<product> [ [ '+' | '-' ] <product> ]*`. The operators must be
+    /// surrounded by whitespace, so `1px -2px` is not a subtraction.
+    fn parse_calc_sum(&mut self) -> Result<CalcNode, String> {
+        let mut node = self.parse_calc_product()?;
+        loop {
+            let start = self.pos;
+            self.consume_whitespace();
+            let operator = if self.eof() { ' ' } else { self.next_char() };
+            if self.pos == start || !matches!(operator, '+' | '-') || !self.nth_char(1).is_some_and(char::is_whitespace) {
+                self.pos = start;
+                return Ok(node);
+            }
+            self.consume_char();
+            self.consume_whitespace();
+            let right = self.parse_calc_product()?;
+            if node.is_number() != right.is_number() {
+                return Err("Cannot add a number and a dimension in calc()".to_string());
+            }
+            node = match operator {
+                '+' => CalcNode::plus(node, right),
+                _ => CalcNode::minus(node, right),
+            };
+        }
+    }
+
+    /// `<operand> [ [ '*' | '/' ] <operand> ]*`
+    fn parse_calc_product(&mut self) -> Result<CalcNode, String> {
+        let mut node = self.parse_calc_operand()?;
+        loop {
+            let start = self.pos;
+            self.consume_whitespace();
+            let operator = if self.eof() { ' ' } else { self.next_char() };
+            if !matches!(operator, '*' | '/') {
+                self.pos = start;
+                return Ok(node);
+            }
+            self.consume_char();
+            self.consume_whitespace();
+            let right = self.parse_calc_operand()?;
+            node = if operator == '*' {
+                if !node.is_number() && !right.is_number() {
+                    return Err("Cannot multiply two dimensions in calc()".to_string());
+                }
+                CalcNode::times(node, right)
+            } else {
+                if !right.is_number() {
+                    return Err("Cannot divide by a dimension in calc()".to_string());
+                }
+                CalcNode::divided_by(node, right)
+            };
+        }
+    }
+
+    /// A number, dimension, percentage, parenthesized sum or nested math
+    /// function.
+    fn parse_calc_operand(&mut self) -> Result<CalcNode, String> {
+        if self.nth_char(0) == Some('(') {
+            self.consume_char();
+            self.consume_whitespace();
+            let node = self.parse_calc_sum()?;
+            self.consume_whitespace();
+            self.expect_char(')')?;
+            return Ok(node);
+        }
+        if self.starts_number() {
+            return Ok(CalcNode::Leaf(self.parse_numeric()?));
+        }
+        let name = self.parse_identifier().to_ascii_lowercase();
+        if is_math_function(&name) && !self.eof() && self.next_char() == '(' {
+            return self.parse_math_arguments(&name);
+        }
+        Err(format!("Unexpected {:?} in calc()", name))
+    }
+
+    /// Parses a `<number>`, `<percentage>` or `<dimension>`. A number without
+    /// a unit becomes `Value::Number`.
+    fn parse_numeric(&mut self) -> Result<Value, String> {
+        let number = self.parse_float();
+        if self.eof() {
+            return Ok(Value::Number(number));
+        }
+        Ok(match self.next_char() {
+            '%' => {
+                self.consume_char();
+                Value::Length(number, Unit::Percent)
+            }
+            c if valid_identifier_char(c) => Value::Length(number, self.parse_unit()?),
+            _ => Value::Number(number),
+        })
+    }
+
+    /// Checks whether the upcoming input starts a CSS `<number>`: an optional
+    /// sign followed by either a digit or a decimal point and a digit.
+    fn starts_number(&self) -> bool {
+        let mut chars = self.input[self.pos..].chars();
+        let mut c = chars.next();
+        if let Some('+' | '-') = c {
+            c = chars.next();
+        }
+        match c {
+            Some('0'..='9') => true,
+            Some('.') => matches!(chars.next(), Some('0'..='9')),
             _ => false,
-        });
+        }
+    }
+
+    /// Consumes a number following the CSS `<number>` grammar:
+    /// `[+-]? (digits | digits? '.' digits) ([eE] [+-]? digits)?`.
+    fn parse_float(&mut self) -> f32 {
+        let mut s = String::new();
+        if let '+' | '-' = self.next_char() {
+            s.push(self.consume_char());
+        }
+        s.push_str(&self.consume_digits());
+        if self.starts_with(".") && self.nth_char(1).is_some_and(|c| c.is_ascii_digit()) {
+            s.push(self.consume_char());
+            s.push_str(&self.consume_digits());
+        }
+        if let Some('e' | 'E') = self.nth_char(0) {
+            let exponent_start = match self.nth_char(1) {
+                Some('+' | '-') => 2,
+                _ => 1,
+            };
+            if self.nth_char(exponent_start).is_some_and(|c| c.is_ascii_digit()) {
+                for _ in 0..exponent_start {
+                    s.push(self.consume_char());
+                }
+                s.push_str(&self.consume_digits());
+            }
+        }
         s.parse().unwrap()
     }
 
-    fn parse_unit(&mut self) -> Unit {
-        match &*self.parse_identifier().to_ascii_lowercase() {
+    fn consume_digits(&mut self) -> String {
+        self.consume_while(|c| c.is_ascii_digit())
+    }
+
+    fn parse_unit(&mut self) -> Result<Unit, String> {
+        let unit = self.parse_identifier();
+        Ok(match &*unit.to_ascii_lowercase() {
             "px" => Unit::Px,
             "em" => Unit::Em,
             "rem" => Unit::Rem,
-            "%" => Unit::Percent,
-            _ => panic!("Unrecognized unit"),
-        }
+            "vw" => Unit::Vw,
+            "vh" => Unit::Vh,
+            "vmin" => Unit::Vmin,
+            "vmax" => Unit::Vmax,
+            "dpi" => Unit::Dpi,
+            "dpcm" => Unit::Dpcm,
+            "dppx" | "x" => Unit::Dppx,
+            _ => return Err(format!("Unrecognized unit {}", unit)),
+        })
     }
 
-    fn parse_color(&mut self) -> Value {
-        assert_eq!(self.consume_char(), '#');
-        Value::Color(Color {
-            r: self.parse_hex_pair(),
-            g: self.parse_hex_pair(),
-            b: self.parse_hex_pair(),
+    fn parse_color(&mut self) -> Result<Value, String> {
+        self.expect_char('#')?;
+        Ok(Value::Color(Color {
+            r: self.parse_hex_pair()?,
+            g: self.parse_hex_pair()?,
+            b: self.parse_hex_pair()?,
             a: 255,
-        })
+        }))
     }
 
-    fn parse_hex_pair(&mut self) -> u8 {
-        let s = &self.input[self.pos..self.pos + 2];
+    fn parse_hex_pair(&mut self) -> Result<u8, String> {
+        let pair = self.input.get(self.pos..self.pos + 2)
+            .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+            .ok_or_else(|| format!("Expected a #RRGGBB color, found #{}", self.input[self.pos..].trim()))?;
         self.pos += 2;
-        u8::from_str_radix(s, 16).unwrap()
+        Ok(pair)
     }
 
     fn parse_identifier(&mut self) -> String {
@@ -174,10 +1577,43 @@ impl Parser {
         self.input[self.pos..].chars().next().unwrap()
     }
 
+    fn nth_char(&self, n: usize) -> Option<char> {
+        self.input[self.pos..].chars().nth(n)
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        self.input[self.pos..].starts_with(s)
+    }
+
+    /// The location of the current position. Lines are counted from the
+    /// last position asked for, so that parsing stays linear.
+    fn location(&mut self) -> SourceLocation {
+        let (mark, mut line) = self.line_mark;
+        if mark <= self.pos {
+            line += self.input[mark..self.pos].matches('\n').count();
+        } else {
+            line = 1 + self.input[..self.pos].matches('\n').count();
+        }
+        self.line_mark = (self.pos, line);
+        SourceLocation { url: self.base_url.clone(), line }
+    }
+
     fn eof(&self) -> bool {
         self.pos >= self.input.len()
     }
 
+    /// Consumes `expected`, or fails with what was found instead.
+    fn expect_char(&mut self, expected: char) -> Result<(), String> {
+        match self.nth_char(0) {
+            Some(c) if c == expected => {
+                self.consume_char();
+                Ok(())
+            }
+            Some(c) => Err(format!("Expected {} but found {}", expected, c)),
+            None => Err(format!("Expected {} but found the end of input", expected)),
+        }
+    }
+
     fn consume_char(&mut self) -> char {
         let mut iter = self.input[self.pos..].char_indices();
         let (_, cur_char) = iter.next().unwrap();
@@ -187,6 +1623,42 @@ impl Parser {
     }
 }
 
+/// The `&` of a nested rule, matching what its parent's selectors match
+/// with the specificity of the most specific one.
+fn nesting_selector(parent: &[Selector]) -> SimpleSelector {
+    SimpleSelector {
+        pseudo_classes: vec![PseudoClass::Is(parent.to_vec())],
+        ..SimpleSelector::default()
+    }
+}
+
+/// Joins `left` to the leftmost compound of `selector` with `combinator`.
+fn prepend(selector: Selector, combinator: Combinator, left: SimpleSelector) -> Selector {
+    match selector {
+        Selector::Simple(simple) => Selector::Compound(Box::new(Selector::Simple(left)), combinator, simple),
+        Selector::Compound(inner, inner_combinator, right) => {
+            Selector::Compound(Box::new(prepend(*inner, combinator, left)), inner_combinator, right)
+        }
+    }
+}
+
+/// Syntax errors in a stylesheet panic. Value parsing returns them instead,
+/// so that an invalid declaration value, or one built by `var()`
+/// substitution, can be dropped without panicking.
+fn or_panic<T>(result: Result<T, String>) -> T {
+    result.unwrap_or_else(|error| panic!("{}", error))
+}
+
+fn is_math_function(name: &str) -> bool {
+    ["calc", "min", "max", "clamp"].iter().any(|function| name.eq_ignore_ascii_case(function))
+}
+
+/// Whether the source text of a value references a custom property.
+fn contains_var(text: &str) -> bool {
+    let lower = text.to_ascii_lowercase();
+    lower.match_indices("var(").any(|(index, _)| !lower[..index].ends_with(valid_identifier_char))
+}
+
 fn valid_identifier_char(c: char) -> bool {
     c.is_alphanumeric() || c == '-' || c == '_'
 }
\ No newline at end of file
@@ -0,0 +1,544 @@
+use super::stylesheet::*;
+use super::media::*;
+use super::calc::CalcNode;
+use super::supports::SupportsCondition;
+
+/// Output style of `to_css`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CssFormat {
+    /// One declaration per line, two-space indentation.
+    Pretty,
+    /// No whitespace that is not needed to parse the result back.
+    Minified,
+}
+
+impl Stylesheet {
+    /// Serializes the stylesheet so that parsing the result gives back an
+    /// equal `Stylesheet` with author origin.
+    ///
+    /// Imports were inlined when parsing, so their rules are written out in
+    /// place. Named layers are declared up front in their original order,
+    /// and consecutive rules under the same at-rules share one block.
+    /// Anonymous layers have no name to refer back to, so they round-trip
+    /// only when each one's rules are consecutive and it is declared after
+    /// all named layers.
+    pub fn to_css(&self, format: CssFormat) -> String {
+        let mut writer = Writer { format, output: String::new(), depth: 0 };
+
+        let named: Vec<&LayerName> = self.layers.iter()
+            .filter(|layer| layer.0.iter().all(|segment| matches!(segment, LayerSegment::Named(_))))
+            .collect();
+        if !named.is_empty() {
+            let names: Vec<String> = named.iter().map(|layer| layer_name(layer)).collect();
+            writer.line(&format!("@layer {};", names.join(writer.comma())));
+        }
+        for property in &self.properties {
+            writer.open(&format!("@property {}", property.name));
+            writer.line(&format!("syntax{}{};", writer.colon(), quoted(&property.syntax)));
+            writer.line(&format!("inherits{}{};", writer.colon(), property.inherits));
+            if let Some(ref initial) = property.initial_value {
+                writer.line(&format!("initial-value{}{};", writer.colon(), initial));
+            }
+            writer.close();
+        }
+
+        let mut open: Vec<Wrapper> = Vec::new();
+        for rule in &self.rules {
+            let wrappers = wrappers(rule);
+            let shared = open.iter().zip(&wrappers).take_while(|(a, b)| a == b).count();
+            while open.len() > shared {
+                open.pop();
+                writer.close();
+            }
+            for wrapper in &wrappers[shared..] {
+                writer.open(&wrapper.prelude(format));
+                open.push(wrapper.clone());
+            }
+            writer.rule(rule);
+        }
+        for _ in open {
+            writer.close();
+        }
+        writer.output
+    }
+}
+
+/// An at-rule block a rule is written inside.
+#[derive(Debug, Clone, PartialEq)]
+enum Wrapper<'a> {
+    Condition(&'a RuleCondition),
+    /// Consecutive named segments of a layer path, as `@layer a.b`.
+    Layer(Vec<&'a str>),
+    AnonymousLayer(usize),
+}
+
+/// The blocks around a rule: its conditions, outermost first, then its
+/// layer. Anonymous segments split the layer path into nested blocks.
+fn wrappers(rule: &Rule) -> Vec<Wrapper<'_>> {
+    let mut wrappers: Vec<Wrapper> = rule.conditions.iter().map(Wrapper::Condition).collect();
+    let segments = rule.layer.as_ref().map_or(&[][..], |layer| &layer.0[..]);
+    for segment in segments {
+        match (segment, wrappers.last_mut()) {
+            (LayerSegment::Named(name), Some(Wrapper::Layer(names))) => names.push(name),
+            (LayerSegment::Named(name), _) => wrappers.push(Wrapper::Layer(vec![name])),
+            (LayerSegment::Anonymous(n), _) => wrappers.push(Wrapper::AnonymousLayer(*n)),
+        }
+    }
+    wrappers
+}
+
+impl Wrapper<'_> {
+    fn prelude(&self, format: CssFormat) -> String {
+        match *self {
+            Wrapper::Condition(RuleCondition::Media(ref queries)) => format!("@media {}", queries.to_css(format)),
+            Wrapper::Condition(RuleCondition::Supports(ref condition)) => format!("@supports {}", condition.to_css(format)),
+            Wrapper::Layer(ref names) => format!("@layer {}", names.join(".")),
+            Wrapper::AnonymousLayer(_) => "@layer".to_string(),
+        }
+    }
+}
+
+struct Writer {
+    format: CssFormat,
+    output: String,
+    depth: usize,
+}
+
+impl Writer {
+    fn pretty(&self) -> bool {
+        self.format == CssFormat::Pretty
+    }
+
+    fn comma(&self) -> &'static str {
+        if self.pretty() { ", " } else { "," }
+    }
+
+    fn colon(&self) -> &'static str {
+        if self.pretty() { ": " } else { ":" }
+    }
+
+    fn line(&mut self, text: &str) {
+        if self.pretty() {
+            self.output.push_str(&"  ".repeat(self.depth));
+            self.output.push_str(text);
+            self.output.push('\n');
+        } else {
+            self.output.push_str(text);
+        }
+    }
+
+    /// Starts a block. Pretty output separates top-level blocks with a
+    /// blank line.
+    fn open(&mut self, prelude: &str) {
+        if self.pretty() && self.depth == 0 && !self.output.is_empty() {
+            self.output.push('\n');
+        }
+        let brace = if self.pretty() { " {" } else { "{" };
+        self.line(&format!("{}{}", prelude, brace));
+        self.depth += 1;
+    }
+
+    fn close(&mut self) {
+        self.depth -= 1;
+        if !self.pretty() && self.output.ends_with(';') {
+            self.output.pop();
+        }
+        self.line("}");
+    }
+
+    fn rule(&mut self, rule: &Rule) {
+        let selectors: Vec<String> = rule.selectors.iter().map(|s| s.to_css(self.format)).collect();
+        self.open(&selectors.join(self.comma()));
+        for declaration in &rule.declarations {
+            let text = declaration.to_css(self.format);
+            self.line(&text);
+        }
+        self.close();
+    }
+}
+
+impl Declaration {
+    pub fn to_css(&self, format: CssFormat) -> String {
+        let (colon, important) = match format {
+            CssFormat::Pretty => (": ", " !important"),
+            CssFormat::Minified => (":", "!important"),
+        };
+        format!(
+            "{}{}{}{};",
+            self.name,
+            colon,
+            self.value.to_css(format),
+            if self.important { important } else { "" }
+        )
+    }
+}
+
+impl Value {
+    pub fn to_css(&self, format: CssFormat) -> String {
+        match *self {
+            Value::Keyword(ref keyword) => keyword.clone(),
+            Value::Length(n, ref unit) => format!("{}{}", n, unit.to_css()),
+            Value::Number(n) => n.to_string(),
+            Value::Color(ref color) => format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b),
+            Value::String(ref s) => quoted(s),
+            Value::Url(ref url) => format!("url({})", quoted(url)),
+            Value::Function(ref name, ref arguments) => {
+                let separator = if format == CssFormat::Pretty { ", " } else { "," };
+                let arguments: Vec<String> = arguments.iter().map(|argument| argument.to_css(format)).collect();
+                format!("{}({})", name, arguments.join(separator))
+            }
+            Value::List(ref items) => {
+                let items: Vec<String> = items.iter().map(|item| item.to_css(format)).collect();
+                items.join(" ")
+            }
+            Value::Calc(ref node) => match **node {
+                CalcNode::Min(_) | CalcNode::Max(_) | CalcNode::Clamp(..) => node.to_css(format),
+                _ => format!("calc({})", node.to_css(format)),
+            },
+            Value::Tokens(ref tokens) => tokens.clone(),
+        }
+    }
+}
+
+impl Unit {
+    pub fn to_css(&self) -> &'static str {
+        match *self {
+            Unit::Px => "px",
+            Unit::Em => "em",
+            Unit::Rem => "rem",
+            Unit::Percent => "%",
+            Unit::Vw => "vw",
+            Unit::Vh => "vh",
+            Unit::Vmin => "vmin",
+            Unit::Vmax => "vmax",
+            Unit::Dpi => "dpi",
+            Unit::Dpcm => "dpcm",
+            Unit::Dppx => "dppx",
+        }
+    }
+}
+
+impl CalcNode {
+    /// The expression without a surrounding `calc()`, parenthesized where
+    /// needed to parse back into the same tree.
+    pub fn to_css(&self, format: CssFormat) -> String {
+        let pretty = format == CssFormat::Pretty;
+        let operand = |node: &CalcNode, wrap: bool| {
+            if wrap { format!("({})", node.to_css(format)) } else { node.to_css(format) }
+        };
+        let list = |name: &str, arguments: &[&CalcNode]| {
+            let arguments: Vec<String> = arguments.iter().map(|argument| argument.to_css(format)).collect();
+            format!("{}({})", name, arguments.join(if pretty { ", " } else { "," }))
+        };
+        let product_operator = |operator: &str| if pretty { format!(" {} ", operator) } else { operator.to_string() };
+        match *self {
+            CalcNode::Leaf(ref value) => value.to_css(format),
+            CalcNode::Add(ref left, ref right) => format!("{} + {}", left.to_css(format), operand(right, right.is_sum())),
+            CalcNode::Sub(ref left, ref right) => format!("{} - {}", left.to_css(format), operand(right, right.is_sum())),
+            CalcNode::Mul(ref left, ref right) => format!(
+                "{}{}{}",
+                operand(left, left.is_sum()),
+                product_operator("*"),
+                operand(right, right.is_sum() || right.is_product())
+            ),
+            CalcNode::Div(ref left, ref right) => format!(
+                "{}{}{}",
+                operand(left, left.is_sum()),
+                product_operator("/"),
+                operand(right, right.is_sum() || right.is_product())
+            ),
+            CalcNode::Min(ref arguments) => list("min", &arguments.iter().collect::<Vec<_>>()),
+            CalcNode::Max(ref arguments) => list("max", &arguments.iter().collect::<Vec<_>>()),
+            CalcNode::Clamp(ref min, ref value, ref max) => list("clamp", &[min, value, max]),
+        }
+    }
+
+    fn is_sum(&self) -> bool {
+        matches!(self, CalcNode::Add(..) | CalcNode::Sub(..))
+    }
+
+    fn is_product(&self) -> bool {
+        matches!(self, CalcNode::Mul(..) | CalcNode::Div(..))
+    }
+}
+
+impl Selector {
+    pub fn to_css(&self, format: CssFormat) -> String {
+        match *self {
+            Selector::Simple(ref simple) => simple.to_css(format),
+            Selector::Compound(ref left, combinator, ref right) => {
+                let combinator = match (combinator, format) {
+                    (Combinator::Descendant, _) => " ",
+                    (Combinator::Child, CssFormat::Pretty) => " > ",
+                    (Combinator::NextSibling, CssFormat::Pretty) => " + ",
+                    (Combinator::SubsequentSibling, CssFormat::Pretty) => " ~ ",
+                    (Combinator::Child, CssFormat::Minified) => ">",
+                    (Combinator::NextSibling, CssFormat::Minified) => "+",
+                    (Combinator::SubsequentSibling, CssFormat::Minified) => "~",
+                };
+                format!("{}{}{}", left.to_css(format), combinator, right.to_css(format))
+            }
+        }
+    }
+}
+
+impl SimpleSelector {
+    pub fn to_css(&self, format: CssFormat) -> String {
+        let mut css = self.tag_name.clone().unwrap_or_default();
+        if let Some(ref id) = self.id {
+            css.push('#');
+            css.push_str(id);
+        }
+        for class in &self.classes {
+            css.push('.');
+            css.push_str(class);
+        }
+        for attribute in &self.attributes {
+            css.push_str(&attribute.to_css());
+        }
+        for pseudo_class in &self.pseudo_classes {
+            css.push_str(&pseudo_class.to_css(format));
+        }
+        if css.is_empty() {
+            css.push('*');
+        }
+        if let Some(pseudo_element) = self.pseudo_element {
+            css.push_str(match pseudo_element {
+                PseudoElement::Before => "::before",
+                PseudoElement::After => "::after",
+                PseudoElement::Marker => "::marker",
+            });
+        }
+        css
+    }
+}
+
+impl AttributeSelector {
+    pub fn to_css(&self) -> String {
+        let Some(ref matcher) = self.matcher else {
+            return format!("[{}]", self.name);
+        };
+        let operator = match matcher.operator {
+            AttributeOperator::Equals => "=",
+            AttributeOperator::Includes => "~=",
+            AttributeOperator::DashMatch => "|=",
+            AttributeOperator::Prefix => "^=",
+            AttributeOperator::Suffix => "$=",
+            AttributeOperator::Substring => "*=",
+        };
+        let case = match matcher.case {
+            AttributeCase::Default => "",
+            AttributeCase::Insensitive => " i",
+            AttributeCase::Sensitive => " s",
+        };
+        format!("[{}{}{}{}]", self.name, operator, quoted(&matcher.value), case)
+    }
+}
+
+impl PseudoClass {
+    pub fn to_css(&self, format: CssFormat) -> String {
+        let list = |selectors: &[Selector]| {
+            let selectors: Vec<String> = selectors.iter().map(|s| s.to_css(format)).collect();
+            selectors.join(if format == CssFormat::Pretty { ", " } else { "," })
+        };
+        let nth_of = |name: &str, nth: &Nth, of: &[Selector]| {
+            if of.is_empty() {
+                format!(":{}({})", name, nth.to_css())
+            } else {
+                format!(":{}({} of {})", name, nth.to_css(), list(of))
+            }
+        };
+        match *self {
+            PseudoClass::Root => ":root".to_string(),
+            PseudoClass::Empty => ":empty".to_string(),
+            PseudoClass::FirstChild => ":first-child".to_string(),
+            PseudoClass::LastChild => ":last-child".to_string(),
+            PseudoClass::OnlyChild => ":only-child".to_string(),
+            PseudoClass::FirstOfType => ":first-of-type".to_string(),
+            PseudoClass::LastOfType => ":last-of-type".to_string(),
+            PseudoClass::OnlyOfType => ":only-of-type".to_string(),
+            PseudoClass::Hover => ":hover".to_string(),
+            PseudoClass::Active => ":active".to_string(),
+            PseudoClass::Focus => ":focus".to_string(),
+            PseudoClass::FocusWithin => ":focus-within".to_string(),
+            PseudoClass::FocusVisible => ":focus-visible".to_string(),
+            PseudoClass::Checked => ":checked".to_string(),
+            PseudoClass::Disabled => ":disabled".to_string(),
+            PseudoClass::Enabled => ":enabled".to_string(),
+            PseudoClass::Target => ":target".to_string(),
+            PseudoClass::Link => ":link".to_string(),
+            PseudoClass::Visited => ":visited".to_string(),
+            PseudoClass::NthChild(ref nth, ref of) => nth_of("nth-child", nth, of),
+            PseudoClass::NthLastChild(ref nth, ref of) => nth_of("nth-last-child", nth, of),
+            PseudoClass::NthOfType(ref nth) => format!(":nth-of-type({})", nth.to_css()),
+            PseudoClass::NthLastOfType(ref nth) => format!(":nth-last-of-type({})", nth.to_css()),
+            PseudoClass::Not(ref selectors) => format!(":not({})", list(selectors)),
+            PseudoClass::Is(ref selectors) => format!(":is({})", list(selectors)),
+            PseudoClass::Where(ref selectors) => format!(":where({})", list(selectors)),
+            PseudoClass::Has(ref relative) => {
+                let selectors: Vec<String> = relative.iter()
+                    .map(|r| {
+                        let combinator = match r.combinator {
+                            Combinator::Descendant => "",
+                            Combinator::Child => "> ",
+                            Combinator::NextSibling => "+ ",
+                            Combinator::SubsequentSibling => "~ ",
+                        };
+                        format!("{}{}", combinator, r.selector.to_css(format))
+                    })
+                    .collect();
+                format!(":has({})", selectors.join(if format == CssFormat::Pretty { ", " } else { "," }))
+            }
+        }
+    }
+}
+
+impl Nth {
+    /// `An+B` in its shortest form: `2n+1`, `-n+3`, `n`, `4`.
+    pub fn to_css(&self) -> String {
+        let a = match self.a {
+            0 => return self.b.to_string(),
+            1 => "n".to_string(),
+            -1 => "-n".to_string(),
+            a => format!("{}n", a),
+        };
+        match self.b {
+            0 => a,
+            b if b > 0 => format!("{}+{}", a, b),
+            b => format!("{}{}", a, b),
+        }
+    }
+}
+
+impl MediaQueryList {
+    pub fn to_css(&self, format: CssFormat) -> String {
+        let queries: Vec<String> = self.queries.iter().map(|query| query.to_css(format)).collect();
+        queries.join(if format == CssFormat::Pretty { ", " } else { "," })
+    }
+}
+
+impl MediaQuery {
+    pub fn to_css(&self, format: CssFormat) -> String {
+        let Some(ref media_type) = self.media_type else {
+            return self.condition.as_ref().map_or(String::new(), |condition| condition.to_css(format));
+        };
+        let mut css = match self.qualifier {
+            Some(MediaQualifier::Not) => format!("not {}", media_type),
+            Some(MediaQualifier::Only) => format!("only {}", media_type),
+            None => media_type.clone(),
+        };
+        if let Some(ref condition) = self.condition {
+            // `or` is not allowed directly after a media type.
+            let condition = match condition {
+                MediaCondition::Or(_) => format!("({})", condition.to_css(format)),
+                _ => condition.to_css(format),
+            };
+            css.push_str(" and ");
+            css.push_str(&condition);
+        }
+        css
+    }
+}
+
+impl MediaCondition {
+    pub fn to_css(&self, format: CssFormat) -> String {
+        let joined = |conditions: &[MediaCondition], operator: &str| {
+            let conditions: Vec<String> = conditions.iter().map(|c| c.to_css_in_parens(format)).collect();
+            conditions.join(operator)
+        };
+        match *self {
+            MediaCondition::Feature(ref feature) => feature.to_css(format),
+            MediaCondition::Not(ref condition) => format!("not {}", condition.to_css_in_parens(format)),
+            MediaCondition::And(ref conditions) => joined(conditions, " and "),
+            MediaCondition::Or(ref conditions) => joined(conditions, " or "),
+        }
+    }
+
+    fn to_css_in_parens(&self, format: CssFormat) -> String {
+        match *self {
+            MediaCondition::Feature(ref feature) => feature.to_css(format),
+            _ => format!("({})", self.to_css(format)),
+        }
+    }
+}
+
+impl MediaFeature {
+    pub fn to_css(&self, format: CssFormat) -> String {
+        let pretty = format == CssFormat::Pretty;
+        match *self {
+            MediaFeature::Boolean(ref name) => format!("({})", name),
+            MediaFeature::Plain(ref name, ref value) => {
+                format!("({}{}{})", name, if pretty { ": " } else { ":" }, value.to_css(format))
+            }
+            MediaFeature::Range(ref name, ref comparisons) => match comparisons.as_slice() {
+                [(low_comparison, low), (high_comparison, high)] => format!(
+                    "({} {} {} {} {})",
+                    low.to_css(format),
+                    low_comparison.flipped().to_css(),
+                    name,
+                    high_comparison.to_css(),
+                    high.to_css(format)
+                ),
+                comparisons => {
+                    let tests: Vec<String> = comparisons.iter()
+                        .map(|(comparison, value)| format!("{} {} {}", name, comparison.to_css(), value.to_css(format)))
+                        .collect();
+                    format!("({})", tests.join(") and ("))
+                }
+            },
+        }
+    }
+}
+
+impl MediaComparison {
+    pub fn to_css(self) -> &'static str {
+        match self {
+            MediaComparison::Lt => "<",
+            MediaComparison::Le => "<=",
+            MediaComparison::Eq => "=",
+            MediaComparison::Ge => ">=",
+            MediaComparison::Gt => ">",
+        }
+    }
+}
+
+impl SupportsCondition {
+    pub fn to_css(&self, format: CssFormat) -> String {
+        let joined = |conditions: &[SupportsCondition], operator: &str| {
+            let conditions: Vec<String> = conditions.iter().map(|c| c.to_css_in_parens(format)).collect();
+            conditions.join(operator)
+        };
+        match *self {
+            SupportsCondition::Declaration(..) | SupportsCondition::Unknown(_) => self.to_css_in_parens(format),
+            SupportsCondition::Not(ref condition) => format!("not {}", condition.to_css_in_parens(format)),
+            SupportsCondition::And(ref conditions) => joined(conditions, " and "),
+            SupportsCondition::Or(ref conditions) => joined(conditions, " or "),
+        }
+    }
+
+    fn to_css_in_parens(&self, format: CssFormat) -> String {
+        match *self {
+            SupportsCondition::Declaration(ref name, ref value) => {
+                let colon = if format == CssFormat::Pretty { ": " } else { ":" };
+                format!("({}{}{})", name, colon, value.to_css(format))
+            }
+            SupportsCondition::Unknown(ref text) => text.clone(),
+            _ => format!("({})", self.to_css(format)),
+        }
+    }
+}
+
+fn layer_name(layer: &LayerName) -> String {
+    let segments: Vec<&str> = layer.0.iter()
+        .map(|segment| match segment {
+            LayerSegment::Named(name) => name.as_str(),
+            LayerSegment::Anonymous(_) => "",
+        })
+        .collect();
+    segments.join(".")
+}
+
+/// A double-quoted CSS string, escaping quotes and backslashes.
+fn quoted(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
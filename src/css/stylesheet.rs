@@ -1,39 +1,248 @@
-use std::collections::HashMap;
+use super::media::{MediaEnvironment, MediaQueryList};
+use super::calc::CalcNode;
+use super::supports::SupportsCondition;
+use url::Url;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Stylesheet {
     pub rules: Vec<Rule>,
+    pub origin: Origin,
+    /// Custom properties registered with `@property`, in source order.
+    pub properties: Vec<PropertyRegistration>,
+    /// Cascade layers in the order they are first declared, by full name.
+    /// A layer's parent always comes before it.
+    pub layers: Vec<LayerName>,
+}
+
+/// Where a stylesheet comes from, which decides its place in the cascade.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Origin {
+    UserAgent,
+    User,
+    Author,
 }
 
 #[derive(Debug, Clone)]
 pub struct Rule {
     pub selectors: Vec<Selector>,
     pub declarations: Vec<Declaration>,
+    /// Conditions of the at-rules the rule is nested in, outermost first. The
+    /// rule only applies when all of them hold.
+    pub conditions: Vec<RuleCondition>,
+    /// The cascade layer the rule belongs to, if any.
+    pub layer: Option<LayerName>,
+    /// Where the rule's selector was written.
+    pub location: SourceLocation,
+}
+
+/// Rules are equal when they say the same thing, wherever they were
+/// written, so that a stylesheet parsed again from its serialization
+/// compares equal to the original.
+impl PartialEq for Rule {
+    fn eq(&self, other: &Rule) -> bool {
+        self.selectors == other.selectors
+            && self.declarations == other.declarations
+            && self.conditions == other.conditions
+            && self.layer == other.layer
+    }
+}
+
+/// A position in a stylesheet, for telling where a rule came from. Rules
+/// inlined from `@import`s keep the URL of the file they were written in.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SourceLocation {
+    /// The stylesheet's URL, or `None` if it was not loaded from one.
+    pub url: Option<Url>,
+    /// 1-based.
+    pub line: usize,
+}
+
+/// A cascade layer name such as `framework.base`, one segment per dot.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LayerName(pub Vec<LayerSegment>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LayerSegment {
+    Named(String),
+    /// A layer without a name, e.g. from `@import url(x.css) layer;`. Each
+    /// one is distinct, so they are numbered in order of appearance.
+    Anonymous(usize),
+}
+
+/// `@property --name { syntax: ...; inherits: ...; initial-value: ...; }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PropertyRegistration {
+    pub name: String,
+    /// The syntax string, e.g. `<color>` or `<length> | auto`; `*` accepts
+    /// any value.
+    pub syntax: String,
+    pub inherits: bool,
+    /// Tokens of the initial value, used when the property is not set or
+    /// its value is invalid.
+    pub initial_value: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleCondition {
+    Media(MediaQueryList),
+    Supports(SupportsCondition),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Selector {
     Simple(SimpleSelector),
+    /// `left combinator right`, where `right` is the part matched against the
+    /// element itself. Longer chains nest to the left, so `a b > c` is
+    /// `Compound(Compound(a, Descendant, b), Child, c)`.
+    Compound(Box<Selector>, Combinator, SimpleSelector),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Combinator {
+    /// `a b`
+    Descendant,
+    /// `a > b`
+    Child,
+    /// `a + b`
+    NextSibling,
+    /// `a ~ b`
+    SubsequentSibling,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct SimpleSelector {
     pub tag_name: Option<String>,
     pub id: Option<String>,
     pub classes: Vec<String>,
+    pub attributes: Vec<AttributeSelector>,
+    pub pseudo_classes: Vec<PseudoClass>,
+    /// Only allowed on the rightmost simple selector of a selector.
+    pub pseudo_element: Option<PseudoElement>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PseudoElement {
+    Before,
+    After,
+    /// The marker box of a `display: list-item` element.
+    Marker,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PseudoClass {
+    Root,
+    Empty,
+    FirstChild,
+    LastChild,
+    OnlyChild,
+    FirstOfType,
+    LastOfType,
+    OnlyOfType,
+    /// `:nth-child(An+B of S)`; the selector list is empty without `of S`.
+    NthChild(Nth, Vec<Selector>),
+    NthLastChild(Nth, Vec<Selector>),
+    NthOfType(Nth),
+    NthLastOfType(Nth),
+    Not(Vec<Selector>),
+    Is(Vec<Selector>),
+    Where(Vec<Selector>),
+    Has(Vec<RelativeSelector>),
+    /// The dynamic pseudo-classes, which match the interaction state the
+    /// embedder gives elements and, for `:checked`, `:disabled`,
+    /// `:enabled`, `:link` and `:visited`, their attributes.
+    Hover,
+    Active,
+    Focus,
+    FocusWithin,
+    FocusVisible,
+    Checked,
+    Disabled,
+    Enabled,
+    Target,
+    Link,
+    Visited,
+}
+
+/// The `An+B` microsyntax: matches the 1-based positions `A*n + B` for
+/// some `n >= 0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Nth {
+    pub a: i32,
+    pub b: i32,
+}
+
+/// An argument of `:has()`, such as `> img`. The combinator relates the
+/// leftmost part of `selector` to the element `:has()` is tested on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelativeSelector {
+    pub combinator: Combinator,
+    pub selector: Selector,
+}
+
+/// `[name]`, or `[name op "value" flag]` when `matcher` is present.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeSelector {
+    pub name: String,
+    pub matcher: Option<AttributeMatcher>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeMatcher {
+    pub operator: AttributeOperator,
+    pub value: String,
+    pub case: AttributeCase,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeOperator {
+    /// `=`
+    Equals,
+    /// `~=`: one of the whitespace-separated words equals the value.
+    Includes,
+    /// `|=`: equals the value or starts with the value followed by `-`.
+    DashMatch,
+    /// `^=`
+    Prefix,
+    /// `$=`
+    Suffix,
+    /// `*=`
+    Substring,
+}
+
+/// The `i` and `s` flags. Without a flag, HTML decides per attribute name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeCase {
+    Default,
+    Insensitive,
+    Sensitive,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Declaration {
     pub name: String,
     pub value: Value,
+    pub important: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Keyword(String),
     Length(f32, Unit),
+    Number(f32),
     Color(Color),
+    String(String),
+    Url(String),
+    /// A functional notation such as `attr(title)` or `counter(item, upper-roman)`,
+    /// with one value per comma-separated argument.
+    Function(String, Vec<Value>),
+    /// Space-separated component values, e.g. `"(" attr(title) ")"`.
+    List(Vec<Value>),
+    /// A `calc()`, `min()`, `max()` or `clamp()` expression that could not
+    /// be simplified to a single value at parse time.
+    Calc(Box<CalcNode>),
+    /// Unparsed source text: the value of a custom property, or a value
+    /// containing `var()` that is only parsed once variables are substituted.
+    Tokens(String),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -42,9 +251,17 @@ pub enum Unit {
     Em,
     Rem,
     Percent,
+    Vw,
+    Vh,
+    Vmin,
+    Vmax,
+    Dpi,
+    Dpcm,
+    /// Also written `x`.
+    Dppx,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -52,19 +269,127 @@ pub struct Color {
     pub a: u8,
 }
 
+impl Color {
+    pub const BLACK: Color = Color { r: 0, g: 0, b: 0, a: 255 };
+    pub const WHITE: Color = Color { r: 255, g: 255, b: 255, a: 255 };
+    pub const TRANSPARENT: Color = Color { r: 0, g: 0, b: 0, a: 0 };
+
+    /// A named color. Only a handful of names are known.
+    pub fn from_keyword(name: &str) -> Option<Color> {
+        let (r, g, b) = match name.to_ascii_lowercase().as_str() {
+            "transparent" => return Some(Color::TRANSPARENT),
+            "white" => (255, 255, 255),
+            "black" => (0, 0, 0),
+            "red" => (255, 0, 0),
+            "green" => (0, 255, 0),
+            "blue" => (0, 0, 255),
+            "yellow" => (255, 255, 0),
+            "gray" | "grey" => (128, 128, 128),
+            "lightgray" | "lightgrey" => (211, 211, 211),
+            "darkgray" | "darkgrey" => (169, 169, 169),
+            _ => return None,
+        };
+        Some(Color { r, g, b, a: 255 })
+    }
+}
+
 impl Stylesheet {
     pub fn new(rules: Vec<Rule>) -> Stylesheet {
-        Stylesheet { rules }
+        Stylesheet {
+            rules,
+            origin: Origin::Author,
+            properties: Vec::new(),
+            layers: Vec::new(),
+        }
+    }
+
+    pub fn with_origin(mut self, origin: Origin) -> Stylesheet {
+        self.origin = origin;
+        self
+    }
+}
+
+impl Rule {
+    pub fn applies_in(&self, env: &MediaEnvironment) -> bool {
+        self.conditions.iter().all(|condition| condition.matches(env))
+    }
+}
+
+impl LayerName {
+    /// `self` as a sublayer path inside `parent`.
+    pub fn nested_in(&self, parent: &LayerName) -> LayerName {
+        LayerName(parent.0.iter().chain(&self.0).cloned().collect())
+    }
+}
+
+impl RuleCondition {
+    pub fn matches(&self, env: &MediaEnvironment) -> bool {
+        match *self {
+            RuleCondition::Media(ref queries) => queries.matches(env),
+            RuleCondition::Supports(ref condition) => condition.matches(),
+        }
     }
 }
 
 impl Selector {
+    /// The pseudo-element this selector targets, if any.
+    pub fn pseudo_element(&self) -> Option<PseudoElement> {
+        match *self {
+            Selector::Simple(ref simple) | Selector::Compound(_, _, ref simple) => simple.pseudo_element,
+        }
+    }
+
     pub fn specificity(&self) -> (usize, usize, usize) {
-        let Selector::Simple(ref simple) = *self;
-        let id = simple.id.iter().count();
-        let classes = simple.classes.len();
-        let tag = simple.tag_name.iter().count();
-        (id, classes, tag)
+        match *self {
+            Selector::Simple(ref simple) => simple.specificity(),
+            Selector::Compound(ref left, _, ref right) => {
+                let (a1, b1, c1) = left.specificity();
+                let (a2, b2, c2) = right.specificity();
+                (a1 + a2, b1 + b2, c1 + c2)
+            }
+        }
+    }
+}
+
+impl SimpleSelector {
+    pub fn specificity(&self) -> (usize, usize, usize) {
+        let id = self.id.iter().count();
+        let classes = self.classes.len() + self.attributes.len();
+        let tag = self.tag_name.iter().count() + self.pseudo_element.iter().count();
+        self.pseudo_classes.iter()
+            .map(PseudoClass::specificity)
+            .fold((id, classes, tag), |(a1, b1, c1), (a2, b2, c2)| (a1 + a2, b1 + b2, c1 + c2))
+    }
+}
+
+impl PseudoClass {
+    /// `:where()` contributes nothing, `:is()`, `:not()` and `:has()` take
+    /// their most specific argument, and `:nth-child(An+B of S)` counts as a
+    /// pseudo-class plus its most specific `S`.
+    pub fn specificity(&self) -> (usize, usize, usize) {
+        fn max_of<'a>(selectors: impl Iterator<Item = &'a Selector>) -> (usize, usize, usize) {
+            selectors.map(Selector::specificity).max().unwrap_or((0, 0, 0))
+        }
+        match *self {
+            PseudoClass::Where(_) => (0, 0, 0),
+            PseudoClass::Is(ref selectors) | PseudoClass::Not(ref selectors) => max_of(selectors.iter()),
+            PseudoClass::Has(ref relative) => max_of(relative.iter().map(|r| &r.selector)),
+            PseudoClass::NthChild(_, ref selectors) | PseudoClass::NthLastChild(_, ref selectors) => {
+                let (a, b, c) = max_of(selectors.iter());
+                (a, b + 1, c)
+            }
+            _ => (0, 1, 0),
+        }
+    }
+}
+
+impl Nth {
+    pub fn matches(&self, position: i32) -> bool {
+        if self.a == 0 {
+            return position == self.b;
+        }
+        let offset = position - self.b;
+        offset % self.a == 0 && offset / self.a >= 0
     }
 }
 
@@ -0,0 +1,127 @@
+use super::stylesheet::{Color, Unit, Value};
+use crate::style::properties::CssWideKeyword;
+
+/// The condition of an `@supports` rule or an `@import ... supports(...)`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SupportsCondition {
+    /// `(display: grid)`
+    Declaration(String, Value),
+    Not(Box<SupportsCondition>),
+    And(Vec<SupportsCondition>),
+    Or(Vec<SupportsCondition>),
+    /// A function or parenthesized expression this engine does not know,
+    /// such as `selector(...)`, kept as source text. It is never supported.
+    Unknown(String),
+}
+
+impl SupportsCondition {
+    pub fn matches(&self) -> bool {
+        match *self {
+            SupportsCondition::Declaration(ref name, ref value) => supports_declaration(name, value),
+            SupportsCondition::Not(ref condition) => !condition.matches(),
+            SupportsCondition::And(ref conditions) => conditions.iter().all(SupportsCondition::matches),
+            SupportsCondition::Or(ref conditions) => conditions.iter().any(SupportsCondition::matches),
+            SupportsCondition::Unknown(_) => false,
+        }
+    }
+}
+
+/// Values accepted by a property the engine implements.
+#[derive(Clone, Copy)]
+enum PropertyValues {
+    /// One of the listed keywords.
+    Keywords(&'static [&'static str]),
+    /// A length, percentage or math function, or one of the listed keywords.
+    Length(&'static [&'static str]),
+    /// Up to four lengths, as in `margin: 10px auto`.
+    Lengths(&'static [&'static str]),
+    Color,
+    /// Any value; the property interprets what it understands.
+    Any,
+}
+
+const LIST_STYLE_TYPES: &[&str] = &[
+    "none", "disc", "circle", "square", "decimal", "decimal-leading-zero", "lower-roman", "upper-roman",
+    "lower-alpha", "upper-alpha", "lower-latin", "upper-latin", "lower-greek",
+];
+
+/// The properties layout and rendering read.
+const PROPERTIES: &[(&str, PropertyValues)] = &[
+    ("display", PropertyValues::Keywords(&["block", "inline", "inline-block", "list-item", "none"])),
+    ("width", PropertyValues::Length(&["auto"])),
+    ("height", PropertyValues::Length(&["auto"])),
+    ("margin", PropertyValues::Lengths(&["auto"])),
+    ("margin-top", PropertyValues::Length(&["auto"])),
+    ("margin-right", PropertyValues::Length(&["auto"])),
+    ("margin-bottom", PropertyValues::Length(&["auto"])),
+    ("margin-left", PropertyValues::Length(&["auto"])),
+    ("padding", PropertyValues::Lengths(&[])),
+    ("padding-top", PropertyValues::Length(&[])),
+    ("padding-right", PropertyValues::Length(&[])),
+    ("padding-bottom", PropertyValues::Length(&[])),
+    ("padding-left", PropertyValues::Length(&[])),
+    ("border-width", PropertyValues::Lengths(&[])),
+    ("border-top-width", PropertyValues::Length(&[])),
+    ("border-right-width", PropertyValues::Length(&[])),
+    ("border-bottom-width", PropertyValues::Length(&[])),
+    ("border-left-width", PropertyValues::Length(&[])),
+    ("border-color", PropertyValues::Color),
+    ("background-color", PropertyValues::Color),
+    ("background", PropertyValues::Color),
+    ("color", PropertyValues::Color),
+    ("font-size", PropertyValues::Length(&[])),
+    ("content", PropertyValues::Any),
+    ("quotes", PropertyValues::Any),
+    ("counter-reset", PropertyValues::Any),
+    ("counter-increment", PropertyValues::Any),
+    ("counter-set", PropertyValues::Any),
+    ("list-style-type", PropertyValues::Keywords(LIST_STYLE_TYPES)),
+];
+
+/// Whether the engine implements `name: value`. Custom properties accept
+/// anything, every property accepts the CSS-wide keywords, and values with
+/// `var()` are assumed valid.
+pub fn supports_declaration(name: &str, value: &Value) -> bool {
+    if name.starts_with("--") {
+        return true;
+    }
+    let Some(&(_, values)) = PROPERTIES.iter().find(|(property, _)| *property == name) else {
+        return false;
+    };
+    if let Value::Tokens(_) = *value {
+        return true;
+    }
+    if CssWideKeyword::of(value).is_some() {
+        return true;
+    }
+    match values {
+        PropertyValues::Keywords(keywords) => is_keyword(value, keywords),
+        PropertyValues::Length(keywords) => is_length(value) || is_keyword(value, keywords),
+        PropertyValues::Lengths(keywords) => {
+            let items = match value {
+                Value::List(items) => items.as_slice(),
+                value => std::slice::from_ref(value),
+            };
+            items.len() <= 4 && items.iter().all(|item| is_length(item) || is_keyword(item, keywords))
+        }
+        PropertyValues::Color => match value {
+            Value::Color(_) => true,
+            Value::Keyword(keyword) => keyword.eq_ignore_ascii_case("currentcolor") || Color::from_keyword(keyword).is_some(),
+            _ => false,
+        },
+        PropertyValues::Any => true,
+    }
+}
+
+fn is_keyword(value: &Value, keywords: &[&str]) -> bool {
+    matches!(value, Value::Keyword(keyword) if keywords.contains(&keyword.as_str()))
+}
+
+fn is_length(value: &Value) -> bool {
+    match *value {
+        Value::Length(_, ref unit) => !matches!(unit, Unit::Dpi | Unit::Dpcm | Unit::Dppx),
+        Value::Number(n) => n == 0.0,
+        Value::Calc(ref node) => !node.is_number(),
+        _ => false,
+    }
+}
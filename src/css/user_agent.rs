@@ -0,0 +1,16 @@
+use super::stylesheet::{Origin, Stylesheet};
+use super::parser::Parser;
+
+/// The default stylesheet, after the rendering section of the HTML
+/// standard: display types, body and block margins, headings, lists and
+/// phrasing elements. Box shorthands are used throughout so that an author
+/// `margin` or `padding` replaces all four sides.
+pub const USER_AGENT_CSS: &str = include_str!("user_agent.css");
+
+/// The default stylesheet, parsed, with user-agent origin.
+pub fn user_agent_stylesheet() -> Stylesheet {
+    eprintln!("  [CSS] Loading user-agent stylesheet...");
+    Parser::new(USER_AGENT_CSS.to_string())
+        .parse_stylesheet()
+        .with_origin(Origin::UserAgent)
+}
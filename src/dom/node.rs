@@ -17,6 +17,53 @@ pub enum NodeType {
 pub struct ElementData {
     pub tag_name: String,
     pub attributes: HashMap<String, String>,
+    /// Interaction state for the dynamic pseudo-classes. Parsed documents
+    /// start without any; embedders simulate it with `add_element_state`.
+    pub state: ElementState,
+}
+
+/// Flags for the interaction state of an element, such as being hovered or
+/// focused, which `:hover`, `:focus` and the like match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ElementState(u16);
+
+impl ElementState {
+    pub const HOVER: ElementState = ElementState(1 << 0);
+    pub const ACTIVE: ElementState = ElementState(1 << 1);
+    pub const FOCUS: ElementState = ElementState(1 << 2);
+    /// The element or one of its descendants has focus.
+    pub const FOCUS_WITHIN: ElementState = ElementState(1 << 3);
+    /// The focus should be shown, as after moving it with the keyboard.
+    pub const FOCUS_VISIBLE: ElementState = ElementState(1 << 4);
+    pub const VISITED: ElementState = ElementState(1 << 5);
+    pub const CHECKED: ElementState = ElementState(1 << 6);
+    pub const DISABLED: ElementState = ElementState(1 << 7);
+    /// The element is the target of the document URL's fragment.
+    pub const TARGET: ElementState = ElementState(1 << 8);
+
+    pub fn empty() -> ElementState {
+        ElementState(0)
+    }
+
+    pub fn contains(self, other: ElementState) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: ElementState) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: ElementState) {
+        self.0 &= !other.0;
+    }
+}
+
+impl std::ops::BitOr for ElementState {
+    type Output = ElementState;
+
+    fn bitor(self, other: ElementState) -> ElementState {
+        ElementState(self.0 | other.0)
+    }
 }
 
 impl Node {
@@ -32,6 +79,7 @@ impl Node {
             node_type: NodeType::Element(ElementData {
                 tag_name: name,
                 attributes: attrs,
+                state: ElementState::empty(),
             }),
             children,
         }
@@ -43,6 +91,62 @@ impl Node {
             children: Vec::new(),
         }
     }
+
+    /// Adds `state` to the element reached by following the child indices
+    /// in `path`. As in a browser, hovering or activating an element also
+    /// hovers or activates its ancestors, and focusing it puts its
+    /// ancestors in `FOCUS_WITHIN`.
+    pub fn add_element_state(&mut self, path: &[usize], state: ElementState) {
+        let mut ancestor_state = ElementState::empty();
+        for flag in [ElementState::HOVER, ElementState::ACTIVE] {
+            if state.contains(flag) {
+                ancestor_state.insert(flag);
+            }
+        }
+        if state.contains(ElementState::FOCUS) {
+            ancestor_state.insert(ElementState::FOCUS_WITHIN);
+        }
+
+        let mut node = self;
+        for &index in path {
+            if let NodeType::Element(ref mut elem) = node.node_type {
+                elem.state.insert(ancestor_state);
+            }
+            node = &mut node.children[index];
+        }
+        let NodeType::Element(ref mut elem) = node.node_type else {
+            panic!("Cannot set the state of a node that is not an element");
+        };
+        elem.state.insert(state);
+        elem.state.insert(ancestor_state);
+    }
+
+    /// Makes the first element with the id `fragment` (without `#`) the
+    /// `:target`, as when the document URL ends in `#fragment`. Returns
+    /// whether there was one.
+    pub fn set_target(&mut self, fragment: &str) -> bool {
+        fn find(node: &Node, fragment: &str, path: &mut Vec<usize>) -> bool {
+            if let NodeType::Element(ref elem) = node.node_type {
+                if elem.id().is_some_and(|id| id == fragment) {
+                    return true;
+                }
+            }
+            for (index, child) in node.children.iter().enumerate() {
+                path.push(index);
+                if find(child, fragment, path) {
+                    return true;
+                }
+                path.pop();
+            }
+            false
+        }
+        let mut path = Vec::new();
+        let found = find(self, fragment, &mut path);
+        if found {
+            self.add_element_state(&path, ElementState::TARGET);
+        }
+        found
+    }
 }
 
 impl ElementData {
@@ -60,4 +164,94 @@ impl ElementData {
             None => Vec::new(),
         }
     }
-}
\ No newline at end of file
+
+    /// Checkboxes and radio buttons with `checked`, selected options, and
+    /// anything marked `CHECKED`.
+    pub fn is_checked(&self) -> bool {
+        let checkable = self.tag_name == "input"
+            && self.get_attribute("type").is_some_and(|t| t.eq_ignore_ascii_case("checkbox") || t.eq_ignore_ascii_case("radio"));
+        self.state.contains(ElementState::CHECKED)
+            || (checkable && self.get_attribute("checked").is_some())
+            || (self.tag_name == "option" && self.get_attribute("selected").is_some())
+    }
+}
+
+/// A node together with its position in the document. Selector matching uses
+/// it to walk to ancestors and siblings; the parent chain usually lives on the
+/// stack of a tree traversal.
+#[derive(Debug, Clone, Copy)]
+pub struct ElementRef<'a> {
+    pub node: &'a Node,
+    pub parent: Option<&'a ElementRef<'a>>,
+    /// Index of `node` within its parent's children.
+    pub index: usize,
+}
+
+impl<'a> ElementRef<'a> {
+    pub fn root(node: &'a Node) -> ElementRef<'a> {
+        ElementRef {
+            node,
+            parent: None,
+            index: 0,
+        }
+    }
+
+    pub fn child(&'a self, index: usize) -> ElementRef<'a> {
+        ElementRef {
+            node: &self.node.children[index],
+            parent: Some(self),
+            index,
+        }
+    }
+
+    pub fn element(&self) -> Option<&'a ElementData> {
+        match self.node.node_type {
+            NodeType::Element(ref elem) => Some(elem),
+            _ => None,
+        }
+    }
+
+    pub fn parent_element(&self) -> Option<ElementRef<'a>> {
+        self.parent.copied()
+    }
+
+    pub fn ancestors(&self) -> impl Iterator<Item = ElementRef<'a>> {
+        std::iter::successors(self.parent_element(), |parent| parent.parent_element())
+    }
+
+    /// Element siblings before this node, nearest first.
+    pub fn preceding_sibling_elements(&self) -> impl Iterator<Item = ElementRef<'a>> {
+        let parent = self.parent;
+        let siblings = match parent {
+            Some(parent) => &parent.node.children[..self.index],
+            None => &[],
+        };
+        siblings.iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, node)| matches!(node.node_type, NodeType::Element(_)))
+            .map(move |(index, node)| ElementRef { node, parent, index })
+    }
+
+    pub fn previous_sibling_element(&self) -> Option<ElementRef<'a>> {
+        self.preceding_sibling_elements().next()
+    }
+
+    /// Element siblings after this node, nearest first.
+    pub fn following_sibling_elements(&self) -> impl Iterator<Item = ElementRef<'a>> {
+        let parent = self.parent;
+        let start = self.index + 1;
+        let siblings = match parent {
+            Some(parent) => &parent.node.children[start..],
+            None => &[],
+        };
+        siblings.iter()
+            .enumerate()
+            .filter(|(_, node)| matches!(node.node_type, NodeType::Element(_)))
+            .map(move |(offset, node)| ElementRef { node, parent, index: start + offset })
+    }
+
+    pub fn is_same_node(&self, other: &ElementRef) -> bool {
+        std::ptr::eq(self.node, other.node)
+    }
+}
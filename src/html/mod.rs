@@ -1,4 +1,4 @@
-use crate::dom::{Node, ElementData, NodeType};
+use crate::dom::Node;
 use std::collections::HashMap;
 
 pub struct HTMLParser {
@@ -15,7 +15,7 @@ impl HTMLParser {
     }
     
     pub fn parse(&mut self, html: &str) -> Node {
-        println!("  [HTML] Parsing {} bytes of HTML...", html.len());
+        eprintln!("  [HTML] Parsing {} bytes of HTML...", html.len());
         self.input = html.to_string();
         self.pos = 0;
         
@@ -95,16 +95,29 @@ impl HTMLParser {
         attributes
     }
 
+    /// A boolean attribute such as `checked` has no `=` and the empty
+    /// string as its value.
     fn parse_attr(&mut self) -> (String, String) {
-        let name = self.parse_tag_name();
+        let name = self.consume_while(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == ':');
+        assert!(!name.is_empty(), "Expected an attribute name");
+        self.consume_whitespace();
+        if self.eof() || self.next_char() != '=' {
+            return (name, String::new());
+        }
         assert_eq!(self.consume_char(), '=');
+        self.consume_whitespace();
         let value = self.parse_attr_value();
         (name, value)
     }
 
+    /// A quoted value, or an unquoted one running up to whitespace or the
+    /// end of the tag.
     fn parse_attr_value(&mut self) -> String {
-        let open_quote = self.consume_char();
-        assert!(open_quote == '"' || open_quote == '\'');
+        let open_quote = self.next_char();
+        if open_quote != '"' && open_quote != '\'' {
+            return self.consume_while(|c| !c.is_whitespace() && c != '>');
+        }
+        self.consume_char();
         let value = self.consume_while(|c| c != open_quote);
         assert_eq!(self.consume_char(), open_quote);
         value
@@ -148,4 +161,10 @@ impl HTMLParser {
         self.pos += next_pos;
         cur_char
     }
-}
\ No newline at end of file
+}
+
+impl Default for HTMLParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,5 +1,5 @@
-use crate::css::Value;
-use crate::style::{StyledNode, Display};
+use crate::style::{StyledNode, Display, LengthPercentageOrAuto};
+use super::form_control::FormControl;
 
 #[derive(Clone, Copy, Default, Debug)]
 pub struct Rect {
@@ -29,6 +29,9 @@ pub struct LayoutBox<'a> {
     pub dimensions: Dimensions,
     pub box_type: BoxType<'a>,
     pub children: Vec<LayoutBox<'a>>,
+    /// Set for form controls, which are sized from their content and drawn
+    /// by the renderer; their children get no boxes.
+    pub form_control: Option<FormControl>,
 }
 
 pub enum BoxType<'a> {
@@ -68,6 +71,7 @@ impl<'a> LayoutBox<'a> {
             box_type,
             dimensions: Default::default(),
             children: Vec::new(),
+            form_control: None,
         }
     }
 
@@ -88,16 +92,23 @@ pub fn layout_tree<'a>(node: &'a StyledNode<'a>, mut containing_block: Dimension
 }
 
 fn build_layout_tree<'a>(style_node: &'a StyledNode<'a>) -> LayoutBox<'a> {
-    let mut root = LayoutBox::new(match style_node.display() {
-        Display::Block => BoxType::BlockNode(style_node),
+    let mut root = LayoutBox::new(match style_node.style.display {
+        Display::Block | Display::ListItem | Display::InlineBlock => BoxType::BlockNode(style_node),
         Display::Inline => BoxType::InlineNode(style_node),
         Display::None => panic!("Root node has display: none"),
     });
 
+    if style_node.pseudo_element.is_none() {
+        root.form_control = FormControl::from_node(style_node.node);
+        if root.form_control.is_some() {
+            return root;
+        }
+    }
+
     for child in &style_node.children {
-        match child.display() {
-            Display::Block => root.children.push(build_layout_tree(child)),
-            Display::Inline => root.get_inline_container()
+        match child.style.display {
+            Display::Block | Display::ListItem => root.children.push(build_layout_tree(child)),
+            Display::Inline | Display::InlineBlock => root.get_inline_container()
                 .children
                 .push(build_layout_tree(child)),
             Display::None => {}
@@ -127,7 +138,33 @@ impl<'a> LayoutBox<'a> {
         match self.box_type {
             BoxType::BlockNode(_) => self.layout_block(containing_block),
             BoxType::InlineNode(_) => {}
-            BoxType::AnonymousBlock => {}
+            BoxType::AnonymousBlock => self.layout_inline_blocks(containing_block),
+        }
+    }
+
+    /// Lays out an anonymous block's `inline-block` boxes, including those
+    /// inside inline elements, side by side on lines from left to right,
+    /// starting a new line when the next box does not fit. Boxes on a line
+    /// are aligned at the top. There is no inline layout of text, so inline
+    /// elements take no space.
+    fn layout_inline_blocks(&mut self, containing_block: Dimensions) {
+        let d = &mut self.dimensions;
+        d.content.x = containing_block.content.x;
+        d.content.y = containing_block.content.y + containing_block.content.height;
+        d.content.width = containing_block.content.width;
+        d.content.height = 0.0;
+
+        let mut line = Line { x: 0.0, top: 0.0, height: 0.0 };
+        place_inline_blocks(&mut self.children, *d, &mut line);
+        self.dimensions.content.height = line.top + line.height;
+    }
+
+    /// Moves the box and everything in it by `(dx, dy)`.
+    fn translate(&mut self, dx: f32, dy: f32) {
+        self.dimensions.content.x += dx;
+        self.dimensions.content.y += dy;
+        for child in &mut self.children {
+            child.translate(dx, dy);
         }
     }
 
@@ -138,102 +175,108 @@ impl<'a> LayoutBox<'a> {
         self.calculate_block_height();
     }
 
-    fn calculate_block_width(&mut self, containing_block: Dimensions) {
-        let style = self.get_style_node();
-
-        let auto = Value::Keyword("auto".to_string());
-        let mut width = style.value("width").unwrap_or(&auto).clone();
-
-        let zero = Value::Length(0.0, crate::css::Unit::Px);
+    /// The width and height of a form control when they are `auto`.
+    fn intrinsic_size(&self) -> Option<(f32, f32)> {
+        let font_size = self.get_style_node().style.font_size;
+        self.form_control.as_ref().map(|control| control.intrinsic_size(font_size))
+    }
 
-        let mut margin_left = style.lookup("margin-left", "margin", &zero);
-        let mut margin_right = style.lookup("margin-right", "margin", &zero);
+    /// Percentages refer to the width of the containing block, also for
+    /// vertical margins and padding. A form control with `width: auto`
+    /// takes its intrinsic width. The `auto` margins of form controls and
+    /// `inline-block` boxes are zero.
+    fn calculate_block_width(&mut self, containing_block: Dimensions) {
+        let style = &self.get_style_node().style;
+        let base = containing_block.content.width;
+        let intrinsic_width = self.intrinsic_size().map(|(width, _)| width);
+
+        let mut width = auto_or_px(&style.width, base).or(intrinsic_width);
+        let mut margin_left = auto_or_px(&style.margin.left, base);
+        let mut margin_right = auto_or_px(&style.margin.right, base);
+        let inline_level = style.display == Display::InlineBlock;
+        if intrinsic_width.is_some() || inline_level {
+            margin_left = margin_left.or(Some(0.0));
+            margin_right = margin_right.or(Some(0.0));
+        }
 
-        let border_left = style.lookup("border-left-width", "border-width", &zero);
-        let border_right = style.lookup("border-right-width", "border-width", &zero);
+        let border_left = style.border_width.left;
+        let border_right = style.border_width.right;
 
-        let padding_left = style.lookup("padding-left", "padding", &zero);
-        let padding_right = style.lookup("padding-right", "padding", &zero);
+        let padding_left = style.padding.left.resolve(base);
+        let padding_right = style.padding.right.resolve(base);
 
         let total = sum([
-            &margin_left,
-            &margin_right,
-            &border_left,
-            &border_right,
-            &padding_left,
-            &padding_right,
-            &width,
-        ].iter().map(|v| v.to_px()));
-
-        if width != auto && total > containing_block.content.width {
-            if margin_left == auto {
-                margin_left = Value::Length(0.0, crate::css::Unit::Px);
-            }
-            if margin_right == auto {
-                margin_right = Value::Length(0.0, crate::css::Unit::Px);
-            }
+            margin_left.unwrap_or(0.0),
+            margin_right.unwrap_or(0.0),
+            border_left,
+            border_right,
+            padding_left,
+            padding_right,
+            width.unwrap_or(0.0),
+        ].into_iter());
+
+        if width.is_some() && total > base {
+            margin_left = margin_left.or(Some(0.0));
+            margin_right = margin_right.or(Some(0.0));
         }
 
-        let underflow = containing_block.content.width - total;
+        let underflow = base - total;
 
-        match (width == auto, margin_left == auto, margin_right == auto) {
-            (false, false, false) => {
-                margin_right = Value::Length(margin_right.to_px() + underflow, crate::css::Unit::Px);
+        match (width, margin_left, margin_right) {
+            // An inline-level box does not fill its line.
+            (Some(_), Some(_), Some(_)) if inline_level => {}
+            (Some(_), Some(_), Some(right)) => {
+                margin_right = Some(right + underflow);
             }
-            (false, false, true) => {
-                margin_right = Value::Length(underflow, crate::css::Unit::Px);
+            (Some(_), Some(_), None) => {
+                margin_right = Some(underflow);
             }
-            (false, true, false) => {
-                margin_left = Value::Length(underflow, crate::css::Unit::Px);
+            (Some(_), None, Some(_)) => {
+                margin_left = Some(underflow);
             }
-            (true, _, _) => {
-                if margin_left == auto {
-                    margin_left = Value::Length(0.0, crate::css::Unit::Px);
-                }
-                if margin_right == auto {
-                    margin_right = Value::Length(0.0, crate::css::Unit::Px);
-                }
+            (None, _, _) => {
+                margin_left = margin_left.or(Some(0.0));
+                margin_right = margin_right.or(Some(0.0));
 
                 if underflow >= 0.0 {
-                    width = Value::Length(underflow, crate::css::Unit::Px);
+                    width = Some(underflow);
                 } else {
-                    width = Value::Length(0.0, crate::css::Unit::Px);
-                    margin_right = Value::Length(margin_right.to_px() + underflow, crate::css::Unit::Px);
+                    width = Some(0.0);
+                    margin_right = margin_right.map(|right| right + underflow);
                 }
             }
-            (false, true, true) => {
-                margin_left = Value::Length(underflow / 2.0, crate::css::Unit::Px);
-                margin_right = Value::Length(underflow / 2.0, crate::css::Unit::Px);
+            (Some(_), None, None) => {
+                margin_left = Some(underflow / 2.0);
+                margin_right = Some(underflow / 2.0);
             }
         }
 
         let d = &mut self.dimensions;
-        d.content.width = width.to_px();
+        d.content.width = width.unwrap_or(0.0);
 
-        d.padding.left = padding_left.to_px();
-        d.padding.right = padding_right.to_px();
+        d.padding.left = padding_left;
+        d.padding.right = padding_right;
 
-        d.border.left = border_left.to_px();
-        d.border.right = border_right.to_px();
+        d.border.left = border_left;
+        d.border.right = border_right;
 
-        d.margin.left = margin_left.to_px();
-        d.margin.right = margin_right.to_px();
+        d.margin.left = margin_left.unwrap_or(0.0);
+        d.margin.right = margin_right.unwrap_or(0.0);
     }
 
     fn calculate_block_position(&mut self, containing_block: Dimensions) {
-        let style = self.get_style_node();
+        let style = &self.get_style_node().style;
+        let base = containing_block.content.width;
         let d = &mut self.dimensions;
 
-        let zero = Value::Length(0.0, crate::css::Unit::Px);
-
-        d.margin.top = style.lookup("margin-top", "margin", &zero).to_px();
-        d.margin.bottom = style.lookup("margin-bottom", "margin", &zero).to_px();
+        d.margin.top = style.margin.top.resolve(base);
+        d.margin.bottom = style.margin.bottom.resolve(base);
 
-        d.border.top = style.lookup("border-top-width", "border-width", &zero).to_px();
-        d.border.bottom = style.lookup("border-bottom-width", "border-width", &zero).to_px();
+        d.border.top = style.border_width.top;
+        d.border.bottom = style.border_width.bottom;
 
-        d.padding.top = style.lookup("padding-top", "padding", &zero).to_px();
-        d.padding.bottom = style.lookup("padding-bottom", "padding", &zero).to_px();
+        d.padding.top = style.padding.top.resolve(base);
+        d.padding.bottom = style.padding.bottom.resolve(base);
 
         d.content.x = containing_block.content.x + d.margin.left + d.border.left + d.padding.left;
 
@@ -241,38 +284,94 @@ impl<'a> LayoutBox<'a> {
             + d.margin.top + d.border.top + d.padding.top;
     }
 
+    /// The renderer draws a block's text at the top of the block, so lines
+    /// of `inline-block` boxes start below it.
     fn layout_block_children(&mut self) {
+        let text_height = self.estimated_text_height();
         let d = &mut self.dimensions;
         for child in &mut self.children {
+            if let (BoxType::AnonymousBlock, Some(text_height)) = (&child.box_type, text_height) {
+                d.content.height = d.content.height.max(text_height);
+            }
             child.layout(*d);
-            d.content.height = d.content.height + child.dimensions.margin_box().height;
+            d.content.height += child.dimensions.margin_box().height;
         }
     }
 
+    /// The height of the block's own text, if it has any. Layout has no
+    /// font metrics, so this assumes two lines.
+    fn estimated_text_height(&self) -> Option<f32> {
+        let style_node = self.get_style_node();
+        if style_node.text_sources().is_empty() {
+            return None;
+        }
+        let estimated_lines = 2.0;
+        Some(style_node.style.font_size * 1.5 * estimated_lines)
+    }
+
+    /// Percentage heights would refer to the height of the containing
+    /// block, which is not known yet, so they count as `auto`. An `auto`
+    /// height fits the block's children and its text.
     fn calculate_block_height(&mut self) {
-        if let Some(Value::Length(h, crate::css::Unit::Px)) = self.get_style_node().value("height") {
-            self.dimensions.content.height = *h;
-        } else {
-            let font_size = self.get_style_node()
-                .value("font-size")
-                .and_then(|v| match v {
-                    Value::Length(s, _) => Some(*s),
-                    _ => None,
-                })
-                .unwrap_or(16.0);
-            
-            let has_text = self.get_style_node().node.children.iter().any(|child| {
-                matches!(child.node_type, crate::dom::NodeType::Text(_))
-            });
-            
-            if has_text {
-                let estimated_lines = 2.0;
-                self.dimensions.content.height = font_size * 1.5 * estimated_lines;
+        let style_node = self.get_style_node();
+        match style_node.style.height {
+            LengthPercentageOrAuto::LengthPercentage(ref height) if !height.has_percentage() => {
+                self.dimensions.content.height = height.resolve(0.0);
+            }
+            _ if self.form_control.is_some() => {
+                self.dimensions.content.height = self.intrinsic_size().map_or(0.0, |(_, height)| height);
+            }
+            _ => {
+                if let Some(text_height) = self.estimated_text_height() {
+                    self.dimensions.content.height = self.dimensions.content.height.max(text_height);
+                }
+            }
+        }
+    }
+}
+
+/// The line being filled with `inline-block` boxes, relative to the
+/// content box of the anonymous block.
+struct Line {
+    x: f32,
+    top: f32,
+    height: f32,
+}
+
+fn place_inline_blocks(boxes: &mut [LayoutBox], container: Dimensions, line: &mut Line) {
+    for layout_box in boxes {
+        match layout_box.box_type {
+            BoxType::BlockNode(_) => {
+                let containing_block = Dimensions {
+                    content: Rect { height: 0.0, ..container.content },
+                    ..Default::default()
+                };
+                layout_box.layout_block(containing_block);
+                let margin_box = layout_box.dimensions.margin_box();
+                if line.x > 0.0 && line.x + margin_box.width > container.content.width {
+                    line.top += line.height;
+                    line.x = 0.0;
+                    line.height = 0.0;
+                }
+                layout_box.translate(line.x, line.top);
+                line.x += margin_box.width;
+                line.height = line.height.max(margin_box.height);
+            }
+            BoxType::InlineNode(_) | BoxType::AnonymousBlock => {
+                place_inline_blocks(&mut layout_box.children, container, line);
             }
         }
     }
 }
 
+/// A length in px, or `None` for `auto`.
+fn auto_or_px(value: &LengthPercentageOrAuto, percentage_base: f32) -> Option<f32> {
+    match value {
+        LengthPercentageOrAuto::Auto => None,
+        LengthPercentageOrAuto::LengthPercentage(length) => Some(length.resolve(percentage_base)),
+    }
+}
+
 fn sum<I>(iter: I) -> f32
 where
     I: Iterator<Item = f32>,
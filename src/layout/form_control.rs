@@ -0,0 +1,192 @@
+use crate::dom::{Node, NodeType, ElementData};
+
+/// Width of an average character in ems. Layout has no font metrics, so
+/// intrinsic widths of text are estimated from the number of characters.
+const CHAR_WIDTH: f32 = 0.55;
+/// Height of one line of text in a control, in ems.
+const LINE_HEIGHT: f32 = 1.2;
+/// Space for the drop-down arrow of a `<select>`, in px.
+const ARROW_WIDTH: f32 = 16.0;
+
+/// A form control drawn by the renderer instead of laid out from its
+/// children: the children of a `<button>`, `<select>` or `<textarea>` only
+/// provide its label, selected option or text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormControl {
+    /// A one-line text input showing its `value`, or else its `placeholder`.
+    TextField { text: String, placeholder: bool, size: usize },
+    TextArea { text: String, rows: usize, cols: usize },
+    Checkbox { checked: bool },
+    Radio { checked: bool },
+    /// `<button>` and `<input type="submit|reset|button">`.
+    Button { label: String },
+    /// A drop-down showing its selected option. `labels` are those of all
+    /// options, which decide its width.
+    Select { label: String, labels: Vec<String> },
+    /// `None` for an indeterminate progress bar without a `value`.
+    Progress { position: Option<f32> },
+    Meter { position: f32, level: MeterLevel },
+}
+
+/// How good a `<meter>` value is according to its `low`, `high` and
+/// `optimum` attributes, which decides the color of the bar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeterLevel {
+    Optimum,
+    Suboptimal,
+    EvenLessGood,
+}
+
+impl FormControl {
+    /// The control `node` renders as, or `None` if it is not one.
+    /// `<input type="hidden">` is not rendered.
+    pub fn from_node(node: &Node) -> Option<FormControl> {
+        let NodeType::Element(ref elem) = node.node_type else {
+            return None;
+        };
+        let attribute = |name| elem.get_attribute(name).map(String::as_str);
+        let count = |name, default| attribute(name).and_then(|n| n.trim().parse().ok()).filter(|&n| n > 0).unwrap_or(default);
+        match &*elem.tag_name {
+            "input" => {
+                let input_type = attribute("type").unwrap_or("text").to_ascii_lowercase();
+                match &*input_type {
+                    "hidden" => None,
+                    "checkbox" => Some(FormControl::Checkbox { checked: elem.is_checked() }),
+                    "radio" => Some(FormControl::Radio { checked: elem.is_checked() }),
+                    "submit" | "reset" | "button" => {
+                        let default = match &*input_type {
+                            "submit" => "Submit",
+                            "reset" => "Reset",
+                            _ => "",
+                        };
+                        Some(FormControl::Button { label: attribute("value").unwrap_or(default).to_string() })
+                    }
+                    _ => {
+                        let size = count("size", 20);
+                        let (text, placeholder) = match attribute("value").filter(|value| !value.is_empty()) {
+                            Some(value) if input_type == "password" => ("\u{2022}".repeat(value.chars().count()), false),
+                            Some(value) => (value.to_string(), false),
+                            None => (attribute("placeholder").unwrap_or("").to_string(), true),
+                        };
+                        Some(FormControl::TextField { text, placeholder, size })
+                    }
+                }
+            }
+            "button" => Some(FormControl::Button { label: collapse_whitespace(&text_content(node)) }),
+            "textarea" => {
+                let text = text_content(node);
+                // A newline right after the start tag is not part of the value.
+                let text = text.strip_prefix('\n').unwrap_or(&text).to_string();
+                Some(FormControl::TextArea { text, rows: count("rows", 2), cols: count("cols", 20) })
+            }
+            "select" => {
+                let mut options = Vec::new();
+                collect_options(node, &mut options);
+                let labels: Vec<String> = options.iter().map(|(option, node)| option_label(option, node)).collect();
+                let selected = options.iter().position(|(option, _)| option.is_checked()).unwrap_or(0);
+                let label = labels.get(selected).cloned().unwrap_or_default();
+                Some(FormControl::Select { label, labels })
+            }
+            "progress" => {
+                let max = number(attribute("max")).filter(|&max| max > 0.0).unwrap_or(1.0);
+                let position = number(attribute("value")).map(|value| value.clamp(0.0, max) / max);
+                Some(FormControl::Progress { position })
+            }
+            "meter" => Some(meter(elem)),
+            _ => None,
+        }
+    }
+
+    /// The width and height of the content box when `width` and `height`
+    /// are `auto`, for a control with font size `font_size`.
+    pub fn intrinsic_size(&self, font_size: f32) -> (f32, f32) {
+        let line = font_size * LINE_HEIGHT;
+        let text_width = |text: &str| text.chars().count() as f32 * font_size * CHAR_WIDTH;
+        match self {
+            FormControl::TextField { size, .. } => (*size as f32 * font_size * CHAR_WIDTH, line),
+            FormControl::TextArea { rows, cols, .. } => (*cols as f32 * font_size * CHAR_WIDTH, *rows as f32 * line),
+            FormControl::Checkbox { .. } | FormControl::Radio { .. } => (13.0, 13.0),
+            FormControl::Button { label } => (text_width(label), line),
+            FormControl::Select { labels, .. } => {
+                let widest = labels.iter().map(|label| text_width(label)).fold(0.0, f32::max);
+                (widest + ARROW_WIDTH, line)
+            }
+            FormControl::Progress { .. } => (font_size * 10.0, font_size),
+            FormControl::Meter { .. } => (font_size * 5.0, font_size),
+        }
+    }
+}
+
+/// The `value` of a `<meter>` as a fraction of its range, and its level
+/// following the HTML standard: the range is split at `low` and `high`, and
+/// the part `optimum` lies in is the best.
+fn meter(elem: &ElementData) -> FormControl {
+    let attribute = |name| number(elem.get_attribute(name).map(String::as_str));
+    let min = attribute("min").unwrap_or(0.0);
+    let max = attribute("max").unwrap_or(1.0).max(min);
+    let value = attribute("value").unwrap_or(0.0).clamp(min, max);
+    let low = attribute("low").unwrap_or(min).clamp(min, max);
+    let high = attribute("high").unwrap_or(max).clamp(low, max);
+    let optimum = attribute("optimum").unwrap_or((min + max) / 2.0).clamp(min, max);
+
+    let level = if optimum < low {
+        if value < low {
+            MeterLevel::Optimum
+        } else if value <= high {
+            MeterLevel::Suboptimal
+        } else {
+            MeterLevel::EvenLessGood
+        }
+    } else if optimum > high {
+        if value > high {
+            MeterLevel::Optimum
+        } else if value >= low {
+            MeterLevel::Suboptimal
+        } else {
+            MeterLevel::EvenLessGood
+        }
+    } else if (low..=high).contains(&value) {
+        MeterLevel::Optimum
+    } else {
+        MeterLevel::Suboptimal
+    };
+    let position = if max > min { (value - min) / (max - min) } else { 0.0 };
+    FormControl::Meter { position, level }
+}
+
+fn number(value: Option<&str>) -> Option<f32> {
+    value.and_then(|value| value.trim().parse().ok()).filter(|value: &f32| value.is_finite())
+}
+
+/// The `<option>` elements of a `<select>`, also those inside `<optgroup>`s.
+fn collect_options<'a>(node: &'a Node, options: &mut Vec<(&'a ElementData, &'a Node)>) {
+    for child in &node.children {
+        if let NodeType::Element(ref elem) = child.node_type {
+            match &*elem.tag_name {
+                "option" => options.push((elem, child)),
+                "optgroup" => collect_options(child, options),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The `label` attribute of an option, or else its text.
+fn option_label(option: &ElementData, node: &Node) -> String {
+    match option.get_attribute("label") {
+        Some(label) => label.clone(),
+        None => collapse_whitespace(&text_content(node)),
+    }
+}
+
+fn text_content(node: &Node) -> String {
+    match node.node_type {
+        NodeType::Text(ref text) => text.clone(),
+        NodeType::Comment(_) => String::new(),
+        NodeType::Element(_) => node.children.iter().map(text_content).collect(),
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -1,16 +1,24 @@
 pub mod box_model;
+pub mod form_control;
 
 pub use box_model::{LayoutBox, BoxType, Dimensions, Rect, EdgeSizes, layout_tree};
+pub use form_control::{FormControl, MeterLevel};
 
 pub struct LayoutEngine;
 
 impl LayoutEngine {
     pub fn new() -> Self {
-        println!("  [Layout] Initializing layout engine...");
+        eprintln!("  [Layout] Initializing layout engine...");
         LayoutEngine
     }
     
     pub fn calculate_layout(&self) {
-        println!("  [Layout] Calculating element positions and sizes...");
+        eprintln!("  [Layout] Calculating element positions and sizes...");
     }
-}
\ No newline at end of file
+}
+
+impl Default for LayoutEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
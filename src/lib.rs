@@ -0,0 +1,6 @@
+pub mod html;
+pub mod css;
+pub mod dom;
+pub mod style;
+pub mod layout;
+pub mod render;
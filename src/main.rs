@@ -1,16 +1,11 @@
-mod html;
-mod css;
-mod dom;
-mod style;
-mod layout;
-mod render;
-
-use html::HTMLParser;
-use css::CSSParser;
-use style::style_tree;
-use layout::{layout_tree, Dimensions};
-use render::ImageRenderer;
+use ukechug::html::HTMLParser;
+use ukechug::css::{CSSParser, Parser as CssParser, CssFormat, Origin, MediaEnvironment, ColorScheme, Stylesheet, user_agent_stylesheet};
+use ukechug::dom::{Node, ElementState};
+use ukechug::style::{style_tree_with_statistics, add_state, StyleReport};
+use ukechug::layout::{layout_tree, Dimensions};
+use ukechug::render::ImageRenderer;
 use std::fs;
+use url::Url;
 use clap::{Parser, Subcommand};
 
 #[derive(Parser)]
@@ -34,6 +29,69 @@ struct Args {
     
     #[arg(short = 'H', long, default_value = "600", help = "Image height in pixels")]
     height: u32,
+
+    #[arg(long, help = "User stylesheet applied with user origin (e.g. high contrast overrides)")]
+    user_css: Option<String>,
+
+    #[arg(long, default_value = "screen", value_parser = ["screen", "print"], help = "Media type for @media queries")]
+    media: String,
+
+    #[arg(long, default_value = "1", help = "Device pixel ratio for resolution media queries")]
+    resolution: f32,
+
+    #[arg(long, default_value = "light", value_parser = ["light", "dark"], help = "Value of prefers-color-scheme")]
+    color_scheme: String,
+
+    #[arg(long, help = "Match prefers-reduced-motion: reduce")]
+    reduced_motion: bool,
+
+    #[arg(long, help = "Do not apply the built-in user-agent stylesheet")]
+    no_ua_css: bool,
+
+    #[arg(long, default_value = "1", value_parser = clap::value_parser!(u32).range(1..), help = "Threads to compute styles on")]
+    threads: u32,
+
+    #[command(flatten)]
+    state: StateArgs,
+}
+
+/// Interaction state to simulate before styling, for screenshots of hover,
+/// focus and other states. Each option takes a selector list and may be
+/// repeated.
+#[derive(clap::Args)]
+struct StateArgs {
+    #[arg(long, value_name = "SELECTOR", help = "Elements to match :hover (their ancestors match too)")]
+    hover: Vec<String>,
+
+    #[arg(long, value_name = "SELECTOR", help = "Elements to match :active (their ancestors match too)")]
+    active: Vec<String>,
+
+    #[arg(long, value_name = "SELECTOR", help = "Elements to match :focus (their ancestors match :focus-within)")]
+    focus: Vec<String>,
+
+    #[arg(long, value_name = "SELECTOR", help = "Elements to focus as from the keyboard, also matching :focus-visible")]
+    focus_visible: Vec<String>,
+
+    #[arg(long, value_name = "SELECTOR", help = "Links to match :visited instead of :link")]
+    visited: Vec<String>,
+
+    #[arg(long, value_name = "SELECTOR", help = "Elements to match :checked")]
+    checked: Vec<String>,
+
+    #[arg(long, value_name = "SELECTOR", help = "Form controls to match :disabled")]
+    disabled: Vec<String>,
+
+    #[arg(long, value_name = "FRAGMENT", help = "URL fragment whose element matches :target, e.g. #section-2")]
+    target: Option<String>,
+}
+
+/// The files a page is loaded from and how to prepare it for styling.
+struct PageSource<'a> {
+    html_file: &'a str,
+    css_file: &'a str,
+    user_css_file: Option<&'a str>,
+    ua_css: bool,
+    state: &'a StateArgs,
 }
 
 #[derive(Subcommand)]
@@ -43,102 +101,280 @@ enum Commands {
         #[arg(help = "File to delete")]
         file: String,
     },
+    #[command(about = "Rewrite a stylesheet with its imports inlined, pretty-printed or minified")]
+    Format {
+        #[arg(help = "CSS file to format")]
+        file: String,
+
+        #[arg(short, long, help = "Output CSS file")]
+        output: String,
+
+        #[arg(long, help = "Strip all whitespace that is not needed")]
+        minify: bool,
+    },
+    #[command(about = "Print the computed values of every element and the rules they came from")]
+    Styles {
+        #[arg(help = "HTML file to style")]
+        html_file: String,
+
+        #[arg(help = "CSS file to apply")]
+        css_file: String,
+
+        #[arg(long, help = "Print JSON instead of text")]
+        json: bool,
+
+        #[arg(short, long, help = "Write the dump to a file instead of standard output")]
+        output: Option<String>,
+    },
 }
 
 fn main() {
     let args = Args::parse();
     
-    println!("========================================");
-    println!("  Welcome to UkeChug Browser Engine!");
-    println!("========================================");
-    println!();
-    println!("Version: 0.1.0");
-    println!();
+    eprintln!("========================================");
+    eprintln!("  Welcome to UkeChug Browser Engine!");
+    eprintln!("========================================");
+    eprintln!();
+    eprintln!("Version: 0.1.0");
+    eprintln!();
     
-    if let Some(Commands::Clean { file }) = args.command {
-        clean_file(&file);
+    match args.command {
+        Some(Commands::Clean { file }) => {
+            clean_file(&file);
+            return;
+        }
+        Some(Commands::Format { file, output, minify }) => {
+            format_file(&file, &output, if minify { CssFormat::Minified } else { CssFormat::Pretty });
+            return;
+        }
+        _ => {}
+    }
+
+    let media = MediaEnvironment {
+        media_type: args.media,
+        resolution: args.resolution,
+        color_scheme: if args.color_scheme == "dark" { ColorScheme::Dark } else { ColorScheme::Light },
+        reduced_motion: args.reduced_motion,
+        ..MediaEnvironment::new(args.width as f32, args.height as f32)
+    };
+
+    if let Some(Commands::Styles { ref html_file, ref css_file, json, ref output }) = args.command {
+        let page = PageSource {
+            html_file,
+            css_file,
+            user_css_file: args.user_css.as_deref(),
+            ua_css: !args.no_ua_css,
+            state: &args.state,
+        };
+        dump_styles(&page, &media, json, output.as_deref());
         return;
     }
-    
+
     let html_file = args.html_file.unwrap_or_else(|| "test.html".to_string());
     let css_file = args.css_file.unwrap_or_else(|| "test.css".to_string());
+    let page = PageSource {
+        html_file: &html_file,
+        css_file: &css_file,
+        user_css_file: args.user_css.as_deref(),
+        ua_css: !args.no_ua_css,
+        state: &args.state,
+    };
+
+    render_from_files(&page, &args.output, &media, args.threads as usize);
     
-    render_from_files(&html_file, &css_file, &args.output, args.width, args.height);
-    
-    println!();
-    println!("========================================");
+    eprintln!();
+    eprintln!("========================================");
 }
 
 fn clean_file(file: &str) {
     if std::path::Path::new(file).exists() {
         match fs::remove_file(file) {
-            Ok(_) => println!("✓ Deleted: {}", file),
-            Err(e) => println!("✗ Could not delete {}: {}", file, e),
+            Ok(_) => eprintln!("✓ Deleted: {}", file),
+            Err(e) => eprintln!("✗ Could not delete {}: {}", file, e),
         }
     } else {
-        println!("✗ File not found: {}", file);
+        eprintln!("✗ File not found: {}", file);
     }
 }
 
-fn render_from_files(html_file: &str, css_file: &str, output_file: &str, width: u32, height: u32) {
-    println!("Loading files:");
-    println!("  HTML: {}", html_file);
-    println!("  CSS: {}", css_file);
-    println!("  Output: {}", output_file);
-    println!("  Size: {}x{}", width, height);
-    println!();
+fn format_file(file: &str, output_file: &str, format: CssFormat) {
+    let css = match fs::read_to_string(file) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("✗ Could not read {}: {}", file, e);
+            return;
+        }
+    };
+    let css_parser = CSSParser::new();
+    let stylesheet = match fs::canonicalize(file).ok().and_then(|path| Url::from_file_path(path).ok()) {
+        Some(url) => css_parser.parse_with_url(&css, url),
+        None => css_parser.parse(&css),
+    };
+    let formatted = stylesheet.to_css(format);
+    match fs::write(output_file, &formatted) {
+        Ok(_) => eprintln!("✓ Wrote {} ({} -> {} bytes)", output_file, css.len(), formatted.len()),
+        Err(e) => eprintln!("✗ Could not write {}: {}", output_file, e),
+    }
+}
+
+fn render_from_files(page: &PageSource, output_file: &str, media: &MediaEnvironment, threads: usize) {
+    let (width, height) = (media.width as u32, media.height as u32);
+    eprintln!("Loading files:");
+    eprintln!("  HTML: {}", page.html_file);
+    eprintln!("  CSS: {}", page.css_file);
+    if let Some(user_css_file) = page.user_css_file {
+        eprintln!("  User CSS: {}", user_css_file);
+    }
+    if !page.ua_css {
+        eprintln!("  User-agent CSS: disabled");
+    }
+    eprintln!("  Output: {}", output_file);
+    eprintln!("  Size: {}x{}", width, height);
+    eprintln!("  Media: {} ({}dppx, {:?})", media.media_type, media.resolution, media.color_scheme);
+    if threads > 1 {
+        eprintln!("  Style threads: {}", threads);
+    }
+    eprintln!();
+    
+    let Some((root_node, stylesheets)) = load_page(page) else {
+        return;
+    };
+
+    eprintln!("  [Style] Creating styled tree...");
+    let (styled_root, statistics) = style_tree_with_statistics(&root_node, &stylesheets, media, threads);
+    eprintln!(
+        "  [Style] Styled {} elements; style sharing: {} hits, {} misses ({:.0}% hit rate)",
+        statistics.elements,
+        statistics.sharing_hits,
+        statistics.sharing_misses,
+        statistics.hit_rate() * 100.0,
+    );
+    
+    eprintln!("  [Layout] Creating layout tree...");
+    let mut viewport: Dimensions = Default::default();
+    viewport.content.width = width as f32;
+    viewport.content.height = height as f32;
+    
+    let layout_root = layout_tree(&styled_root, viewport);
+    
+    eprintln!("  [Render] Rendering to image...");
+    let mut image_renderer = ImageRenderer::new(width, height);
+    image_renderer.render(&layout_root);
     
+    match image_renderer.save(output_file) {
+        Ok(_) => {
+            eprintln!("  [Render] Image saved to {}", output_file);
+            eprintln!();
+            eprintln!("Success! Open {} to see your rendered page.", output_file);
+        }
+        Err(e) => eprintln!("  [Render] Error saving image: {}", e),
+    }
+}
+
+/// Reads and parses the page and its stylesheets, in cascade order: the
+/// user-agent stylesheet if enabled, the user stylesheet, then the author
+/// stylesheet, and applies the simulated interaction state. Read errors are
+/// reported and give `None`.
+fn load_page(page: &PageSource) -> Option<(Node, Vec<Stylesheet>)> {
+    let PageSource { html_file, css_file, user_css_file, ua_css, state } = *page;
     let html_content = match fs::read_to_string(html_file) {
         Ok(content) => {
-            println!("  [File] Loaded {} ({} bytes)", html_file, content.len());
+            eprintln!("  [File] Loaded {} ({} bytes)", html_file, content.len());
             content
         }
         Err(e) => {
-            println!("  [Error] Could not read {}: {}", html_file, e);
-            return;
+            eprintln!("  [Error] Could not read {}: {}", html_file, e);
+            return None;
         }
     };
     
     let css_content = match fs::read_to_string(css_file) {
         Ok(content) => {
-            println!("  [File] Loaded {} ({} bytes)", css_file, content.len());
+            eprintln!("  [File] Loaded {} ({} bytes)", css_file, content.len());
             content
         }
         Err(e) => {
-            println!("  [Error] Could not read {}: {}", css_file, e);
-            return;
+            eprintln!("  [Error] Could not read {}: {}", css_file, e);
+            return None;
+        }
+    };
+
+    let user_css_content = match user_css_file.map(|file| (file, fs::read_to_string(file))) {
+        Some((file, Ok(content))) => {
+            eprintln!("  [File] Loaded {} ({} bytes)", file, content.len());
+            Some(content)
+        }
+        Some((file, Err(e))) => {
+            eprintln!("  [Error] Could not read {}: {}", file, e);
+            return None;
         }
+        None => None,
     };
     
-    println!();
-    println!("  [HTML] Parsing HTML...");
+    eprintln!();
+    eprintln!("  [HTML] Parsing HTML...");
     let mut html_parser = HTMLParser::new();
-    let root_node = html_parser.parse(&html_content);
+    let mut root_node = html_parser.parse(&html_content);
+    simulate_state(&mut root_node, state);
     
-    println!("  [CSS] Parsing CSS...");
+    eprintln!("  [CSS] Parsing CSS...");
     let css_parser = CSSParser::new();
-    let stylesheet = css_parser.parse(&css_content);
-    
-    println!("  [Style] Creating styled tree...");
-    let styled_root = style_tree(&root_node, &stylesheet);
-    
-    println!("  [Layout] Creating layout tree...");
-    let mut viewport: Dimensions = Default::default();
-    viewport.content.width = width as f32;
-    viewport.content.height = height as f32;
-    
-    let layout_root = layout_tree(&styled_root, viewport);
-    
-    println!("  [Render] Rendering to image...");
-    let mut image_renderer = ImageRenderer::new(width, height);
-    image_renderer.render(&layout_root);
-    
-    match image_renderer.save(output_file) {
-        Ok(_) => {
-            println!("  [Render] Image saved to {}", output_file);
-            println!();
-            println!("Success! Open {} to see your rendered page.", output_file);
+    let parse_file = |css: &str, file: &str| match fs::canonicalize(file).ok().and_then(|path| Url::from_file_path(path).ok()) {
+        Some(url) => css_parser.parse_with_url(css, url),
+        None => css_parser.parse(css),
+    };
+    let mut stylesheets = Vec::new();
+    if ua_css {
+        stylesheets.push(user_agent_stylesheet());
+    }
+    if let (Some(user_css_content), Some(user_css_file)) = (user_css_content, user_css_file) {
+        stylesheets.push(parse_file(&user_css_content, user_css_file).with_origin(Origin::User));
+    }
+    stylesheets.push(parse_file(&css_content, css_file));
+    Some((root_node, stylesheets))
+}
+
+fn simulate_state(root: &mut Node, state: &StateArgs) {
+    let flags = [
+        ("hover", &state.hover, ElementState::HOVER),
+        ("active", &state.active, ElementState::ACTIVE),
+        ("focus", &state.focus, ElementState::FOCUS),
+        ("focus-visible", &state.focus_visible, ElementState::FOCUS | ElementState::FOCUS_VISIBLE),
+        ("visited", &state.visited, ElementState::VISITED),
+        ("checked", &state.checked, ElementState::CHECKED),
+        ("disabled", &state.disabled, ElementState::DISABLED),
+    ];
+    for (name, selectors, flag) in flags {
+        for selector in selectors {
+            let matched = add_state(root, &CssParser::new(selector.clone()).parse_selector_list(), flag);
+            eprintln!("  [State] :{} on {} element(s) matching {}", name, matched, selector);
         }
-        Err(e) => println!("  [Render] Error saving image: {}", e),
     }
-}
\ No newline at end of file
+    if let Some(ref target) = state.target {
+        let fragment = target.strip_prefix('#').unwrap_or(target);
+        if root.set_target(fragment) {
+            eprintln!("  [State] :target is #{}", fragment);
+        } else {
+            eprintln!("  [State] No element with id {} to target", fragment);
+        }
+    }
+}
+
+fn dump_styles(page: &PageSource, media: &MediaEnvironment, json: bool, output_file: Option<&str>) {
+    let Some((root_node, stylesheets)) = load_page(page) else {
+        return;
+    };
+    eprintln!("  [Style] Inspecting computed styles...");
+    let report = StyleReport::new(&root_node, &stylesheets, media);
+    let dump = if json { report.to_json() } else { report.to_text() };
+    match output_file {
+        Some(output_file) => match fs::write(output_file, &dump) {
+            Ok(_) => eprintln!("✓ Wrote styles of {} elements to {}", report.elements.len(), output_file),
+            Err(e) => eprintln!("✗ Could not write {}: {}", output_file, e),
+        },
+        None => {
+            eprintln!();
+            print!("{}", dump);
+        }
+    }
+}
@@ -1,11 +1,23 @@
-use crate::layout::{LayoutBox, BoxType};
+use crate::layout::{LayoutBox, BoxType, FormControl, MeterLevel};
+use crate::layout::Rect as LayoutRect;
 use crate::dom::NodeType;
-use crate::css::Value;
-use crate::render::text_drawer::TextDrawer;
+use crate::css::Color;
+use crate::render::text_drawer::{TextDrawer, TextRun};
 use image::{RgbaImage, Rgba};
-use imageproc::drawing::{draw_filled_rect_mut, draw_hollow_rect_mut};
+use imageproc::drawing::{draw_filled_rect_mut, draw_filled_circle_mut, draw_line_segment_mut};
 use imageproc::rect::Rect;
 
+/// Colors of the parts of form controls that CSS does not style.
+const CONTROL_BORDER: Rgba<u8> = Rgba([118, 118, 118, 255]);
+const CONTROL_FILL: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const ACCENT: Rgba<u8> = Rgba([0, 117, 255, 255]);
+const PLACEHOLDER: Rgba<u8> = Rgba([117, 117, 117, 255]);
+const TRACK: Rgba<u8> = Rgba([239, 239, 239, 255]);
+const TRACK_BORDER: Rgba<u8> = Rgba([178, 178, 178, 255]);
+const METER_OPTIMUM: Rgba<u8> = Rgba([16, 124, 16, 255]);
+const METER_SUBOPTIMAL: Rgba<u8> = Rgba([255, 185, 0, 255]);
+const METER_EVEN_LESS_GOOD: Rgba<u8> = Rgba([216, 59, 1, 255]);
+
 pub struct ImageRenderer<'a> {
     image: RgbaImage,
     text_drawer: TextDrawer<'a>,
@@ -25,7 +37,10 @@ impl<'a> ImageRenderer<'a> {
     fn render_layout_box(&mut self, layout_box: &LayoutBox) {
         self.render_background(layout_box);
         self.render_borders(layout_box);
-        self.render_text(layout_box);
+        match layout_box.form_control {
+            Some(ref control) => self.render_form_control(layout_box, control),
+            None => self.render_text(layout_box),
+        }
 
         for child in &layout_box.children {
             self.render_layout_box(child);
@@ -33,157 +48,199 @@ impl<'a> ImageRenderer<'a> {
     }
 
     fn render_background(&mut self, layout_box: &LayoutBox) {
-        let color = self.get_background_color(layout_box);
-        
-        let d = layout_box.dimensions;
-        let rect = d.border_box();
-
-        let x = rect.x as i32;
-        let y = rect.y as i32;
-        let width = rect.width as i32;
-        let height = rect.height as i32;
-
-        if width > 0 && height > 0 {
-            let image_rect = Rect::at(x, y).of_size(width as u32, height as u32);
-            draw_filled_rect_mut(&mut self.image, image_rect, color);
+        let color = match layout_box.box_type {
+            BoxType::BlockNode(style_node) | BoxType::InlineNode(style_node) => style_node.style.background_color,
+            BoxType::AnonymousBlock => return,
+        };
+        if color.a == 0 {
+            return;
         }
+
+        let rect = layout_box.dimensions.border_box();
+        self.fill_rect(rect.x, rect.y, rect.width, rect.height, to_rgba(color));
     }
 
+    /// Borders are drawn solid on each side that has a width.
     fn render_borders(&mut self, layout_box: &LayoutBox) {
-        let border_color = self.get_border_color(layout_box);
-        let border_width = self.get_border_width(layout_box);
-
-        if border_width == 0 {
-            return;
-        }
+        let color = match layout_box.box_type {
+            BoxType::BlockNode(style_node) | BoxType::InlineNode(style_node) => to_rgba(style_node.style.border_color),
+            BoxType::AnonymousBlock => return,
+        };
 
         let d = layout_box.dimensions;
         let rect = d.border_box();
+        let border = d.border;
+
+        self.fill_rect(rect.x, rect.y, rect.width, border.top, color);
+        self.fill_rect(rect.x, rect.y + rect.height - border.bottom, rect.width, border.bottom, color);
+        self.fill_rect(rect.x, rect.y, border.left, rect.height, color);
+        self.fill_rect(rect.x + rect.width - border.right, rect.y, border.right, rect.height, color);
+    }
 
-        let x = rect.x as i32;
-        let y = rect.y as i32;
-        let width = rect.width as i32;
-        let height = rect.height as i32;
+    fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Rgba<u8>) {
+        let width = width as i32;
+        let height = height as i32;
 
         if width > 0 && height > 0 {
-            for i in 0..border_width {
-                let offset = i as i32;
-                let adjusted_x = x + offset;
-                let adjusted_y = y + offset;
-                let adjusted_width = (width - offset * 2).max(1) as u32;
-                let adjusted_height = (height - offset * 2).max(1) as u32;
-
-                if adjusted_width > 0 && adjusted_height > 0 {
-                    let image_rect = Rect::at(adjusted_x, adjusted_y)
-                        .of_size(adjusted_width, adjusted_height);
-                    draw_hollow_rect_mut(&mut self.image, image_rect, border_color);
-                }
-            }
+            let image_rect = Rect::at(x as i32, y as i32).of_size(width as u32, height as u32);
+            draw_filled_rect_mut(&mut self.image, image_rect, color);
         }
     }
 
     fn render_text(&mut self, layout_box: &LayoutBox) {
         if let BoxType::BlockNode(style_node) | BoxType::InlineNode(style_node) = &layout_box.box_type {
+            // Inline generated content is drawn with its originating block.
+            if style_node.pseudo_element.is_some() && matches!(layout_box.box_type, BoxType::InlineNode(_)) {
+                return;
+            }
+
             if let NodeType::Element(elem) = &style_node.node.node_type {
-                println!("Rendering text for <{}>", elem.tag_name);
+                eprintln!("Rendering text for <{}>", elem.tag_name);
             }
-            
-            for child_node in &style_node.node.children {
-                if let NodeType::Text(text) = &child_node.node_type {
-                    let d = layout_box.dimensions;
-                    
-                    println!("  Box dimensions:");
-                    println!("    content: x={}, y={}, w={}, h={}", d.content.x, d.content.y, d.content.width, d.content.height);
-                    println!("    padding: l={}, t={}", d.padding.left, d.padding.top);
-                    
-                    let x = (d.content.x + d.padding.left + 10.0) as i32;
-                    let y = (d.content.y + d.padding.top + 10.0) as i32;
-                    let max_width = d.content.width.max(100.0) - 20.0;
-                    
-                    println!("    text position: x={}, y={}, max_width={}", x, y, max_width);
-                    
-                    let font_size = self.get_font_size(style_node);
-                    let text_color = self.get_text_color(style_node);
-                    
-                    self.text_drawer.draw_text(&mut self.image, text.trim(), x, y, font_size, text_color, max_width);
-                }
+
+            let runs: Vec<TextRun> = style_node.text_sources().into_iter()
+                .filter_map(|source| {
+                    let text = source.own_text()?;
+                    // Text nodes carry no style of their own.
+                    let style = if source.pseudo_element.is_some() { &source.style } else { &style_node.style };
+                    Some(TextRun {
+                        text: text.to_string(),
+                        size: style.font_size,
+                        color: to_rgba(style.color),
+                    })
+                })
+                .collect();
+
+            if runs.iter().all(|run| run.text.trim().is_empty()) {
+                return;
             }
+
+            let d = layout_box.dimensions;
+
+            eprintln!("  Box dimensions:");
+            eprintln!("    content: x={}, y={}, w={}, h={}", d.content.x, d.content.y, d.content.width, d.content.height);
+            eprintln!("    padding: l={}, t={}", d.padding.left, d.padding.top);
+
+            let x = (d.content.x + d.padding.left + 10.0) as i32;
+            let y = (d.content.y + d.padding.top + 10.0) as i32;
+            let max_width = d.content.width.max(100.0) - 20.0;
+
+            eprintln!("    text position: x={}, y={}, max_width={}", x, y, max_width);
+
+            self.text_drawer.draw_runs(&mut self.image, &runs, x, y, max_width);
         }
     }
 
-    fn get_background_color(&self, layout_box: &LayoutBox) -> Rgba<u8> {
-        match &layout_box.box_type {
-            BoxType::BlockNode(style_node) | BoxType::InlineNode(style_node) => {
-                if let Some(value) = style_node.value("background-color") {
-                    return self.value_to_color(value);
+    /// Draws a form control inside its content box. Text is drawn in the
+    /// control's `color` and font size; the other parts use fixed colors.
+    fn render_form_control(&mut self, layout_box: &LayoutBox, control: &FormControl) {
+        let style_node = layout_box.get_style_node();
+        if let NodeType::Element(elem) = &style_node.node.node_type {
+            eprintln!("Rendering <{}> as a form control", elem.tag_name);
+        }
+        let style = &style_node.style;
+        let content = layout_box.dimensions.content;
+        let color = to_rgba(style.color);
+        let size = style.font_size;
+
+        match control {
+            FormControl::TextField { text, placeholder, .. } => {
+                let color = if *placeholder { PLACEHOLDER } else { color };
+                self.draw_label(text, content, size, color, false);
+            }
+            FormControl::TextArea { text, .. } => {
+                let line_height = size * 1.2;
+                for (index, line) in text.lines().enumerate() {
+                    let y = content.y + index as f32 * line_height;
+                    if y + line_height > content.y + content.height {
+                        break;
+                    }
+                    let run = TextRun { text: line.to_string(), size, color };
+                    self.text_drawer.draw_clipped(&mut self.image, &run, content.x as i32, y as i32, content.width);
                 }
-                if let Some(value) = style_node.value("background") {
-                    return self.value_to_color(value);
+            }
+            FormControl::Checkbox { checked } => {
+                let side = content.width.min(content.height);
+                let x = content.x + (content.width - side) / 2.0;
+                let y = content.y + (content.height - side) / 2.0;
+                if *checked {
+                    self.fill_rect(x, y, side, side, ACCENT);
+                    let point = |fx: f32, fy: f32| (x + side * fx, y + side * fy);
+                    for offset in [-0.5, 0.0, 0.5] {
+                        let (start, middle, end) = (point(0.22, 0.5), point(0.42, 0.7), point(0.78, 0.3));
+                        let shift = |(px, py): (f32, f32)| (px, py + offset);
+                        draw_line_segment_mut(&mut self.image, shift(start), shift(middle), CONTROL_FILL);
+                        draw_line_segment_mut(&mut self.image, shift(middle), shift(end), CONTROL_FILL);
+                    }
+                } else {
+                    self.fill_rect(x, y, side, side, CONTROL_BORDER);
+                    self.fill_rect(x + 1.0, y + 1.0, side - 2.0, side - 2.0, CONTROL_FILL);
                 }
             }
-            _ => {}
-        }
-        Rgba([255, 255, 255, 255])
-    }
-
-    fn get_border_color(&self, layout_box: &LayoutBox) -> Rgba<u8> {
-        match &layout_box.box_type {
-            BoxType::BlockNode(style_node) | BoxType::InlineNode(style_node) => {
-                if let Some(value) = style_node.value("border-color") {
-                    return self.value_to_color(value);
+            FormControl::Radio { checked } => {
+                let radius = (content.width.min(content.height) / 2.0) as i32;
+                let center = ((content.x + content.width / 2.0) as i32, (content.y + content.height / 2.0) as i32);
+                if *checked {
+                    draw_filled_circle_mut(&mut self.image, center, radius, ACCENT);
+                    draw_filled_circle_mut(&mut self.image, center, radius - 2, CONTROL_FILL);
+                    draw_filled_circle_mut(&mut self.image, center, radius - 4, ACCENT);
+                } else {
+                    draw_filled_circle_mut(&mut self.image, center, radius, CONTROL_BORDER);
+                    draw_filled_circle_mut(&mut self.image, center, radius - 1, CONTROL_FILL);
                 }
             }
-            _ => {}
-        }
-        Rgba([0, 0, 0, 255])
-    }
-
-    fn get_border_width(&self, layout_box: &LayoutBox) -> u32 {
-        match &layout_box.box_type {
-            BoxType::BlockNode(style_node) | BoxType::InlineNode(style_node) => {
-                if let Some(Value::Length(width, _)) = style_node.value("border-width") {
-                    return (*width as u32).max(0);
+            FormControl::Button { label } => self.draw_label(label, content, size, color, true),
+            FormControl::Select { label, .. } => {
+                let arrow = 16.0_f32.min(content.width);
+                let text_box = LayoutRect { width: content.width - arrow, ..content };
+                self.draw_label(label, text_box, size, color, false);
+                // A small triangle pointing down, centered in the arrow space.
+                let center_x = content.x + content.width - arrow / 2.0;
+                let top = content.y + content.height / 2.0 - 2.0;
+                for row in 0..4 {
+                    let half = 4.0 - row as f32;
+                    self.fill_rect(center_x - half, top + row as f32, half * 2.0, 1.0, color);
                 }
             }
-            _ => {}
+            FormControl::Progress { position } => {
+                self.draw_track(content);
+                if let Some(position) = position {
+                    self.fill_rect(content.x, content.y, content.width * position, content.height, ACCENT);
+                }
+            }
+            FormControl::Meter { position, level } => {
+                self.draw_track(content);
+                let bar = match level {
+                    MeterLevel::Optimum => METER_OPTIMUM,
+                    MeterLevel::Suboptimal => METER_SUBOPTIMAL,
+                    MeterLevel::EvenLessGood => METER_EVEN_LESS_GOOD,
+                };
+                self.fill_rect(content.x, content.y, content.width * position, content.height, bar);
+            }
         }
-        1
     }
 
-    fn get_text_color(&self, style_node: &crate::style::StyledNode) -> Rgba<u8> {
-        if let Some(value) = style_node.value("color") {
-            return self.value_to_color(value);
-        }
-        Rgba([0, 0, 0, 255])
+    /// One line of text vertically centered in `rect`, and horizontally
+    /// too if `centered`; what does not fit is cut off.
+    fn draw_label(&mut self, text: &str, rect: LayoutRect, size: f32, color: Rgba<u8>, centered: bool) {
+        let text_width = self.text_drawer.text_width(text, size);
+        let x = if centered { rect.x + ((rect.width - text_width) / 2.0).max(0.0) } else { rect.x };
+        let y = rect.y + (rect.height - size * 1.2) / 2.0;
+        let run = TextRun { text: text.to_string(), size, color };
+        self.text_drawer.draw_clipped(&mut self.image, &run, x as i32, y as i32, rect.width);
     }
 
-    fn get_font_size(&self, style_node: &crate::style::StyledNode) -> f32 {
-        if let Some(Value::Length(size, _)) = style_node.value("font-size") {
-            return *size;
-        }
-        16.0
-    }
-
-    fn value_to_color(&self, value: &Value) -> Rgba<u8> {
-        match value {
-            Value::Color(c) => Rgba([c.r, c.g, c.b, c.a]),
-            Value::Keyword(k) => match k.as_str() {
-                "white" => Rgba([255, 255, 255, 255]),
-                "black" => Rgba([0, 0, 0, 255]),
-                "red" => Rgba([255, 0, 0, 255]),
-                "green" => Rgba([0, 255, 0, 255]),
-                "blue" => Rgba([0, 0, 255, 255]),
-                "yellow" => Rgba([255, 255, 0, 255]),
-                "gray" => Rgba([128, 128, 128, 255]),
-                "lightgray" => Rgba([211, 211, 211, 255]),
-                "darkgray" => Rgba([169, 169, 169, 255]),
-                _ => Rgba([255, 255, 255, 255]),
-            },
-            _ => Rgba([255, 255, 255, 255]),
-        }
+    /// The empty bar of a `<progress>` or `<meter>`.
+    fn draw_track(&mut self, rect: LayoutRect) {
+        self.fill_rect(rect.x - 1.0, rect.y - 1.0, rect.width + 2.0, rect.height + 2.0, TRACK_BORDER);
+        self.fill_rect(rect.x, rect.y, rect.width, rect.height, TRACK);
     }
 
     pub fn save(&self, path: &str) -> Result<(), image::ImageError> {
         self.image.save(path)
     }
-}
\ No newline at end of file
+}
+
+fn to_rgba(color: Color) -> Rgba<u8> {
+    Rgba([color.r, color.g, color.b, color.a])
+}
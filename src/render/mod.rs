@@ -7,11 +7,17 @@ pub struct RenderEngine;
 
 impl RenderEngine {
     pub fn new() -> Self {
-        println!("  [Render] Initializing render engine...");
+        eprintln!("  [Render] Initializing render engine...");
         RenderEngine
     }
     
     pub fn render(&self) {
-        println!("  [Render] Drawing webpage to screen...");
+        eprintln!("  [Render] Drawing webpage to screen...");
     }
-}
\ No newline at end of file
+}
+
+impl Default for RenderEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
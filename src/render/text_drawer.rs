@@ -6,6 +6,17 @@ pub struct TextDrawer<'a> {
     font: FontRef<'a>,
 }
 
+/// A piece of text drawn in one size and color. Consecutive runs flow
+/// together; only whitespace inside them separates words.
+pub struct TextRun {
+    pub text: String,
+    pub size: f32,
+    pub color: Rgba<u8>,
+}
+
+/// A word made of pieces of one or more runs, as (run index, text).
+type Word<'r> = Vec<(usize, &'r str)>;
+
 impl<'a> TextDrawer<'a> {
     pub fn new() -> TextDrawer<'a> {
         let font_data = include_bytes!("../fonts/Roboto-Regular.ttf");
@@ -14,6 +25,7 @@ impl<'a> TextDrawer<'a> {
         TextDrawer { font }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn draw_text(
         &self,
         image: &mut RgbaImage,
@@ -55,6 +67,89 @@ impl<'a> TextDrawer<'a> {
         }
     }
     
+    /// Draws styled runs as one wrapped paragraph starting at `(x, y)`.
+    /// Each line is as tall as 1.5 times its largest font size.
+    pub fn draw_runs(&self, image: &mut RgbaImage, runs: &[TextRun], x: i32, y: i32, max_width: f32) {
+        let mut current_line: Vec<Word> = Vec::new();
+        let mut current_width = 0.0;
+        let mut current_y = y;
+
+        for word in split_words(runs) {
+            let space = if current_line.is_empty() {
+                0.0
+            } else {
+                self.space_width(runs[word[0].0].size)
+            };
+            let width = self.word_width(runs, &word);
+
+            if current_width + space + width > max_width && !current_line.is_empty() {
+                current_y += self.draw_line(image, runs, &current_line, x, current_y);
+                current_line.clear();
+                current_width = width;
+            } else {
+                current_width += space + width;
+            }
+            current_line.push(word);
+        }
+
+        if !current_line.is_empty() {
+            self.draw_line(image, runs, &current_line, x, current_y);
+        }
+    }
+
+    /// Draws `run` on one line from `(x, y)`, leaving out the characters
+    /// that would reach past `max_width`.
+    pub fn draw_clipped(&self, image: &mut RgbaImage, run: &TextRun, x: i32, y: i32, max_width: f32) {
+        let scale = PxScale::from(run.size);
+        let scaled_font = self.font.as_scaled(scale);
+        let mut width = 0.0;
+        let end = run.text.char_indices()
+            .find(|&(_, ch)| {
+                width += scaled_font.h_advance(scaled_font.scaled_glyph(ch).id);
+                width > max_width
+            })
+            .map_or(run.text.len(), |(index, _)| index);
+        if end > 0 {
+            draw_text_mut(image, run.color, x, y, scale, &self.font, &run.text[..end]);
+        }
+    }
+
+    /// The width of `text` on one line at font size `size`.
+    pub fn text_width(&self, text: &str, size: f32) -> f32 {
+        self.measure_text(text, &self.font.as_scaled(PxScale::from(size)))
+    }
+
+    /// Draws one line of words and returns its height.
+    fn draw_line(&self, image: &mut RgbaImage, runs: &[TextRun], words: &[Word], x: i32, y: i32) -> i32 {
+        let mut cursor = x as f32;
+        let mut max_size: f32 = 0.0;
+        for (i, word) in words.iter().enumerate() {
+            if i > 0 {
+                cursor += self.space_width(runs[word[0].0].size);
+            }
+            for &(run_index, piece) in word {
+                let run = &runs[run_index];
+                let scale = PxScale::from(run.size);
+                draw_text_mut(image, run.color, cursor as i32, y, scale, &self.font, piece);
+                cursor += self.measure_text(piece, &self.font.as_scaled(scale));
+                max_size = max_size.max(run.size);
+            }
+        }
+        (max_size * 1.5) as i32
+    }
+
+    fn word_width(&self, runs: &[TextRun], word: &Word) -> f32 {
+        word.iter()
+            .map(|&(run_index, piece)| {
+                self.measure_text(piece, &self.font.as_scaled(PxScale::from(runs[run_index].size)))
+            })
+            .sum()
+    }
+
+    fn space_width(&self, size: f32) -> f32 {
+        self.measure_text(" ", &self.font.as_scaled(PxScale::from(size)))
+    }
+
     fn measure_text(&self, text: &str, scaled_font: &ab_glyph::PxScaleFont<&FontRef>) -> f32 {
         let mut width = 0.0;
         for ch in text.chars() {
@@ -96,4 +191,36 @@ impl<'a> TextDrawer<'a> {
         
         line_count as f32 * line_height
     }
-}
\ No newline at end of file
+}
+
+impl<'a> Default for TextDrawer<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Splits runs into words at whitespace. Runs are not separated from each
+/// other, so `"(" ` followed by `"note"` forms the single word `(note`.
+fn split_words(runs: &[TextRun]) -> Vec<Word<'_>> {
+    let mut words = Vec::new();
+    let mut current: Word = Vec::new();
+    for (run_index, run) in runs.iter().enumerate() {
+        let mut rest = run.text.as_str();
+        while !rest.is_empty() {
+            let start = rest.len() - rest.trim_start().len();
+            if start > 0 && !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            rest = &rest[start..];
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            if end > 0 {
+                current.push((run_index, &rest[..end]));
+            }
+            rest = &rest[end..];
+        }
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
@@ -0,0 +1,86 @@
+use crate::dom::{ElementData, ElementRef};
+
+const FILTER_BITS: usize = 1024;
+
+/// A Bloom filter over the tag names, ids and classes of an element's
+/// ancestors. Selector matching asks it whether the ancestors a descendant
+/// or child combinator needs could exist before walking up the tree; a
+/// negative answer is certain, a positive one may be wrong.
+///
+/// The style traversal keeps one filter per level of the tree: a child's
+/// filter is its parent's with the parent added.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AncestorFilter {
+    bits: [u64; FILTER_BITS / 64],
+}
+
+impl AncestorFilter {
+    pub fn new() -> AncestorFilter {
+        AncestorFilter { bits: [0; FILTER_BITS / 64] }
+    }
+
+    /// The filter for the ancestors of `element`, built by walking up from
+    /// it. Traversals should rather extend their parent's filter with
+    /// `insert`.
+    pub fn for_element(element: &ElementRef) -> AncestorFilter {
+        let mut filter = AncestorFilter::new();
+        for ancestor in element.ancestors() {
+            if let Some(elem) = ancestor.element() {
+                filter.insert(elem);
+            }
+        }
+        filter
+    }
+
+    pub fn insert(&mut self, elem: &ElementData) {
+        self.insert_hash(tag_hash(&elem.tag_name));
+        if let Some(id) = elem.id() {
+            self.insert_hash(id_hash(id));
+        }
+        for class in elem.classes() {
+            self.insert_hash(class_hash(class));
+        }
+    }
+
+    fn insert_hash(&mut self, hash: u32) {
+        for bit in bit_positions(hash) {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    /// Whether some ancestor may have the name the hash was made from.
+    pub fn might_contain(&self, hash: u32) -> bool {
+        bit_positions(hash).iter().all(|&bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+}
+
+impl Default for AncestorFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Two bits per name, taken from the low and high halves of its hash.
+fn bit_positions(hash: u32) -> [usize; 2] {
+    [hash as usize % FILTER_BITS, (hash >> 16) as usize % FILTER_BITS]
+}
+
+pub fn tag_hash(name: &str) -> u32 {
+    hash(b't', name)
+}
+
+pub fn id_hash(id: &str) -> u32 {
+    hash(b'#', id)
+}
+
+pub fn class_hash(class: &str) -> u32 {
+    hash(b'.', class)
+}
+
+/// 32-bit FNV-1a over a kind byte and the name, so that a tag, an id and a
+/// class with the same name hash differently.
+fn hash(kind: u8, name: &str) -> u32 {
+    std::iter::once(kind).chain(name.bytes()).fold(0x811c_9dc5, |hash: u32, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
@@ -0,0 +1,362 @@
+use crate::css::{Value, Unit, Color, CalcNode, LengthContext};
+use crate::css::calc::DEFAULT_FONT_SIZE;
+use super::PropertyMap;
+use super::counters::{ListStyleType, counter_list};
+
+/// The style of an element in the form layout and rendering use: every
+/// property they read, typed, with relative lengths other than percentages
+/// resolved to px and unset properties at their initial values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComputedStyle {
+    pub display: Display,
+    pub width: LengthPercentageOrAuto,
+    pub height: LengthPercentageOrAuto,
+    pub margin: Sides<LengthPercentageOrAuto>,
+    pub padding: Sides<LengthPercentage>,
+    /// In px. Borders are drawn solid whenever they have a width;
+    /// `border-style` is not implemented.
+    pub border_width: Sides<f32>,
+    pub border_color: Color,
+    pub background_color: Color,
+    pub color: Color,
+    /// In px.
+    pub font_size: f32,
+    pub content: Content,
+    pub quotes: Quotes,
+    pub counter_reset: Vec<(String, i32)>,
+    pub counter_increment: Vec<(String, i32)>,
+    pub counter_set: Vec<(String, i32)>,
+    pub list_style_type: ListStyleType,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Display {
+    Inline,
+    Block,
+    /// A block with a `::marker` that increments the `list-item` counter.
+    ListItem,
+    /// Inline-level outside and a block inside. Without inline layout of
+    /// text, these boxes are put on lines of their own below their block's
+    /// text.
+    InlineBlock,
+    None,
+}
+
+impl Display {
+    pub fn is_block(self) -> bool {
+        matches!(self, Display::Block | Display::ListItem)
+    }
+}
+
+/// A length in px or a percentage, which layout resolves against the width
+/// of the containing block.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LengthPercentage {
+    Length(f32),
+    Percentage(f32),
+    /// A math expression mixing px and percentages.
+    Calc(Box<CalcNode>),
+}
+
+impl LengthPercentage {
+    pub const ZERO: LengthPercentage = LengthPercentage::Length(0.0);
+
+    pub fn resolve(&self, percentage_base: f32) -> f32 {
+        match *self {
+            LengthPercentage::Length(px) => px,
+            LengthPercentage::Percentage(percentage) => percentage / 100.0 * percentage_base,
+            LengthPercentage::Calc(ref node) => node.evaluate(&LengthContext {
+                percentage_base,
+                ..LengthContext::new(0.0, 0.0)
+            }),
+        }
+    }
+
+    pub fn has_percentage(&self) -> bool {
+        !matches!(self, LengthPercentage::Length(_))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LengthPercentageOrAuto {
+    Auto,
+    LengthPercentage(LengthPercentage),
+}
+
+impl LengthPercentageOrAuto {
+    pub const ZERO: LengthPercentageOrAuto = LengthPercentageOrAuto::LengthPercentage(LengthPercentage::ZERO);
+
+    pub fn is_auto(&self) -> bool {
+        *self == LengthPercentageOrAuto::Auto
+    }
+
+    /// `auto` resolves to zero.
+    pub fn resolve(&self, percentage_base: f32) -> f32 {
+        match *self {
+            LengthPercentageOrAuto::Auto => 0.0,
+            LengthPercentageOrAuto::LengthPercentage(ref length) => length.resolve(percentage_base),
+        }
+    }
+}
+
+/// One value per side of a box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sides<T> {
+    pub top: T,
+    pub right: T,
+    pub bottom: T,
+    pub left: T,
+}
+
+/// The `content` property.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Content {
+    /// No generated content; a `::marker` shows its list style.
+    Normal,
+    None,
+    Items(Vec<ContentItem>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContentItem {
+    String(String),
+    /// `attr(name)`
+    Attr(String),
+    /// `counter(name, style)`
+    Counter(String, ListStyleType),
+    /// `counters(name, separator, style)`
+    Counters(String, String, ListStyleType),
+    OpenQuote,
+    CloseQuote,
+    NoOpenQuote,
+    NoCloseQuote,
+}
+
+/// The `quotes` property.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Quotes {
+    /// Curly double quotes, then single quotes for nested quotations.
+    Auto,
+    None,
+    /// Open and close quote for each nesting level, outermost first.
+    Pairs(Vec<(String, String)>),
+}
+
+impl Default for ComputedStyle {
+    /// The initial value of every property.
+    fn default() -> ComputedStyle {
+        ComputedStyle {
+            display: Display::Inline,
+            width: LengthPercentageOrAuto::Auto,
+            height: LengthPercentageOrAuto::Auto,
+            margin: Sides::all(LengthPercentageOrAuto::ZERO),
+            padding: Sides::all(LengthPercentage::ZERO),
+            border_width: Sides::all(0.0),
+            border_color: Color::BLACK,
+            background_color: Color::TRANSPARENT,
+            color: Color::BLACK,
+            font_size: DEFAULT_FONT_SIZE,
+            content: Content::Normal,
+            quotes: Quotes::Auto,
+            counter_reset: Vec::new(),
+            counter_increment: Vec::new(),
+            counter_set: Vec::new(),
+            list_style_type: ListStyleType::Disc,
+        }
+    }
+}
+
+impl<T: Clone> Sides<T> {
+    pub fn all(value: T) -> Sides<T> {
+        Sides { top: value.clone(), right: value.clone(), bottom: value.clone(), left: value }
+    }
+}
+
+impl ComputedStyle {
+    /// Builds the typed style from the computed values of an element, after
+    /// the cascade, inheritance and `var()` substitution. `context` gives the
+    /// root font size and the viewport; `font-size` must already be in px.
+    /// Values of the wrong type are treated as if the property were unset.
+    pub fn from_values(values: &PropertyMap, context: &LengthContext) -> ComputedStyle {
+        let initial = ComputedStyle::default();
+        let font_size = match values.get("font-size") {
+            Some(Value::Length(size, Unit::Px)) => *size,
+            _ => initial.font_size,
+        };
+        let context = LengthContext { font_size, ..*context };
+        let length = |value: &Value| length_percentage(value, &context);
+        let length_or_auto = |value: &Value| match value {
+            Value::Keyword(keyword) if keyword == "auto" => Some(LengthPercentageOrAuto::Auto),
+            value => length(value).map(LengthPercentageOrAuto::LengthPercentage),
+        };
+        let border_width = |value: &Value| match value {
+            Value::Keyword(keyword) => match keyword.as_str() {
+                "thin" => Some(1.0),
+                "medium" => Some(3.0),
+                "thick" => Some(5.0),
+                _ => None,
+            },
+            value => match length(value) {
+                Some(LengthPercentage::Length(px)) => Some(px),
+                _ => None,
+            },
+        };
+
+        let color = color(values.get("color"), initial.color, initial.color);
+        ComputedStyle {
+            display: match values.get("display") {
+                Some(Value::Keyword(keyword)) => match keyword.as_str() {
+                    "block" => Display::Block,
+                    "list-item" => Display::ListItem,
+                    "inline-block" => Display::InlineBlock,
+                    "none" => Display::None,
+                    _ => Display::Inline,
+                },
+                _ => initial.display,
+            },
+            width: values.get("width").and_then(length_or_auto).unwrap_or(initial.width),
+            height: values.get("height").and_then(length_or_auto).unwrap_or(initial.height),
+            margin: sides(values, "margin", ["margin-top", "margin-right", "margin-bottom", "margin-left"], length_or_auto, initial.margin),
+            padding: sides(values, "padding", ["padding-top", "padding-right", "padding-bottom", "padding-left"], length, initial.padding),
+            border_width: sides(
+                values,
+                "border-width",
+                ["border-top-width", "border-right-width", "border-bottom-width", "border-left-width"],
+                border_width,
+                initial.border_width,
+            ),
+            border_color: self::color(values.get("border-color"), color, color),
+            background_color: self::color(
+                values.get("background-color").or_else(|| values.get("background")),
+                color,
+                initial.background_color,
+            ),
+            color,
+            font_size,
+            content: content(values.get("content")),
+            quotes: quotes(values.get("quotes")),
+            counter_reset: counter_list(values.get("counter-reset"), 0),
+            counter_increment: counter_list(values.get("counter-increment"), 1),
+            counter_set: counter_list(values.get("counter-set"), 0),
+            list_style_type: match values.get("list-style-type") {
+                Some(Value::Keyword(keyword)) => ListStyleType::from_keyword(keyword).unwrap_or(initial.list_style_type),
+                _ => initial.list_style_type,
+            },
+        }
+    }
+}
+
+/// A length with every unit but `%` resolved to px. Unitless numbers count
+/// as px.
+fn length_percentage(value: &Value, context: &LengthContext) -> Option<LengthPercentage> {
+    match *value {
+        Value::Length(percentage, Unit::Percent) => Some(LengthPercentage::Percentage(percentage)),
+        Value::Length(_, Unit::Dpi | Unit::Dpcm | Unit::Dppx) => None,
+        Value::Length(..) | Value::Number(_) => Some(LengthPercentage::Length(value.resolve(context))),
+        Value::Calc(ref node) if !node.is_number() => Some(match node.with_absolute_lengths(context) {
+            CalcNode::Leaf(Value::Length(percentage, Unit::Percent)) => LengthPercentage::Percentage(percentage),
+            CalcNode::Leaf(value) => LengthPercentage::Length(value.resolve(context)),
+            node => LengthPercentage::Calc(Box::new(node)),
+        }),
+        _ => None,
+    }
+}
+
+/// A box property set by its longhands (`margin-top`, ...) or by its
+/// shorthand with one to four values in top, right, bottom, left order.
+/// A longhand wins over the shorthand.
+fn sides<T: Clone>(
+    values: &PropertyMap,
+    shorthand: &str,
+    longhands: [&str; 4],
+    parse: impl Fn(&Value) -> Option<T>,
+    initial: Sides<T>,
+) -> Sides<T> {
+    let shorthand_values = match values.get(shorthand) {
+        Some(Value::List(items)) => items.as_slice(),
+        Some(value) => std::slice::from_ref(value),
+        None => &[],
+    };
+    let side = |index: usize, initial: T| {
+        let component = match (shorthand_values.len(), index) {
+            (1, _) => Some(0),
+            (2, index) => Some(index % 2),
+            (3, 3) => Some(1),
+            (3 | 4, index) => Some(index),
+            _ => None,
+        };
+        values.get(longhands[index])
+            .or_else(|| component.and_then(|component| shorthand_values.get(component)))
+            .and_then(&parse)
+            .unwrap_or(initial)
+    };
+    Sides {
+        top: side(0, initial.top),
+        right: side(1, initial.right),
+        bottom: side(2, initial.bottom),
+        left: side(3, initial.left),
+    }
+}
+
+/// `currentcolor` is the element's `color`.
+fn color(value: Option<&Value>, current: Color, initial: Color) -> Color {
+    match value {
+        Some(Value::Color(color)) => *color,
+        Some(Value::Keyword(keyword)) if keyword.eq_ignore_ascii_case("currentcolor") => current,
+        Some(Value::Keyword(keyword)) => Color::from_keyword(keyword).unwrap_or(initial),
+        _ => initial,
+    }
+}
+
+fn content(value: Option<&Value>) -> Content {
+    let items = match value {
+        None => return Content::Normal,
+        Some(Value::Keyword(keyword)) if keyword == "normal" => return Content::Normal,
+        Some(Value::Keyword(keyword)) if keyword == "none" => return Content::None,
+        Some(Value::List(items)) => items.as_slice(),
+        Some(value) => std::slice::from_ref(value),
+    };
+    let style = |value: Option<&Value>| match value {
+        Some(Value::Keyword(style)) => ListStyleType::from_keyword(style).unwrap_or(ListStyleType::Decimal),
+        _ => ListStyleType::Decimal,
+    };
+    Content::Items(items.iter()
+        .filter_map(|item| match item {
+            Value::String(s) => Some(ContentItem::String(s.clone())),
+            Value::Function(name, args) => match (name.as_str(), args.as_slice()) {
+                ("attr", [Value::Keyword(attribute), ..]) => Some(ContentItem::Attr(attribute.clone())),
+                ("counter", [Value::Keyword(counter), rest @ ..]) => {
+                    Some(ContentItem::Counter(counter.clone(), style(rest.first())))
+                }
+                ("counters", [Value::Keyword(counter), Value::String(separator), rest @ ..]) => {
+                    Some(ContentItem::Counters(counter.clone(), separator.clone(), style(rest.first())))
+                }
+                _ => None,
+            },
+            Value::Keyword(keyword) => match keyword.as_str() {
+                "open-quote" => Some(ContentItem::OpenQuote),
+                "close-quote" => Some(ContentItem::CloseQuote),
+                "no-open-quote" => Some(ContentItem::NoOpenQuote),
+                "no-close-quote" => Some(ContentItem::NoCloseQuote),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect())
+}
+
+fn quotes(value: Option<&Value>) -> Quotes {
+    match value {
+        Some(Value::Keyword(keyword)) if keyword == "none" => Quotes::None,
+        Some(Value::List(strings)) => {
+            let pairs: Vec<(String, String)> = strings.chunks(2)
+                .filter_map(|pair| match pair {
+                    [Value::String(open), Value::String(close)] => Some((open.clone(), close.clone())),
+                    _ => None,
+                })
+                .collect();
+            if pairs.is_empty() { Quotes::Auto } else { Quotes::Pairs(pairs) }
+        }
+        _ => Quotes::Auto,
+    }
+}
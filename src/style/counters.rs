@@ -0,0 +1,230 @@
+use crate::css::Value;
+
+/// The CSS counters in scope at the current point of a document-order walk.
+///
+/// A counter created on an element is visible to the element, its
+/// descendants and its following siblings with their descendants, so it is
+/// dropped when the element's parent is left.
+#[derive(Default)]
+pub struct CounterScopes {
+    /// Innermost counters last.
+    counters: Vec<Counter>,
+    depth: usize,
+}
+
+struct Counter {
+    name: String,
+    value: i32,
+    /// Depth of the parent of the element that created the counter.
+    scope: usize,
+}
+
+impl CounterScopes {
+    pub fn enter(&mut self) {
+        self.depth += 1;
+    }
+
+    /// Leaves the current element, ending the scope of counters its children
+    /// created.
+    pub fn leave(&mut self) {
+        while self.counters.last().is_some_and(|counter| counter.scope >= self.depth) {
+            self.counters.pop();
+        }
+        self.depth -= 1;
+    }
+
+    /// Applies `counter-reset`, `counter-increment` and `counter-set`, in
+    /// that order, for the current element. `list_item` adds the implicit
+    /// `list-item` increment of `display: list-item` elements.
+    pub fn apply(&mut self, reset: &[(String, i32)], increment: &[(String, i32)], set: &[(String, i32)], list_item: bool) {
+        for (name, value) in reset {
+            self.reset(name, *value);
+        }
+        if list_item && !increment.iter().any(|(name, _)| name == "list-item") {
+            self.increment("list-item", 1);
+        }
+        for (name, value) in increment {
+            self.increment(name, *value);
+        }
+        for (name, value) in set {
+            self.set(name, *value);
+        }
+    }
+
+    /// A reset replaces a counter created by a preceding sibling (or by the
+    /// element itself); otherwise it nests a new counter.
+    pub fn reset(&mut self, name: &str, value: i32) {
+        let scope = self.depth - 1;
+        match self.innermost_mut(name) {
+            Some(counter) if counter.scope == scope => counter.value = value,
+            _ => self.counters.push(Counter { name: name.to_string(), value, scope }),
+        }
+    }
+
+    pub fn increment(&mut self, name: &str, by: i32) {
+        self.instantiate(name).value += by;
+    }
+
+    pub fn set(&mut self, name: &str, value: i32) {
+        self.instantiate(name).value = value;
+    }
+
+    /// The innermost value of a counter; a counter not in scope reads as zero.
+    pub fn value(&self, name: &str) -> i32 {
+        self.counters.iter().rev().find(|counter| counter.name == name).map_or(0, |counter| counter.value)
+    }
+
+    /// All nested values of a counter, outermost first, as used by
+    /// `counters()`.
+    pub fn values(&self, name: &str) -> Vec<i32> {
+        let values: Vec<i32> = self.counters.iter()
+            .filter(|counter| counter.name == name)
+            .map(|counter| counter.value)
+            .collect();
+        if values.is_empty() { vec![0] } else { values }
+    }
+
+    fn innermost_mut(&mut self, name: &str) -> Option<&mut Counter> {
+        self.counters.iter_mut().rev().find(|counter| counter.name == name)
+    }
+
+    /// Incrementing or setting a counter that is not in scope creates it on
+    /// the current element first.
+    fn instantiate(&mut self, name: &str) -> &mut Counter {
+        if self.innermost_mut(name).is_none() {
+            self.reset(name, 0);
+        }
+        self.innermost_mut(name).unwrap()
+    }
+}
+
+/// Reads `name [integer]?` pairs from a counter property, with `default`
+/// for names without a number. `none` and a missing value yield nothing.
+pub fn counter_list(value: Option<&Value>, default: i32) -> Vec<(String, i32)> {
+    let items = match value {
+        Some(Value::List(items)) => items.as_slice(),
+        Some(value) => std::slice::from_ref(value),
+        None => &[],
+    };
+
+    let mut counters = Vec::new();
+    let mut iter = items.iter().peekable();
+    while let Some(item) = iter.next() {
+        let Value::Keyword(name) = item else {
+            continue;
+        };
+        if name == "none" {
+            continue;
+        }
+        let value = match iter.peek() {
+            Some(&&Value::Number(n)) => {
+                iter.next();
+                n as i32
+            }
+            _ => default,
+        };
+        counters.push((name.clone(), value));
+    }
+    counters
+}
+
+/// A `list-style-type`, used for list markers and by `counter()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListStyleType {
+    None,
+    Disc,
+    Circle,
+    Square,
+    Decimal,
+    DecimalLeadingZero,
+    LowerRoman,
+    UpperRoman,
+    /// Also `lower-latin`.
+    LowerAlpha,
+    /// Also `upper-latin`.
+    UpperAlpha,
+    LowerGreek,
+}
+
+impl ListStyleType {
+    pub fn from_keyword(keyword: &str) -> Option<ListStyleType> {
+        Some(match keyword {
+            "none" => ListStyleType::None,
+            "disc" => ListStyleType::Disc,
+            "circle" => ListStyleType::Circle,
+            "square" => ListStyleType::Square,
+            "decimal" => ListStyleType::Decimal,
+            "decimal-leading-zero" => ListStyleType::DecimalLeadingZero,
+            "lower-roman" => ListStyleType::LowerRoman,
+            "upper-roman" => ListStyleType::UpperRoman,
+            "lower-alpha" | "lower-latin" => ListStyleType::LowerAlpha,
+            "upper-alpha" | "upper-latin" => ListStyleType::UpperAlpha,
+            "lower-greek" => ListStyleType::LowerGreek,
+            _ => return None,
+        })
+    }
+
+    /// Formats a counter value. Styles that cannot represent the value fall
+    /// back to `decimal`.
+    pub fn format(self, value: i32) -> String {
+        match self {
+            ListStyleType::None => String::new(),
+            ListStyleType::Disc => "\u{2022}".to_string(),
+            ListStyleType::Circle => "\u{25e6}".to_string(),
+            ListStyleType::Square => "\u{25aa}".to_string(),
+            ListStyleType::DecimalLeadingZero if (-9..=9).contains(&value) => {
+                format!("{}0{}", if value < 0 { "-" } else { "" }, value.abs())
+            }
+            ListStyleType::LowerRoman if (1..4000).contains(&value) => roman(value).to_ascii_lowercase(),
+            ListStyleType::UpperRoman if (1..4000).contains(&value) => roman(value),
+            ListStyleType::LowerAlpha if value >= 1 => alphabetic(value, &LATIN),
+            ListStyleType::UpperAlpha if value >= 1 => alphabetic(value, &LATIN).to_ascii_uppercase(),
+            ListStyleType::LowerGreek if value >= 1 => alphabetic(value, &GREEK),
+            _ => value.to_string(),
+        }
+    }
+
+    /// Whether the style draws a symbol rather than a number.
+    pub fn is_symbolic(self) -> bool {
+        matches!(self, ListStyleType::Disc | ListStyleType::Circle | ListStyleType::Square | ListStyleType::None)
+    }
+}
+
+const LATIN: [char; 26] = [
+    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm',
+    'n', 'o', 'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z',
+];
+
+const GREEK: [char; 24] = [
+    'α', 'β', 'γ', 'δ', 'ε', 'ζ', 'η', 'θ', 'ι', 'κ', 'λ', 'μ',
+    'ν', 'ξ', 'ο', 'π', 'ρ', 'σ', 'τ', 'υ', 'φ', 'χ', 'ψ', 'ω',
+];
+
+/// Bijective base-N numbering: a, b, ..., z, aa, ab, ...
+fn alphabetic(value: i32, symbols: &[char]) -> String {
+    let base = symbols.len() as i32;
+    let mut value = value;
+    let mut result = Vec::new();
+    while value > 0 {
+        value -= 1;
+        result.push(symbols[(value % base) as usize]);
+        value /= base;
+    }
+    result.iter().rev().collect()
+}
+
+fn roman(value: i32) -> String {
+    const NUMERALS: [(i32, &str); 13] = [
+        (1000, "M"), (900, "CM"), (500, "D"), (400, "CD"), (100, "C"), (90, "XC"),
+        (50, "L"), (40, "XL"), (10, "X"), (9, "IX"), (5, "V"), (4, "IV"), (1, "I"),
+    ];
+    let mut value = value;
+    let mut result = String::new();
+    for &(amount, numeral) in &NUMERALS {
+        while value >= amount {
+            result.push_str(numeral);
+            value -= amount;
+        }
+    }
+    result
+}
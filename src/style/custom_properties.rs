@@ -0,0 +1,277 @@
+use crate::css::{Value, Unit, Parser, Stylesheet, PropertyRegistration};
+use super::PropertyMap;
+use super::properties::CssWideKeyword;
+use std::collections::HashMap;
+
+/// Custom properties registered with `@property`, by name. A later
+/// registration of the same name wins.
+pub struct PropertyRegistry<'s> {
+    registrations: HashMap<&'s str, &'s PropertyRegistration>,
+}
+
+impl<'s> PropertyRegistry<'s> {
+    pub fn new(stylesheets: &'s [Stylesheet]) -> PropertyRegistry<'s> {
+        let registrations = stylesheets.iter()
+            .flat_map(|stylesheet| &stylesheet.properties)
+            .filter(|registration| registration.initial_value.as_deref().is_none_or(|initial| {
+                syntax_accepts(&registration.syntax, initial)
+            }))
+            .map(|registration| (registration.name.as_str(), registration))
+            .collect();
+        PropertyRegistry { registrations }
+    }
+
+    fn initial_value(&self, name: &str) -> Option<String> {
+        self.registrations.get(name).and_then(|registration| registration.initial_value.clone())
+    }
+
+    /// Unregistered custom properties inherit.
+    fn inherits(&self, name: &str) -> bool {
+        self.registrations.get(name).is_none_or(|registration| registration.inherits)
+    }
+
+    fn accepts(&self, name: &str, tokens: &str) -> bool {
+        self.registrations.get(name).is_none_or(|registration| syntax_accepts(&registration.syntax, tokens))
+    }
+}
+
+pub fn is_custom_property(name: &str) -> bool {
+    name.starts_with("--")
+}
+
+/// Turns the cascaded values of an element into computed custom properties
+/// and substitutes `var()` in its other properties.
+///
+/// Custom properties keep their values as `Value::Tokens`, and may be set
+/// to `inherit`, `initial` or `unset`. Inherited ones come from `parent`,
+/// the values of the parent element or, for a pseudo-element, of its
+/// originating element. A custom property that
+/// references an invalid one, takes part in a reference cycle or does not
+/// match its registered syntax is invalid at computed-value time and falls
+/// back to its initial value. Any other property left invalid by
+/// substitution, or substituted into something that does not parse as a
+/// value, is `unset`.
+pub fn compute(values: &mut PropertyMap, parent: Option<&PropertyMap>, registry: &PropertyRegistry) {
+    let declared: HashMap<String, String> = values.iter()
+        .filter_map(|(name, value)| match value {
+            Value::Tokens(tokens) if is_custom_property(name) => Some((name.clone(), tokens.clone())),
+            _ => None,
+        })
+        .collect();
+
+    let mut names: Vec<&str> = declared.keys().map(String::as_str)
+        .chain(parent.into_iter().flat_map(|parent| parent.keys()).map(String::as_str).filter(|name| is_custom_property(name)))
+        .chain(registry.registrations.keys().copied())
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+
+    let mut resolver = Resolver {
+        declared: &declared,
+        parent,
+        registry,
+        computed: HashMap::new(),
+        stack: Vec::new(),
+    };
+    let custom: HashMap<String, String> = names.into_iter()
+        .filter_map(|name| resolver.value(name).map(|tokens| (name.to_string(), tokens)))
+        .collect();
+
+    values.retain(|name, _| !is_custom_property(name));
+    let unresolved: Vec<String> = values.iter()
+        .filter(|(_, value)| matches!(value, Value::Tokens(_)))
+        .map(|(name, _)| name.clone())
+        .collect();
+    for name in unresolved {
+        let Some(Value::Tokens(tokens)) = values.remove(&name) else {
+            continue;
+        };
+        let value = match substitute(&tokens, &mut |reference| custom.get(reference).cloned()) {
+            Some(text) => Parser::new(text).try_parse_standalone_value(),
+            None => Err("var() without a value or fallback".to_string()),
+        };
+        match value {
+            Ok(value) => {
+                values.insert(name, value);
+            }
+            Err(error) => {
+                eprintln!("  [Style] {}: {} is invalid at computed-value time ({})", name, tokens, error);
+                values.insert(name, Value::Keyword("unset".to_string()));
+            }
+        }
+    }
+    values.extend(custom.into_iter().map(|(name, tokens)| (name, Value::Tokens(tokens))));
+}
+
+/// Resolves the custom properties of one element, following `var()`
+/// references between them.
+struct Resolver<'a> {
+    declared: &'a HashMap<String, String>,
+    parent: Option<&'a PropertyMap>,
+    registry: &'a PropertyRegistry<'a>,
+    /// Declared properties resolved so far; `None` marks an invalid one.
+    computed: HashMap<String, Option<String>>,
+    /// Declared properties being resolved, innermost last.
+    stack: Vec<String>,
+}
+
+impl Resolver<'_> {
+    /// The computed value of a custom property, or `None` if it has none
+    /// (the guaranteed-invalid value).
+    fn value(&mut self, name: &str) -> Option<String> {
+        let declared = self.declared;
+        let Some(tokens) = declared.get(name) else {
+            return self.unset_value(name);
+        };
+        match CssWideKeyword::of(&Value::Tokens(tokens.clone())) {
+            Some(CssWideKeyword::Inherit) => return self.parent_value(name),
+            Some(CssWideKeyword::Initial) => return self.registry.initial_value(name),
+            Some(_) => return self.unset_value(name),
+            None => {}
+        }
+        if let Some(value) = self.computed.get(name) {
+            return value.clone().or_else(|| self.registry.initial_value(name));
+        }
+        if let Some(start) = self.stack.iter().position(|other| other == name) {
+            for member in &self.stack[start..] {
+                self.computed.insert(member.clone(), None);
+            }
+            return None;
+        }
+
+        self.stack.push(name.to_string());
+        let value = substitute(tokens, &mut |reference| self.value(reference));
+        self.stack.pop();
+
+        // A property found to be part of a cycle stays invalid even if a
+        // fallback let substitution succeed.
+        let in_cycle = self.computed.get(name) == Some(&None);
+        let value = value.filter(|tokens| !in_cycle && self.registry.accepts(name, tokens));
+        self.computed.insert(name.to_string(), value.clone());
+        value.or_else(|| self.registry.initial_value(name))
+    }
+
+    /// The value of a custom property the element does not declare, or
+    /// declares as `unset`.
+    fn unset_value(&self, name: &str) -> Option<String> {
+        if self.registry.inherits(name) {
+            self.parent_value(name)
+        } else {
+            self.registry.initial_value(name)
+        }
+    }
+
+    /// The value of the parent, for inheritance and `inherit`.
+    fn parent_value(&self, name: &str) -> Option<String> {
+        match self.parent.and_then(|parent| parent.get(name)) {
+            Some(Value::Tokens(tokens)) => Some(tokens.clone()),
+            _ => self.registry.initial_value(name),
+        }
+    }
+}
+
+/// Replaces every `var(<name> [, <fallback>]?)` in `tokens` with the value
+/// `lookup` gives for the name, or with the fallback if it gives none.
+/// Returns `None` if a reference without a fallback cannot be resolved.
+fn substitute(tokens: &str, lookup: &mut dyn FnMut(&str) -> Option<String>) -> Option<String> {
+    let mut result = String::new();
+    let mut rest = tokens;
+    while let Some(start) = find_var(rest) {
+        result.push_str(&rest[..start]);
+        let arguments_start = start + "var(".len();
+        let end = arguments_start + find_top_level(&rest[arguments_start..], ')')?;
+        let arguments = &rest[arguments_start..end];
+        let (name, fallback) = match find_top_level(arguments, ',') {
+            Some(comma) => (&arguments[..comma], Some(&arguments[comma + 1..])),
+            None => (arguments, None),
+        };
+        match lookup(name.trim()) {
+            Some(value) => result.push_str(&value),
+            None => result.push_str(&substitute(fallback?.trim(), lookup)?),
+        }
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    Some(result)
+}
+
+/// Byte offset of the first `var(` outside a string.
+fn find_var(text: &str) -> Option<usize> {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (index, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q && previous != '\\' => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if text[index..].get(..4).is_some_and(|s| s.eq_ignore_ascii_case("var("))
+                && !(previous.is_alphanumeric() || previous == '-' || previous == '_') =>
+            {
+                return Some(index);
+            }
+            None => {}
+        }
+        previous = c;
+    }
+    None
+}
+
+/// Byte offset of the first `target` outside strings and nested blocks.
+fn find_top_level(text: &str, target: char) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut previous = ' ';
+    for (index, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q && previous != '\\' => quote = None,
+            Some(_) => {}
+            None => match c {
+                c if c == target && depth == 0 => return Some(index),
+                '"' | '\'' => quote = Some(c),
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth -= 1,
+                _ => {}
+            },
+        }
+        previous = c;
+    }
+    None
+}
+
+/// Checks tokens against a registered syntax such as `<length>`,
+/// `<color> | none` or `<number>+`.
+fn syntax_accepts(syntax: &str, tokens: &str) -> bool {
+    if syntax == "*" {
+        return true;
+    }
+    let Ok(value) = Parser::new(tokens.to_string()).try_parse_standalone_value() else {
+        return false;
+    };
+    syntax.split('|').map(str::trim).any(|component| match component.strip_suffix('+') {
+        Some(single) => match value {
+            Value::List(ref items) => items.iter().all(|item| component_accepts(single, item)),
+            ref item => component_accepts(single, item),
+        },
+        None => component_accepts(component, &value),
+    })
+}
+
+fn component_accepts(component: &str, value: &Value) -> bool {
+    match (component, value) {
+        ("<length>", Value::Length(_, unit)) => !matches!(unit, Unit::Percent | Unit::Dpi | Unit::Dpcm | Unit::Dppx),
+        ("<length>" | "<length-percentage>", Value::Calc(node)) => !node.is_number(),
+        ("<number>", Value::Calc(node)) => node.is_number(),
+        ("<length>", Value::Number(n)) => *n == 0.0,
+        ("<percentage>", Value::Length(_, Unit::Percent)) => true,
+        ("<length-percentage>", _) => component_accepts("<length>", value) || component_accepts("<percentage>", value),
+        ("<number>", Value::Number(_)) => true,
+        ("<integer>", Value::Number(n)) => n.fract() == 0.0,
+        ("<resolution>", Value::Length(_, Unit::Dpi | Unit::Dpcm | Unit::Dppx)) => true,
+        ("<color>", Value::Color(_) | Value::Keyword(_)) => true,
+        ("<string>", Value::String(_)) => true,
+        ("<url>", Value::Url(_)) => true,
+        ("<custom-ident>", Value::Keyword(_)) => true,
+        (keyword, Value::Keyword(ident)) if !keyword.starts_with('<') => keyword == ident,
+        _ => false,
+    }
+}
@@ -0,0 +1,116 @@
+use crate::css::PseudoElement;
+use crate::dom::NodeType;
+use super::counters::{CounterScopes, ListStyleType};
+use super::computed::{Display, Content, ContentItem, Quotes};
+use super::StyledNode;
+
+const DEFAULT_QUOTES: &[(&str, &str)] = &[("\u{201c}", "\u{201d}"), ("\u{2018}", "\u{2019}")];
+
+/// State carried through the styled tree in document order while generated
+/// content is resolved.
+#[derive(Default)]
+struct ContentState {
+    quote_depth: usize,
+    counters: CounterScopes,
+}
+
+/// Fills in `generated_text` for every pseudo-element node. Counters and
+/// quotes carry across the whole document, so this walks the tree in
+/// document order once styles are known.
+pub fn resolve(root: &mut StyledNode) {
+    let mut state = ContentState::default();
+    resolve_node(root, &mut state);
+}
+
+fn resolve_node(node: &mut StyledNode, state: &mut ContentState) {
+    if node.style.display == Display::None || matches!(node.node.node_type, NodeType::Text(_) | NodeType::Comment(_)) {
+        return;
+    }
+
+    state.counters.enter();
+    let style = &node.style;
+    state.counters.apply(
+        &style.counter_reset,
+        &style.counter_increment,
+        &style.counter_set,
+        style.display == Display::ListItem,
+    );
+
+    match node.pseudo_element {
+        Some(PseudoElement::Marker) if uses_default_marker(node) => {}
+        Some(_) => node.generated_text = Some(content_text(node, state)),
+        None => {}
+    }
+
+    let list_style_type = node.style.list_style_type;
+    for child in &mut node.children {
+        if child.pseudo_element == Some(PseudoElement::Marker) && uses_default_marker(child) {
+            child.generated_text = Some(marker_text(state.counters.value("list-item"), list_style_type));
+        }
+        resolve_node(child, state);
+    }
+    state.counters.leave();
+}
+
+fn uses_default_marker(marker: &StyledNode) -> bool {
+    marker.style.content == Content::Normal
+}
+
+/// The default marker of a list item: a symbol, or the formatted
+/// `list-item` counter followed by a period.
+fn marker_text(value: i32, list_style_type: ListStyleType) -> String {
+    match list_style_type {
+        ListStyleType::None => String::new(),
+        style if style.is_symbolic() => format!("{} ", style.format(value)),
+        style => format!("{}. ", style.format(value)),
+    }
+}
+
+fn content_text(node: &StyledNode, state: &mut ContentState) -> String {
+    let Content::Items(ref items) = node.style.content else {
+        return String::new();
+    };
+
+    let mut text = String::new();
+    for item in items {
+        match item {
+            ContentItem::String(s) => text.push_str(s),
+            ContentItem::Attr(attribute) => {
+                if let NodeType::Element(ref elem) = node.node.node_type {
+                    text.push_str(elem.get_attribute(attribute).map_or("", |v| v.as_str()));
+                }
+            }
+            ContentItem::Counter(counter, style) => text.push_str(&style.format(state.counters.value(counter))),
+            ContentItem::Counters(counter, separator, style) => {
+                let values: Vec<String> = state.counters.values(counter).into_iter()
+                    .map(|value| style.format(value))
+                    .collect();
+                text.push_str(&values.join(separator));
+            }
+            ContentItem::OpenQuote => {
+                text.push_str(quote(&node.style.quotes, state.quote_depth).0);
+                state.quote_depth += 1;
+            }
+            ContentItem::CloseQuote => {
+                if state.quote_depth > 0 {
+                    state.quote_depth -= 1;
+                    text.push_str(quote(&node.style.quotes, state.quote_depth).1);
+                }
+            }
+            ContentItem::NoOpenQuote => state.quote_depth += 1,
+            ContentItem::NoCloseQuote => state.quote_depth = state.quote_depth.saturating_sub(1),
+        }
+    }
+    text
+}
+
+/// The quote pair for a nesting depth. The last pair is reused past the end
+/// of the list.
+fn quote(quotes: &Quotes, depth: usize) -> (&str, &str) {
+    let pairs: Vec<(&str, &str)> = match quotes {
+        Quotes::None => return ("", ""),
+        Quotes::Auto => DEFAULT_QUOTES.to_vec(),
+        Quotes::Pairs(pairs) => pairs.iter().map(|(open, close)| (open.as_str(), close.as_str())).collect(),
+    };
+    pairs[depth.min(pairs.len() - 1)]
+}
@@ -0,0 +1,182 @@
+use std::fmt::Write;
+use crate::css::{Stylesheet, Value, Origin, SourceLocation, CssFormat, MediaEnvironment};
+use crate::dom::Node;
+use super::styled_node::{CandidateDeclaration, DeclarationSource, inspect_tree};
+
+/// The computed values of one element.
+pub struct ElementInspection<'a> {
+    pub node: &'a Node,
+    /// The element and its ancestors as `tag#id.class`, from the root down,
+    /// e.g. `html > body > div#main.note`.
+    pub label: String,
+    /// Sorted by name.
+    pub properties: Vec<InspectedProperty>,
+}
+
+pub struct InspectedProperty {
+    pub name: String,
+    pub value: Value,
+    pub source: ValueSource,
+}
+
+/// Where a computed value came from.
+pub enum ValueSource {
+    /// The declaration that won the cascade, from a rule or the `style`
+    /// attribute.
+    Declared(Box<CandidateDeclaration>),
+    /// Not declared for the element; inherited from its parent.
+    Inherited,
+}
+
+/// The computed values of every element of a document and the rules they
+/// came from, for debugging stylesheets.
+pub struct StyleReport<'a> {
+    pub elements: Vec<ElementInspection<'a>>,
+    stylesheets: &'a [Stylesheet],
+}
+
+impl<'a> StyleReport<'a> {
+    pub fn new(root: &'a Node, stylesheets: &'a [Stylesheet], media: &MediaEnvironment) -> StyleReport<'a> {
+        StyleReport { elements: inspect_tree(root, stylesheets, media), stylesheets }
+    }
+
+    /// One block per element, one line per property:
+    ///
+    /// ```text
+    /// html > body > p.note
+    ///   color: #336699;     .note  (page.css:12)
+    ///   font-size: 16px;    inherited
+    /// ```
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for element in &self.elements {
+            writeln!(text, "{}", element.label).unwrap();
+            let declarations: Vec<String> = element.properties.iter()
+                .map(|property| format!("{}: {};", property.name, property.value.to_css(CssFormat::Pretty)))
+                .collect();
+            let width = declarations.iter().map(|declaration| declaration.chars().count()).max().unwrap_or(0);
+            for (declaration, property) in declarations.iter().zip(&element.properties) {
+                let source = match property.source {
+                    ValueSource::Inherited => "inherited".to_string(),
+                    ValueSource::Declared(ref declared) => match self.rule_source(declared) {
+                        Some((selector, file, line)) => format!("{}  ({}:{})", selector, file, line),
+                        None => "style attribute".to_string(),
+                    },
+                };
+                writeln!(text, "  {:width$}  {}", declaration, source, width = width).unwrap();
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// An array with an object per element:
+    ///
+    /// ```json
+    /// [{"element": "html > body > p.note", "properties": [
+    ///   {"name": "color", "value": "#336699",
+    ///    "source": {"type": "rule", "selector": ".note", "file": "page.css", "line": 12,
+    ///               "origin": "author", "important": false}},
+    ///   {"name": "font-size", "value": "16px", "source": {"type": "inherited"}}]}]
+    /// ```
+    ///
+    /// Inline declarations have the source type `"style-attribute"`.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("[");
+        for (index, element) in self.elements.iter().enumerate() {
+            if index > 0 {
+                json.push(',');
+            }
+            write!(json, "\n  {{\"element\": {}, \"properties\": [", json_string(&element.label)).unwrap();
+            for (index, property) in element.properties.iter().enumerate() {
+                if index > 0 {
+                    json.push(',');
+                }
+                let source = match property.source {
+                    ValueSource::Inherited => "{\"type\": \"inherited\"}".to_string(),
+                    ValueSource::Declared(ref declared) => match self.rule_source(declared) {
+                        Some((selector, file, line)) => format!(
+                            "{{\"type\": \"rule\", \"selector\": {}, \"file\": {}, \"line\": {}, \"origin\": {}, \"important\": {}}}",
+                            json_string(&selector),
+                            json_string(&file),
+                            line,
+                            json_string(origin_name(self.origin(declared))),
+                            declared.declaration.important,
+                        ),
+                        None => format!("{{\"type\": \"style-attribute\", \"important\": {}}}", declared.declaration.important),
+                    },
+                };
+                write!(
+                    json,
+                    "\n    {{\"name\": {}, \"value\": {}, \"source\": {}}}",
+                    json_string(&property.name),
+                    json_string(&property.value.to_css(CssFormat::Pretty)),
+                    source,
+                ).unwrap();
+            }
+            json.push_str("\n  ]}");
+        }
+        json.push_str("\n]\n");
+        json
+    }
+
+    /// The selector, file and line of the rule a declaration belongs to, or
+    /// `None` for an inline declaration.
+    fn rule_source(&self, declared: &CandidateDeclaration) -> Option<(String, String, usize)> {
+        let DeclarationSource::Stylesheet { stylesheet, rule, .. } = declared.source else {
+            return None;
+        };
+        let stylesheet = &self.stylesheets[stylesheet];
+        let location = &stylesheet.rules[rule].location;
+        let selector = declared.selector.as_ref().map_or(String::new(), |selector| selector.to_css(CssFormat::Pretty));
+        Some((selector, file_name(location, stylesheet.origin), location.line))
+    }
+
+    fn origin(&self, declared: &CandidateDeclaration) -> Origin {
+        match declared.source {
+            DeclarationSource::Stylesheet { stylesheet, .. } => self.stylesheets[stylesheet].origin,
+            DeclarationSource::Inline { .. } => Origin::Author,
+        }
+    }
+}
+
+/// A file path relative to the current directory where possible.
+/// Stylesheets not loaded from a file are named by their origin.
+fn file_name(location: &SourceLocation, origin: Origin) -> String {
+    match location.url {
+        Some(ref url) => match url.to_file_path() {
+            Ok(path) => {
+                let cwd = std::env::current_dir().unwrap_or_default();
+                path.strip_prefix(&cwd).unwrap_or(&path).display().to_string()
+            }
+            Err(_) => url.to_string(),
+        },
+        None if origin == Origin::UserAgent => "user-agent stylesheet".to_string(),
+        None => "<stylesheet>".to_string(),
+    }
+}
+
+fn origin_name(origin: Origin) -> &'static str {
+    match origin {
+        Origin::UserAgent => "user-agent",
+        Origin::User => "user",
+        Origin::Author => "author",
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use crate::css::{Stylesheet, Selector, SimpleSelector, Combinator, PseudoClass, MediaEnvironment};
+
+/// Which elements may change style when something about one element
+/// changes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Invalidation {
+    /// The element itself.
+    pub element: bool,
+    /// Its descendants.
+    pub descendants: bool,
+    /// The siblings after it and their descendants.
+    pub later_siblings: bool,
+    /// The siblings before it and their descendants, for
+    /// `:nth-last-child(An+B of S)`.
+    pub earlier_siblings: bool,
+    /// Anything in the document, for `:has()`, which reaches up to
+    /// ancestors and back to earlier siblings.
+    pub document: bool,
+}
+
+impl Invalidation {
+    const NONE: Invalidation = Invalidation {
+        element: false,
+        descendants: false,
+        later_siblings: false,
+        earlier_siblings: false,
+        document: false,
+    };
+    const ELEMENT: Invalidation = Invalidation { element: true, ..Invalidation::NONE };
+    const DESCENDANTS: Invalidation = Invalidation { descendants: true, ..Invalidation::NONE };
+    const LATER_SIBLINGS: Invalidation = Invalidation { later_siblings: true, ..Invalidation::NONE };
+    const LOCAL: Invalidation = Invalidation {
+        element: true,
+        descendants: true,
+        later_siblings: true,
+        earlier_siblings: true,
+        document: false,
+    };
+    const DOCUMENT: Invalidation = Invalidation { document: true, ..Invalidation::NONE };
+}
+
+impl std::ops::BitOrAssign for Invalidation {
+    fn bitor_assign(&mut self, other: Invalidation) {
+        self.element |= other.element;
+        self.descendants |= other.descendants;
+        self.later_siblings |= other.later_siblings;
+        self.earlier_siblings |= other.earlier_siblings;
+        self.document |= other.document;
+    }
+}
+
+/// For every class, id and attribute name some selector tests, which
+/// elements a change to it on one element may restyle. A name in the
+/// rightmost compound of a selector affects the element itself, one left of
+/// a descendant or child combinator its descendants and one left of a
+/// sibling combinator the siblings after it. Names inside `:is()`, `:not()`
+/// and the like are not placed that precisely and affect all of those as
+/// well as the siblings before it; names inside `:has()` affect the whole
+/// document.
+///
+/// Only rules that apply in the media environment the map was built for
+/// are included.
+#[derive(Debug, Default)]
+pub struct InvalidationMap {
+    classes: HashMap<String, Invalidation>,
+    ids: HashMap<String, Invalidation>,
+    attributes: HashMap<String, Invalidation>,
+    /// Whether any selector uses `:has()`, whose match can change when
+    /// children are inserted or removed anywhere below an element.
+    has_relational: bool,
+    /// Whether `:empty` or another structural pseudo-class appears left of
+    /// a sibling combinator, as in `div:empty + p`, so that inserting or
+    /// removing a child can restyle the siblings after its parent.
+    structural_before_sibling: bool,
+}
+
+impl InvalidationMap {
+    pub fn new(stylesheets: &[Stylesheet], media: &MediaEnvironment) -> InvalidationMap {
+        let mut map = InvalidationMap::default();
+        for rule in stylesheets.iter().flat_map(|stylesheet| &stylesheet.rules) {
+            if !rule.applies_in(media) {
+                continue;
+            }
+            for selector in &rule.selectors {
+                map.add_selector(selector, None);
+            }
+        }
+        map
+    }
+
+    /// Records the names in `selector`. Inside a pseudo-class argument,
+    /// `scope` overrides the position of each compound.
+    fn add_selector(&mut self, selector: &Selector, scope: Option<Invalidation>) {
+        let mut current = selector;
+        let mut position = Invalidation::ELEMENT;
+        loop {
+            let (left, combinator, compound) = match *current {
+                Selector::Simple(ref simple) => (None, None, simple),
+                Selector::Compound(ref left, combinator, ref right) => (Some(&**left), Some(combinator), right),
+            };
+            self.add_compound(compound, scope.unwrap_or(position));
+            let Some(left) = left else {
+                return;
+            };
+            position = match combinator {
+                Some(Combinator::Descendant | Combinator::Child) => Invalidation::DESCENDANTS,
+                _ => Invalidation::LATER_SIBLINGS,
+            };
+            current = left;
+        }
+    }
+
+    fn add_compound(&mut self, compound: &SimpleSelector, scope: Invalidation) {
+        if scope.later_siblings && compound.pseudo_classes.iter().any(is_structural) {
+            self.structural_before_sibling = true;
+        }
+        for class in &compound.classes {
+            *self.classes.entry(class.clone()).or_default() |= scope;
+        }
+        if let Some(ref id) = compound.id {
+            *self.ids.entry(id.clone()).or_default() |= scope;
+        }
+        for attribute in &compound.attributes {
+            *self.attributes.entry(attribute.name.clone()).or_default() |= scope;
+        }
+        for pseudo_class in &compound.pseudo_classes {
+            match *pseudo_class {
+                PseudoClass::Not(ref selectors)
+                | PseudoClass::Is(ref selectors)
+                | PseudoClass::Where(ref selectors)
+                | PseudoClass::NthChild(_, ref selectors)
+                | PseudoClass::NthLastChild(_, ref selectors) => {
+                    let mut inner = Invalidation::LOCAL;
+                    inner |= scope;
+                    for selector in selectors {
+                        self.add_selector(selector, Some(inner));
+                    }
+                }
+                PseudoClass::Has(ref relative) => {
+                    self.has_relational = true;
+                    for relative in relative {
+                        self.add_selector(&relative.selector, Some(Invalidation::DOCUMENT));
+                    }
+                }
+                PseudoClass::Checked => self.add_attributes(&["checked", "selected", "type"], scope),
+                PseudoClass::Disabled | PseudoClass::Enabled => self.add_attributes(&["disabled"], scope),
+                PseudoClass::Link | PseudoClass::Visited => self.add_attributes(&["href"], scope),
+                _ => {}
+            }
+        }
+    }
+
+    /// Pseudo-classes that look at attributes depend on them as attribute
+    /// selectors do.
+    fn add_attributes(&mut self, names: &[&str], scope: Invalidation) {
+        for name in names {
+            *self.attributes.entry(name.to_string()).or_default() |= scope;
+        }
+    }
+
+    /// What changing attribute `name` of an element from `old` to `new`
+    /// may restyle. Only the classes added or removed count for `class`,
+    /// and the old and new ids for `id`. The element itself is always
+    /// included: it may then no longer be alike the elements it shared a
+    /// style with.
+    pub fn attribute_change(&self, name: &str, old: Option<&str>, new: Option<&str>) -> Invalidation {
+        let mut invalidation = Invalidation::ELEMENT;
+        if let Some(&by_attribute) = self.attributes.get(name) {
+            invalidation |= by_attribute;
+        }
+        match name {
+            "class" => {
+                let old: Vec<&str> = old.map(|old| old.split_whitespace().collect()).unwrap_or_default();
+                let new: Vec<&str> = new.map(|new| new.split_whitespace().collect()).unwrap_or_default();
+                let changed = old.iter().filter(|class| !new.contains(class))
+                    .chain(new.iter().filter(|class| !old.contains(class)));
+                for class in changed {
+                    if let Some(&by_class) = self.classes.get(*class) {
+                        invalidation |= by_class;
+                    }
+                }
+            }
+            "id" if old != new => {
+                for id in old.into_iter().chain(new) {
+                    if let Some(&by_id) = self.ids.get(id) {
+                        invalidation |= by_id;
+                    }
+                }
+            }
+            _ => {}
+        }
+        invalidation
+    }
+
+    /// What inserting or removing a child may restyle, as a change to the
+    /// parent: the parent for `:empty`, every child for the structural
+    /// pseudo-classes and sibling combinators, and the siblings after the
+    /// parent when those depend on it being `:empty` or the like.
+    pub fn child_list_change(&self) -> Invalidation {
+        Invalidation {
+            element: true,
+            descendants: true,
+            later_siblings: self.structural_before_sibling,
+            document: self.has_relational,
+            ..Invalidation::NONE
+        }
+    }
+}
+
+/// Pseudo-classes whose match depends on an element's children or its
+/// position among its siblings.
+fn is_structural(pseudo_class: &PseudoClass) -> bool {
+    matches!(
+        pseudo_class,
+        PseudoClass::Empty
+            | PseudoClass::FirstChild
+            | PseudoClass::LastChild
+            | PseudoClass::OnlyChild
+            | PseudoClass::FirstOfType
+            | PseudoClass::LastOfType
+            | PseudoClass::OnlyOfType
+            | PseudoClass::NthChild(..)
+            | PseudoClass::NthLastChild(..)
+            | PseudoClass::NthOfType(_)
+            | PseudoClass::NthLastOfType(_)
+    )
+}
@@ -1,16 +1,49 @@
 pub mod styled_node;
+pub mod generated_content;
+pub mod counters;
+pub mod custom_properties;
+pub mod properties;
+pub mod computed;
+pub mod rule_map;
+pub mod bloom;
+pub mod sharing;
+pub mod invalidation;
+pub mod restyle;
+pub mod inspect;
+pub mod state;
 
-pub use styled_node::{StyledNode, PropertyMap, Display, style_tree};
+pub use styled_node::{
+    StyledNode, PropertyMap, CascadeLevel, CandidateDeclaration, DeclarationSource, Specificity, MatchedRule,
+    ElementStyles, style_tree, style_tree_with_statistics, restyle_tree, inspect_tree, matching_elements,
+    cascade_candidates, matching_rules, indexed_matching_rules,
+};
+pub use rule_map::RuleMap;
+pub use bloom::AncestorFilter;
+pub use sharing::StyleStatistics;
+pub use invalidation::{Invalidation, InvalidationMap};
+pub use restyle::{NodeStyle, StyledDocument};
+pub use state::add_state;
+pub use inspect::{StyleReport, ElementInspection, InspectedProperty, ValueSource};
+pub use computed::{
+    ComputedStyle, Display, LengthPercentage, LengthPercentageOrAuto, Sides, Content, ContentItem, Quotes,
+};
+pub use counters::ListStyleType;
 
 pub struct StyleEngine;
 
 impl StyleEngine {
     pub fn new() -> Self {
-        println!("  [Style] Initializing style engine...");
+        eprintln!("  [Style] Initializing style engine...");
         StyleEngine
     }
     
     pub fn apply_styles(&self) {
-        println!("  [Style] Applying styles to elements...");
+        eprintln!("  [Style] Applying styles to elements...");
     }
-}
\ No newline at end of file
+}
+
+impl Default for StyleEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
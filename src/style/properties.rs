@@ -0,0 +1,184 @@
+use crate::css::{Value, Unit, Parser, LengthContext};
+use crate::css::calc::DEFAULT_FONT_SIZE;
+use super::PropertyMap;
+use super::custom_properties::is_custom_property;
+
+/// A property the style engine knows, with whether it inherits and its
+/// initial value as CSS text.
+pub struct PropertyDefinition {
+    pub name: &'static str,
+    pub inherited: bool,
+    pub initial: &'static str,
+}
+
+const fn inherited(name: &'static str, initial: &'static str) -> PropertyDefinition {
+    PropertyDefinition { name, inherited: true, initial }
+}
+
+const fn not_inherited(name: &'static str, initial: &'static str) -> PropertyDefinition {
+    PropertyDefinition { name, inherited: false, initial }
+}
+
+pub const PROPERTIES: &[PropertyDefinition] = &[
+    inherited("color", "black"),
+    inherited("font-family", "serif"),
+    inherited("font-size", "medium"),
+    inherited("font-style", "normal"),
+    inherited("font-variant", "normal"),
+    inherited("font-weight", "normal"),
+    inherited("line-height", "normal"),
+    inherited("letter-spacing", "normal"),
+    inherited("word-spacing", "normal"),
+    inherited("text-align", "start"),
+    inherited("text-indent", "0"),
+    inherited("text-transform", "none"),
+    inherited("white-space", "normal"),
+    inherited("visibility", "visible"),
+    inherited("direction", "ltr"),
+    inherited("cursor", "auto"),
+    inherited("quotes", "auto"),
+    inherited("list-style-type", "disc"),
+    inherited("list-style-position", "outside"),
+    inherited("list-style-image", "none"),
+    not_inherited("display", "inline"),
+    not_inherited("width", "auto"),
+    not_inherited("height", "auto"),
+    not_inherited("margin", "0"),
+    not_inherited("margin-top", "0"),
+    not_inherited("margin-right", "0"),
+    not_inherited("margin-bottom", "0"),
+    not_inherited("margin-left", "0"),
+    not_inherited("padding", "0"),
+    not_inherited("padding-top", "0"),
+    not_inherited("padding-right", "0"),
+    not_inherited("padding-bottom", "0"),
+    not_inherited("padding-left", "0"),
+    not_inherited("border-width", "medium"),
+    not_inherited("border-top-width", "medium"),
+    not_inherited("border-right-width", "medium"),
+    not_inherited("border-bottom-width", "medium"),
+    not_inherited("border-left-width", "medium"),
+    not_inherited("border-style", "none"),
+    not_inherited("border-color", "currentcolor"),
+    not_inherited("background-color", "transparent"),
+    not_inherited("background", "transparent"),
+    not_inherited("content", "normal"),
+    not_inherited("counter-reset", "none"),
+    not_inherited("counter-increment", "none"),
+    not_inherited("counter-set", "none"),
+];
+
+pub fn definition(name: &str) -> Option<&'static PropertyDefinition> {
+    PROPERTIES.iter().find(|property| property.name == name)
+}
+
+/// Unknown properties do not inherit.
+pub fn is_inherited(name: &str) -> bool {
+    definition(name).is_some_and(|property| property.inherited)
+}
+
+pub fn initial_value(name: &str) -> Option<Value> {
+    definition(name).and_then(|property| Parser::new(property.initial.to_string()).parse_standalone_value())
+}
+
+/// The CSS-wide keywords every property accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CssWideKeyword {
+    Inherit,
+    Initial,
+    Unset,
+    Revert,
+    RevertLayer,
+}
+
+impl CssWideKeyword {
+    /// The keyword a value consists of, if any. Custom properties hold it as
+    /// tokens.
+    pub fn of(value: &Value) -> Option<CssWideKeyword> {
+        let text = match value {
+            Value::Keyword(keyword) => keyword.as_str(),
+            Value::Tokens(tokens) => tokens.trim(),
+            _ => return None,
+        };
+        match text.to_ascii_lowercase().as_str() {
+            "inherit" => Some(CssWideKeyword::Inherit),
+            "initial" => Some(CssWideKeyword::Initial),
+            "unset" => Some(CssWideKeyword::Unset),
+            "revert" => Some(CssWideKeyword::Revert),
+            "revert-layer" => Some(CssWideKeyword::RevertLayer),
+            _ => None,
+        }
+    }
+}
+
+/// Turns the specified values of an element into computed values:
+/// resolves `inherit`, `initial` and `unset`, copies inherited properties
+/// the element does not set from `parent` and makes `font-size` absolute,
+/// so that descendants inherit px rather than a relative size.
+///
+/// `context` holds the parent's font size and the root font size. Custom
+/// properties are left alone; `custom_properties::compute` handles them.
+/// `revert` and `revert-layer` are resolved by the cascade; if one is left
+/// here, nothing was reverted to and it acts as `unset`.
+pub fn compute(values: &mut PropertyMap, parent: Option<&PropertyMap>, context: &LengthContext) {
+    let keywords: Vec<(String, CssWideKeyword)> = values.iter()
+        .filter(|(name, _)| !is_custom_property(name))
+        .filter_map(|(name, value)| CssWideKeyword::of(value).map(|keyword| (name.clone(), keyword)))
+        .collect();
+    for (name, keyword) in keywords {
+        let inherit = match keyword {
+            CssWideKeyword::Inherit => true,
+            CssWideKeyword::Initial => false,
+            CssWideKeyword::Unset | CssWideKeyword::Revert | CssWideKeyword::RevertLayer => is_inherited(&name),
+        };
+        let value = match parent.and_then(|parent| parent.get(&name)) {
+            Some(value) if inherit => Some(value.clone()),
+            _ => initial_value(&name),
+        };
+        match value {
+            Some(value) => values.insert(name, value),
+            None => values.remove(&name),
+        };
+    }
+
+    let font_size = values.get("font-size").and_then(|size| absolute_font_size(size, context));
+    match font_size {
+        Some(size) => values.insert("font-size".to_string(), Value::Length(size, Unit::Px)),
+        None => values.remove("font-size"),
+    };
+
+    if let Some(parent) = parent {
+        for property in PROPERTIES.iter().filter(|property| property.inherited) {
+            if let (false, Some(value)) = (values.contains_key(property.name), parent.get(property.name)) {
+                values.insert(property.name.to_string(), value.clone());
+            }
+        }
+    }
+}
+
+/// A font size in px. `em`, `%` and the relative keywords refer to the
+/// parent's font size, which is `context.font_size`.
+fn absolute_font_size(size: &Value, context: &LengthContext) -> Option<f32> {
+    let parent_size = context.font_size;
+    match *size {
+        Value::Length(..) | Value::Calc(_) => Some(size.resolve(&LengthContext {
+            percentage_base: parent_size,
+            ..*context
+        })),
+        Value::Number(0.0) => Some(0.0),
+        Value::Keyword(ref keyword) => match keyword.as_str() {
+            "xx-small" => Some(DEFAULT_FONT_SIZE * 3.0 / 5.0),
+            "x-small" => Some(DEFAULT_FONT_SIZE * 3.0 / 4.0),
+            "small" => Some(DEFAULT_FONT_SIZE * 8.0 / 9.0),
+            "medium" => Some(DEFAULT_FONT_SIZE),
+            "large" => Some(DEFAULT_FONT_SIZE * 6.0 / 5.0),
+            "x-large" => Some(DEFAULT_FONT_SIZE * 3.0 / 2.0),
+            "xx-large" => Some(DEFAULT_FONT_SIZE * 2.0),
+            "xxx-large" => Some(DEFAULT_FONT_SIZE * 3.0),
+            "smaller" => Some(parent_size / 1.2),
+            "larger" => Some(parent_size * 1.2),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,219 @@
+use std::sync::Arc;
+use crate::css::{Stylesheet, PseudoElement, MediaEnvironment};
+use crate::dom::{Node, NodeType};
+use super::computed::ComputedStyle;
+use super::generated_content;
+use super::invalidation::{Invalidation, InvalidationMap};
+use super::sharing::StyleStatistics;
+use super::styled_node::{ElementStyles, restyle_tree};
+use super::StyledNode;
+
+/// The styles of one DOM node from the last style pass, and whether they
+/// are out of date. The tree of `NodeStyle`s mirrors the DOM tree.
+#[derive(Clone, Default)]
+pub struct NodeStyle {
+    /// `None` for text and comment nodes, and for elements not styled yet.
+    pub styles: Option<ElementStyles>,
+    /// The element's styles must be recomputed.
+    pub dirty: bool,
+    /// Some node below this one is dirty.
+    pub dirty_descendants: bool,
+    pub children: Vec<NodeStyle>,
+}
+
+impl NodeStyle {
+    /// Unstyled and dirty data for `node` and everything below it.
+    pub fn new(node: &Node) -> NodeStyle {
+        NodeStyle {
+            styles: None,
+            dirty: true,
+            dirty_descendants: !node.children.is_empty(),
+            children: node.children.iter().map(NodeStyle::new).collect(),
+        }
+    }
+
+    /// Marks this node and everything below it dirty.
+    pub fn mark_subtree_dirty(&mut self) {
+        self.dirty = true;
+        self.mark_descendants_dirty();
+    }
+
+    pub fn mark_descendants_dirty(&mut self) {
+        self.dirty_descendants = !self.children.is_empty();
+        self.children.iter_mut().for_each(NodeStyle::mark_subtree_dirty);
+    }
+}
+
+/// A document that keeps its styles between changes. Changes to attributes
+/// and children go through it, which marks the nodes whose style they may
+/// affect dirty according to the selectors of the stylesheets; `restyle`
+/// then recomputes only those, and the elements below one whose computed
+/// values changed.
+///
+/// Layout is not incremental: the styled tree is laid out again in full.
+pub struct StyledDocument {
+    root: Node,
+    stylesheets: Vec<Stylesheet>,
+    media: MediaEnvironment,
+    invalidation: InvalidationMap,
+    data: NodeStyle,
+}
+
+impl StyledDocument {
+    /// The document is styled in full by the first `restyle`.
+    pub fn new(root: Node, stylesheets: Vec<Stylesheet>, media: MediaEnvironment) -> StyledDocument {
+        StyledDocument {
+            invalidation: InvalidationMap::new(&stylesheets, &media),
+            data: NodeStyle::new(&root),
+            root,
+            stylesheets,
+            media,
+        }
+    }
+
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    pub fn stylesheets(&self) -> &[Stylesheet] {
+        &self.stylesheets
+    }
+
+    pub fn media(&self) -> &MediaEnvironment {
+        &self.media
+    }
+
+    /// Whether some node needs `restyle`.
+    pub fn is_dirty(&self) -> bool {
+        self.data.dirty || self.data.dirty_descendants
+    }
+
+    /// The node reached from the root by following the child indices in
+    /// `path`.
+    pub fn node(&self, path: &[usize]) -> &Node {
+        path.iter().fold(&self.root, |node, &index| &node.children[index])
+    }
+
+    /// Sets attribute `name` of the element at `path`, or removes it if
+    /// `value` is `None`.
+    pub fn set_attribute(&mut self, path: &[usize], name: &str, value: Option<&str>) {
+        let node = path.iter().fold(&mut self.root, |node, &index| &mut node.children[index]);
+        let NodeType::Element(ref mut elem) = node.node_type else {
+            panic!("set_attribute on a node that is not an element");
+        };
+        let old = match value {
+            Some(value) => elem.attributes.insert(name.to_string(), value.to_string()),
+            None => elem.attributes.remove(name),
+        };
+        if old.as_deref() == value {
+            return;
+        }
+        let invalidation = self.invalidation.attribute_change(name, old.as_deref(), value);
+        self.invalidate(path, invalidation);
+    }
+
+    /// Inserts `child` at `index` among the children of the node at `path`.
+    pub fn insert_child(&mut self, path: &[usize], index: usize, child: Node) {
+        let data = NodeStyle::new(&child);
+        let node = path.iter().fold(&mut self.root, |node, &index| &mut node.children[index]);
+        node.children.insert(index, child);
+        self.data_mut(path).children.insert(index, data);
+        self.invalidate(path, self.invalidation.child_list_change());
+    }
+
+    /// Removes and returns child `index` of the node at `path`.
+    pub fn remove_child(&mut self, path: &[usize], index: usize) -> Node {
+        let node = path.iter().fold(&mut self.root, |node, &index| &mut node.children[index]);
+        let child = node.children.remove(index);
+        self.data_mut(path).children.remove(index);
+        self.invalidate(path, self.invalidation.child_list_change());
+        child
+    }
+
+    /// Replaces the stylesheets, which restyles everything.
+    pub fn set_stylesheets(&mut self, stylesheets: Vec<Stylesheet>) {
+        self.stylesheets = stylesheets;
+        self.invalidation = InvalidationMap::new(&self.stylesheets, &self.media);
+        self.data.mark_subtree_dirty();
+    }
+
+    /// Changes the media environment, e.g. when the viewport is resized,
+    /// which restyles everything.
+    pub fn set_media(&mut self, media: MediaEnvironment) {
+        self.media = media;
+        self.invalidation = InvalidationMap::new(&self.stylesheets, &self.media);
+        self.data.mark_subtree_dirty();
+    }
+
+    /// Recomputes the styles of the dirty elements, and counts them.
+    pub fn restyle(&mut self) -> StyleStatistics {
+        if !self.is_dirty() {
+            return StyleStatistics::default();
+        }
+        restyle_tree(&self.root, &mut self.data, &self.stylesheets, &self.media)
+    }
+
+    /// The styled tree from the current styles, with generated content
+    /// resolved. Call `restyle` first after changes.
+    pub fn styled_tree(&self) -> StyledNode<'_> {
+        assert!(!self.is_dirty(), "styled_tree on a document that needs restyle");
+        let initial = Arc::new(ComputedStyle::default());
+        let mut styled_root = styled_node(&self.root, &self.data, &initial);
+        generated_content::resolve(&mut styled_root);
+        styled_root
+    }
+
+    fn data_mut(&mut self, path: &[usize]) -> &mut NodeStyle {
+        path.iter().fold(&mut self.data, |data, &index| &mut data.children[index])
+    }
+
+    /// Marks what `invalidation` says of the node at `path` dirty, and its
+    /// ancestors as having dirty descendants.
+    fn invalidate(&mut self, path: &[usize], invalidation: Invalidation) {
+        if invalidation.document {
+            self.data.mark_subtree_dirty();
+            return;
+        }
+        let Some((&index, parent_path)) = path.split_last() else {
+            // The root has no siblings.
+            let data = &mut self.data;
+            data.dirty |= invalidation.element;
+            if invalidation.descendants {
+                data.mark_descendants_dirty();
+            }
+            return;
+        };
+
+        let mut data = &mut self.data;
+        for &step in parent_path {
+            data.dirty_descendants = true;
+            data = &mut data.children[step];
+        }
+        data.dirty_descendants = true;
+        for (sibling, sibling_data) in data.children.iter_mut().enumerate() {
+            if (sibling > index && invalidation.later_siblings) || (sibling < index && invalidation.earlier_siblings) {
+                sibling_data.mark_subtree_dirty();
+            }
+        }
+        let data = &mut data.children[index];
+        data.dirty |= invalidation.element;
+        if invalidation.descendants {
+            data.mark_descendants_dirty();
+        }
+    }
+}
+
+fn styled_node<'a>(node: &'a Node, data: &NodeStyle, initial: &Arc<ComputedStyle>) -> StyledNode<'a> {
+    let Some(ref styles) = data.styles else {
+        return StyledNode::new(node, Arc::clone(initial), Vec::new());
+    };
+    let pseudo = |pseudo_element, style: &Option<Arc<ComputedStyle>>| {
+        style.as_ref().map(|style| StyledNode::pseudo(node, pseudo_element, Arc::clone(style)))
+    };
+    let mut children = Vec::new();
+    children.extend(pseudo(PseudoElement::Marker, &styles.marker));
+    children.extend(pseudo(PseudoElement::Before, &styles.before));
+    children.extend(node.children.iter().zip(&data.children).map(|(child, data)| styled_node(child, data, initial)));
+    children.extend(pseudo(PseudoElement::After, &styles.after));
+    StyledNode::new(node, Arc::clone(&styles.style), children)
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use crate::css::{Stylesheet, Rule, Selector, SimpleSelector, Combinator, MediaEnvironment};
+use crate::dom::ElementData;
+use super::bloom::{AncestorFilter, tag_hash, id_hash, class_hash};
+
+/// The selectors of a stylesheet sorted into buckets by the rightmost
+/// compound selector, so that an element is only tested against selectors
+/// that can match it. A selector goes into the bucket of its id if it has
+/// one, else of its first class, else of its tag name, else into the
+/// universal bucket.
+///
+/// Only rules that apply in the media environment the map was built for
+/// are included.
+pub struct RuleMap<'a> {
+    by_id: HashMap<&'a str, Vec<RuleEntry<'a>>>,
+    by_class: HashMap<&'a str, Vec<RuleEntry<'a>>>,
+    by_tag: HashMap<&'a str, Vec<RuleEntry<'a>>>,
+    universal: Vec<RuleEntry<'a>>,
+}
+
+/// One selector of a rule.
+pub struct RuleEntry<'a> {
+    /// Index of the rule within its stylesheet.
+    pub rule_index: usize,
+    /// Index of the selector within the rule's selector list.
+    pub selector_index: usize,
+    pub rule: &'a Rule,
+    pub selector: &'a Selector,
+    /// Hashes of the tag names, ids and classes the selector requires of
+    /// ancestors of the element, checked against an `AncestorFilter`.
+    ancestor_hashes: Vec<u32>,
+}
+
+impl<'a> RuleMap<'a> {
+    pub fn new(stylesheet: &'a Stylesheet, media: &MediaEnvironment) -> RuleMap<'a> {
+        let mut map = RuleMap {
+            by_id: HashMap::new(),
+            by_class: HashMap::new(),
+            by_tag: HashMap::new(),
+            universal: Vec::new(),
+        };
+        for (rule_index, rule) in stylesheet.rules.iter().enumerate() {
+            if !rule.applies_in(media) {
+                continue;
+            }
+            for (selector_index, selector) in rule.selectors.iter().enumerate() {
+                let entry = RuleEntry {
+                    rule_index,
+                    selector_index,
+                    rule,
+                    selector,
+                    ancestor_hashes: ancestor_hashes(selector),
+                };
+                let rightmost = rightmost_compound(selector);
+                if let Some(ref id) = rightmost.id {
+                    map.by_id.entry(id).or_default().push(entry);
+                } else if let Some(class) = rightmost.classes.first() {
+                    map.by_class.entry(class).or_default().push(entry);
+                } else if let Some(ref tag_name) = rightmost.tag_name {
+                    map.by_tag.entry(tag_name).or_default().push(entry);
+                } else {
+                    map.universal.push(entry);
+                }
+            }
+        }
+        map
+    }
+
+    /// The selectors that may match `elem`, in rule order and then selector
+    /// order. Those in buckets for other ids, classes and tags are left out,
+    /// as are those that need an ancestor `ancestors` is sure not to hold.
+    /// The rest still have to be matched.
+    pub fn candidates(&self, elem: &ElementData, ancestors: &AncestorFilter) -> Vec<&RuleEntry<'a>> {
+        let mut entries: Vec<&RuleEntry<'a>> = Vec::new();
+        if let Some(id) = elem.id() {
+            entries.extend(self.by_id.get(id.as_str()).into_iter().flatten());
+        }
+        for class in elem.classes() {
+            entries.extend(self.by_class.get(class).into_iter().flatten());
+        }
+        entries.extend(self.by_tag.get(elem.tag_name.as_str()).into_iter().flatten());
+        entries.extend(&self.universal);
+
+        entries.retain(|entry| entry.ancestor_hashes.iter().all(|&hash| ancestors.might_contain(hash)));
+        entries.sort_by_key(|entry| (entry.rule_index, entry.selector_index));
+        // An element with a class listed twice finds that bucket twice.
+        entries.dedup_by_key(|entry| (entry.rule_index, entry.selector_index));
+        entries
+    }
+}
+
+fn rightmost_compound(selector: &Selector) -> &SimpleSelector {
+    match *selector {
+        Selector::Simple(ref simple) | Selector::Compound(_, _, ref simple) => simple,
+    }
+}
+
+/// The hashes of every tag name, id and class in compound selectors that
+/// must match an ancestor of the subject: those left of a descendant or
+/// child combinator. A compound left of a sibling combinator matches a
+/// sibling instead, though the compounds further left of it are ancestors
+/// again.
+fn ancestor_hashes(selector: &Selector) -> Vec<u32> {
+    let mut hashes = Vec::new();
+    let mut current = selector;
+    let mut is_ancestor = false;
+    loop {
+        let (left, combinator, compound) = match *current {
+            Selector::Simple(ref simple) => (None, None, simple),
+            Selector::Compound(ref left, combinator, ref right) => (Some(&**left), Some(combinator), right),
+        };
+        if is_ancestor {
+            hashes.extend(compound.tag_name.iter().map(|name| tag_hash(name)));
+            hashes.extend(compound.id.iter().map(|id| id_hash(id)));
+            hashes.extend(compound.classes.iter().map(|class| class_hash(class)));
+        }
+        let Some(left) = left else {
+            return hashes;
+        };
+        is_ancestor = matches!(combinator, Some(Combinator::Descendant | Combinator::Child));
+        current = left;
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use crate::css::{Stylesheet, Selector, SimpleSelector, Combinator, PseudoClass, MediaEnvironment};
+use crate::dom::ElementData;
+use super::PropertyMap;
+
+/// How many recently styled elements an element is compared with.
+const CACHE_SIZE: usize = 32;
+
+/// What the stylesheets of a style pass make an element's style depend on,
+/// beyond its tag name, classes and ancestors: the attributes selectors test
+/// and the selectors that can tell apart elements alike in all of those.
+pub struct SharingRules<'a> {
+    /// Sorted, so that keys list attribute values in the same order.
+    attributes: Vec<&'a str>,
+    /// Selectors with pseudo-classes or sibling combinators, whose match
+    /// depends on an element's position among its siblings or its
+    /// descendants. Elements only share a style if these match both or
+    /// neither.
+    revalidation_selectors: Vec<&'a Selector>,
+}
+
+impl<'a> SharingRules<'a> {
+    pub fn new(stylesheets: &'a [Stylesheet], media: &MediaEnvironment) -> SharingRules<'a> {
+        let mut attributes = BTreeSet::new();
+        let mut revalidation_selectors = Vec::new();
+        for rule in stylesheets.iter().flat_map(|stylesheet| &stylesheet.rules) {
+            if !rule.applies_in(media) {
+                continue;
+            }
+            for selector in &rule.selectors {
+                collect_attributes(selector, &mut attributes);
+                if needs_revalidation(selector) {
+                    revalidation_selectors.push(selector);
+                }
+            }
+        }
+        SharingRules { attributes: attributes.into_iter().collect(), revalidation_selectors }
+    }
+
+    /// The key of an element whose parent has the computed values `parent`,
+    /// or `None` if it cannot share its style: elements with an `id` or a
+    /// `style` attribute are styled on their own. `revalidate` tells for
+    /// each of the revalidation selectors whether it matches the element.
+    pub fn key<'e>(
+        &self,
+        elem: &'e ElementData,
+        parent: &Arc<PropertyMap>,
+        revalidate: impl FnOnce(&[&Selector]) -> Vec<bool>,
+    ) -> Option<SharingKey<'e>> {
+        if elem.id().is_some() || elem.get_attribute("style").is_some() {
+            return None;
+        }
+        let mut classes = elem.classes();
+        classes.sort_unstable();
+        classes.dedup();
+        Some(SharingKey {
+            parent: Arc::clone(parent),
+            tag_name: &elem.tag_name,
+            classes,
+            attributes: self.attributes.iter()
+                .map(|name| elem.get_attribute(name).map(String::as_str))
+                .collect(),
+            revalidation: revalidate(&self.revalidation_selectors),
+        })
+    }
+}
+
+/// Everything that decides the style of an element without an id or inline
+/// style. Two elements with equal keys match the same rules, inherit the
+/// same values and so have the same style.
+pub struct SharingKey<'e> {
+    /// The computed values of the parent, compared by identity. Parents
+    /// with the same values object shared their style, so the ancestors of
+    /// both elements are alike for every selector that is not revalidated.
+    parent: Arc<PropertyMap>,
+    tag_name: &'e str,
+    classes: Vec<&'e str>,
+    /// The value of each attribute some selector tests.
+    attributes: Vec<Option<&'e str>>,
+    revalidation: Vec<bool>,
+}
+
+impl PartialEq for SharingKey<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.parent, &other.parent)
+            && self.tag_name == other.tag_name
+            && self.classes == other.classes
+            && self.attributes == other.attributes
+            && self.revalidation == other.revalidation
+    }
+}
+
+/// The styles of the most recently styled elements, which a following
+/// element with the same key takes instead of going through the cascade.
+/// Siblings in long lists and tables mostly hit the cache.
+pub struct StyleSharingCache<'e, T> {
+    /// Most recently used last.
+    entries: Vec<(SharingKey<'e>, T)>,
+    pub statistics: StyleStatistics,
+}
+
+impl<'e, T: Clone> StyleSharingCache<'e, T> {
+    pub fn new() -> StyleSharingCache<'e, T> {
+        StyleSharingCache { entries: Vec::new(), statistics: StyleStatistics::default() }
+    }
+
+    /// The style of an element styled earlier with the same key, counting
+    /// a hit or a miss.
+    pub fn lookup(&mut self, key: &SharingKey<'e>) -> Option<T> {
+        match self.entries.iter().rposition(|(candidate, _)| candidate == key) {
+            Some(index) => {
+                self.statistics.sharing_hits += 1;
+                let entry = self.entries.remove(index);
+                let style = entry.1.clone();
+                self.entries.push(entry);
+                Some(style)
+            }
+            None => {
+                self.statistics.sharing_misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn insert(&mut self, key: SharingKey<'e>, style: T) {
+        if self.entries.len() == CACHE_SIZE {
+            self.entries.remove(0);
+        }
+        self.entries.push((key, style));
+    }
+}
+
+impl<T: Clone> Default for StyleSharingCache<'_, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Counters of a style pass.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StyleStatistics {
+    /// Elements styled, whether through the cascade or the cache.
+    pub elements: usize,
+    /// Elements that took the style of an earlier element.
+    pub sharing_hits: usize,
+    /// Elements that could have shared a style but found none to share.
+    pub sharing_misses: usize,
+}
+
+impl StyleStatistics {
+    /// The share of cache lookups that hit, from 0 to 1.
+    pub fn hit_rate(&self) -> f32 {
+        let lookups = self.sharing_hits + self.sharing_misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.sharing_hits as f32 / lookups as f32
+        }
+    }
+}
+
+impl std::ops::AddAssign for StyleStatistics {
+    fn add_assign(&mut self, other: StyleStatistics) {
+        self.elements += other.elements;
+        self.sharing_hits += other.sharing_hits;
+        self.sharing_misses += other.sharing_misses;
+    }
+}
+
+fn collect_attributes<'a>(selector: &'a Selector, attributes: &mut BTreeSet<&'a str>) {
+    for compound in compounds(selector) {
+        attributes.extend(compound.attributes.iter().map(|attribute| attribute.name.as_str()));
+        for pseudo_class in &compound.pseudo_classes {
+            match *pseudo_class {
+                PseudoClass::Not(ref selectors)
+                | PseudoClass::Is(ref selectors)
+                | PseudoClass::Where(ref selectors)
+                | PseudoClass::NthChild(_, ref selectors)
+                | PseudoClass::NthLastChild(_, ref selectors) => {
+                    selectors.iter().for_each(|selector| collect_attributes(selector, attributes));
+                }
+                PseudoClass::Has(ref relative) => {
+                    relative.iter().for_each(|relative| collect_attributes(&relative.selector, attributes));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Whether two siblings alike in tag name, classes and attributes could
+/// still differ in matching `selector`. `:root` cannot, and `:is()`,
+/// `:where()` and `:not()` only through their arguments.
+fn needs_revalidation(selector: &Selector) -> bool {
+    let mut current = selector;
+    while let Selector::Compound(ref left, combinator, _) = *current {
+        if matches!(combinator, Combinator::NextSibling | Combinator::SubsequentSibling) {
+            return true;
+        }
+        current = left;
+    }
+    compounds(selector).iter().flat_map(|compound| &compound.pseudo_classes).any(|pseudo_class| match *pseudo_class {
+        PseudoClass::Root => false,
+        PseudoClass::Not(ref selectors) | PseudoClass::Is(ref selectors) | PseudoClass::Where(ref selectors) => {
+            selectors.iter().any(needs_revalidation)
+        }
+        _ => true,
+    })
+}
+
+/// The compound selectors of a selector, right to left.
+fn compounds(selector: &Selector) -> Vec<&SimpleSelector> {
+    let mut compounds = Vec::new();
+    let mut current = selector;
+    loop {
+        match *current {
+            Selector::Simple(ref simple) => {
+                compounds.push(simple);
+                return compounds;
+            }
+            Selector::Compound(ref left, _, ref right) => {
+                compounds.push(right);
+                current = left;
+            }
+        }
+    }
+}
@@ -0,0 +1,15 @@
+use crate::css::Selector;
+use crate::dom::{Node, ElementState};
+use super::styled_node::matching_elements;
+
+/// Adds `state` to every element that matches one of `selectors`, as
+/// `Node::add_element_state` does, to simulate interaction such as hovering
+/// a button or checking a box before styling. Returns how many elements
+/// matched.
+pub fn add_state(root: &mut Node, selectors: &[Selector], state: ElementState) -> usize {
+    let paths = matching_elements(root, selectors);
+    for path in &paths {
+        root.add_element_state(path, state);
+    }
+    paths.len()
+}
@@ -1,102 +1,999 @@
-use crate::dom::{Node, NodeType, ElementData};
-use crate::css::{Value, Stylesheet, Rule, Selector};
+use crate::dom::{Node, NodeType, ElementData, ElementRef, ElementState};
+use crate::css::{
+    Value, Stylesheet, Rule, Selector, SimpleSelector, Combinator, Declaration, Origin, Parser,
+    AttributeSelector, AttributeOperator, AttributeCase, PseudoClass, RelativeSelector, PseudoElement,
+    MediaEnvironment, LengthContext, LayerName,
+};
+use crate::css::calc::DEFAULT_FONT_SIZE;
+use super::generated_content;
+use super::computed::{ComputedStyle, Display, Content};
+use super::custom_properties::{self, PropertyRegistry};
+use super::properties::{self, CssWideKeyword};
+use super::rule_map::RuleMap;
+use super::bloom::AncestorFilter;
+use super::sharing::{SharingRules, StyleSharingCache, StyleStatistics};
+use super::restyle::NodeStyle;
+use super::inspect::{ElementInspection, InspectedProperty, ValueSource};
+use std::cmp::Reverse;
 use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
 
 pub type PropertyMap = HashMap<String, Value>;
 
 pub struct StyledNode<'a> {
+    /// The DOM node, or the originating element for a pseudo-element.
     pub node: &'a Node,
-    pub specified_values: PropertyMap,
+    pub pseudo_element: Option<PseudoElement>,
+    /// Text and comment nodes have the initial style; they are drawn with
+    /// the style of their parent.
+    /// Elements with the same style may share it.
+    pub style: Arc<ComputedStyle>,
     pub children: Vec<StyledNode<'a>>,
+    /// Text produced by the `content` property of a pseudo-element.
+    pub generated_text: Option<String>,
 }
 
 impl<'a> StyledNode<'a> {
-    pub fn new(node: &'a Node, specified_values: PropertyMap, children: Vec<StyledNode<'a>>) -> StyledNode<'a> {
+    pub fn new(node: &'a Node, style: Arc<ComputedStyle>, children: Vec<StyledNode<'a>>) -> StyledNode<'a> {
         StyledNode {
             node,
-            specified_values,
+            pseudo_element: None,
+            style,
             children,
+            generated_text: None,
         }
     }
 
-    pub fn value(&self, name: &str) -> Option<&Value> {
-        self.specified_values.get(name)
+    pub fn pseudo(node: &'a Node, pseudo_element: PseudoElement, style: Arc<ComputedStyle>) -> StyledNode<'a> {
+        StyledNode {
+            pseudo_element: Some(pseudo_element),
+            ..StyledNode::new(node, style, Vec::new())
+        }
     }
 
-    pub fn display(&self) -> Display {
-        match self.value("display") {
-            Some(Value::Keyword(s)) => match &**s {
-                "block" => Display::Block,
-                "none" => Display::None,
-                _ => Display::Inline,
-            },
-            _ => Display::Inline,
+    /// The text this node draws itself: generated content for a
+    /// pseudo-element, or the contents of a text node.
+    pub fn own_text(&self) -> Option<&str> {
+        if self.pseudo_element.is_some() {
+            return self.generated_text.as_deref();
+        }
+        match self.node.node_type {
+            NodeType::Text(ref text) => Some(text),
+            _ => None,
         }
     }
 
-    pub fn lookup(&self, name: &str, fallback_name: &str, default: &Value) -> Value {
-        self.value(name)
-            .or_else(|| self.value(fallback_name))
-            .unwrap_or(default)
-            .clone()
+    /// The nodes whose text flows inside this box: the node itself for a
+    /// pseudo-element, otherwise its text children and inline `::before` and
+    /// `::after` children.
+    pub fn text_sources(&self) -> Vec<&StyledNode<'a>> {
+        if self.pseudo_element.is_some() {
+            return vec![self];
+        }
+        self.children.iter()
+            .filter(|child| match child.pseudo_element {
+                Some(_) => child.style.display == Display::Inline,
+                None => matches!(child.node.node_type, NodeType::Text(_)),
+            })
+            .collect()
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum Display {
-    Inline,
-    Block,
-    None,
+/// Cascade origins and importance, in increasing order of precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CascadeLevel {
+    UserAgentNormal,
+    UserNormal,
+    AuthorNormal,
+    InlineNormal,
+    AuthorImportant,
+    InlineImportant,
+    UserImportant,
+    UserAgentImportant,
+}
+
+impl CascadeLevel {
+    pub fn new(origin: Origin, important: bool) -> CascadeLevel {
+        match (origin, important) {
+            (Origin::UserAgent, false) => CascadeLevel::UserAgentNormal,
+            (Origin::User, false) => CascadeLevel::UserNormal,
+            (Origin::Author, false) => CascadeLevel::AuthorNormal,
+            (Origin::Author, true) => CascadeLevel::AuthorImportant,
+            (Origin::User, true) => CascadeLevel::UserImportant,
+            (Origin::UserAgent, true) => CascadeLevel::UserAgentImportant,
+        }
+    }
+
+    pub fn inline(important: bool) -> CascadeLevel {
+        if important {
+            CascadeLevel::InlineImportant
+        } else {
+            CascadeLevel::InlineNormal
+        }
+    }
+}
+
+/// The inputs of the cascade shared by every element of a style pass.
+#[derive(Clone, Copy)]
+struct Cascade<'s> {
+    stylesheets: &'s [Stylesheet],
+    /// The rules of each stylesheet that apply in `media`, indexed.
+    rule_maps: &'s [RuleMap<'s>],
+    sharing: &'s SharingRules<'s>,
+    /// The style of text and comment nodes.
+    initial: &'s Arc<ComputedStyle>,
+    media: &'s MediaEnvironment,
+    registry: &'s PropertyRegistry<'s>,
+    layers: &'s LayerOrder,
+}
+
+impl Cascade<'_> {
+    /// The typed style layout and rendering read, from the computed values
+    /// of an element.
+    fn computed_style(&self, values: &PropertyMap, root_font_size: f32) -> ComputedStyle {
+        let context = LengthContext {
+            root_font_size,
+            ..LengthContext::new(self.media.width, self.media.height)
+        };
+        ComputedStyle::from_values(values, &context)
+    }
+}
+
+/// The position of every cascade layer among its siblings, per origin.
+/// Stylesheets of one origin share their layers, which are ordered by first
+/// declaration, so `framework.base` ranks as `[index of framework, index of
+/// base within framework]`.
+struct LayerOrder {
+    ranks: HashMap<(Origin, LayerName), Vec<usize>>,
+}
+
+/// Orders declarations of one cascade level by layer. Normal declarations in
+/// later layers win, unlayered ones win over all layers and a layer's own
+/// rules win over its sublayers; important declarations reverse all that.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum LayerPriority {
+    Normal(Vec<usize>),
+    Important(Reverse<Vec<usize>>),
+}
+
+impl LayerOrder {
+    fn new(stylesheets: &[Stylesheet]) -> LayerOrder {
+        let mut ranks: HashMap<(Origin, LayerName), Vec<usize>> = HashMap::new();
+        let mut sibling_counts: HashMap<(Origin, LayerName), usize> = HashMap::new();
+        for stylesheet in stylesheets {
+            let origin = stylesheet.origin;
+            for layer in &stylesheet.layers {
+                if ranks.contains_key(&(origin, layer.clone())) {
+                    continue;
+                }
+                let parent = LayerName(layer.0[..layer.0.len() - 1].to_vec());
+                let mut rank = ranks.get(&(origin, parent.clone())).cloned().unwrap_or_default();
+                let siblings = sibling_counts.entry((origin, parent)).or_insert(0);
+                rank.push(*siblings);
+                *siblings += 1;
+                ranks.insert((origin, layer.clone()), rank);
+            }
+        }
+        LayerOrder { ranks }
+    }
+
+    /// Each rank ends in `usize::MAX`, which stands for the layer's own
+    /// rules and sorts after all of its sublayers.
+    fn priority(&self, origin: Origin, layer: Option<&LayerName>, important: bool) -> LayerPriority {
+        let mut rank = layer
+            .and_then(|layer| self.ranks.get(&(origin, layer.clone())).cloned())
+            .unwrap_or_default();
+        rank.push(usize::MAX);
+        if important {
+            LayerPriority::Important(Reverse(rank))
+        } else {
+            LayerPriority::Normal(rank)
+        }
+    }
+}
+
+/// Stylesheets are passed in source order; a later sheet wins ties within the
+/// same cascade level and specificity. Rules inside `@media` blocks apply
+/// only when their queries match `media`. Custom properties registered with
+/// `@property` in any of the stylesheets apply to the whole tree. Within a
+/// cascade level, `@layer` order decides before specificity.
+pub fn style_tree<'a>(root: &'a Node, stylesheets: &[Stylesheet], media: &MediaEnvironment) -> StyledNode<'a> {
+    style_tree_with_statistics(root, stylesheets, media, 1).0
+}
+
+/// Like `style_tree`, but styles independent subtrees on up to `threads`
+/// threads, and also counts the elements styled and how many of them shared
+/// the style of an earlier element. The styled tree is the same for any
+/// number of threads; the sharing counters are not, as each thread has a
+/// cache of its own.
+pub fn style_tree_with_statistics<'a>(
+    root: &'a Node,
+    stylesheets: &[Stylesheet],
+    media: &MediaEnvironment,
+    threads: usize,
+) -> (StyledNode<'a>, StyleStatistics) {
+    let (mut styled_root, statistics) = with_cascade(stylesheets, media, |cascade| {
+        let mut cache = StyleSharingCache::new();
+        let styled_root = style_subtree(
+            root,
+            &ElementRef::root(root),
+            cascade,
+            None,
+            &AncestorFilter::new(),
+            &mut cache,
+            threads.max(1),
+        );
+        (styled_root, cache.statistics)
+    });
+    generated_content::resolve(&mut styled_root);
+    (styled_root, statistics)
+}
+
+/// Runs `style` with the cascade for `stylesheets` in `media`.
+fn with_cascade<R>(stylesheets: &[Stylesheet], media: &MediaEnvironment, style: impl FnOnce(&Cascade) -> R) -> R {
+    let registry = PropertyRegistry::new(stylesheets);
+    let layers = LayerOrder::new(stylesheets);
+    let rule_maps: Vec<RuleMap> = stylesheets.iter().map(|stylesheet| RuleMap::new(stylesheet, media)).collect();
+    let sharing = SharingRules::new(stylesheets, media);
+    let initial = Arc::new(ComputedStyle::default());
+    style(&Cascade {
+        stylesheets,
+        rule_maps: &rule_maps,
+        media,
+        registry: &registry,
+        layers: &layers,
+        sharing: &sharing,
+        initial: &initial,
+    })
+}
+
+/// What an element inherits from: the computed values of its parent or,
+/// for a pseudo-element, of its originating element, and the root font size
+/// `rem` refers to.
+#[derive(Clone, Copy)]
+struct Inherited<'p> {
+    values: &'p Arc<PropertyMap>,
+    root_font_size: f32,
+}
+
+/// The computed font size in px.
+fn computed_font_size(values: &PropertyMap) -> f32 {
+    match values.get("font-size") {
+        Some(Value::Length(size, _)) => *size,
+        _ => DEFAULT_FONT_SIZE,
+    }
+}
+
+/// The styles of an element and of its pseudo-elements, which elements with
+/// the same sharing key reuse.
+#[derive(Clone, PartialEq)]
+pub struct ElementStyles {
+    /// The computed values children inherit from.
+    pub values: Arc<PropertyMap>,
+    pub style: Arc<ComputedStyle>,
+    pub marker: Option<Arc<ComputedStyle>>,
+    pub before: Option<Arc<ComputedStyle>>,
+    pub after: Option<Arc<ComputedStyle>>,
 }
 
-pub fn style_tree<'a>(root: &'a Node, stylesheet: &'a Stylesheet) -> StyledNode<'a> {
-    let specified_values = match root.node_type {
-        NodeType::Element(ref elem) => specified_values(elem, stylesheet),
-        NodeType::Text(_) => HashMap::new(),
-        NodeType::Comment(_) => HashMap::new(),
+/// `parent` is `None` for the root. `ancestors` holds the ancestors of
+/// `node`. Elements look for a style to share in `cache` before going
+/// through the cascade. The subtree may be split over `threads` threads.
+fn style_subtree<'a>(
+    node: &'a Node,
+    element: &ElementRef,
+    cascade: &Cascade,
+    parent: Option<Inherited>,
+    ancestors: &AncestorFilter,
+    cache: &mut StyleSharingCache<'a, ElementStyles>,
+    threads: usize,
+) -> StyledNode<'a> {
+    let NodeType::Element(ref elem) = node.node_type else {
+        return StyledNode::new(node, Arc::clone(cascade.initial), Vec::new());
     };
+    let styles = shared_element_styles(elem, element, cascade, parent, ancestors, cache);
 
-    let children = root.children.iter()
-        .map(|child| style_tree(child, stylesheet))
-        .collect();
+    let inherited = Inherited {
+        values: &styles.values,
+        root_font_size: parent.map_or_else(|| computed_font_size(&styles.values), |parent| parent.root_font_size),
+    };
+    let mut children = Vec::new();
+    children.extend(styles.marker.map(|style| StyledNode::pseudo(node, PseudoElement::Marker, style)));
+    children.extend(styles.before.map(|style| StyledNode::pseudo(node, PseudoElement::Before, style)));
+    let mut child_ancestors = *ancestors;
+    child_ancestors.insert(elem);
+    children.extend(style_children(node, element, cascade, inherited, &child_ancestors, cache, threads));
+    children.extend(styles.after.map(|style| StyledNode::pseudo(node, PseudoElement::After, style)));
 
-    StyledNode::new(root, specified_values, children)
+    StyledNode::new(node, styles.style, children)
 }
 
-fn specified_values(elem: &ElementData, stylesheet: &Stylesheet) -> PropertyMap {
-    let mut values = HashMap::new();
-    let mut rules = matching_rules(elem, stylesheet);
+/// The styles of an element from `cache` if an alike element was styled
+/// before, else from the cascade.
+fn shared_element_styles<'a>(
+    elem: &'a ElementData,
+    element: &ElementRef,
+    cascade: &Cascade,
+    parent: Option<Inherited>,
+    ancestors: &AncestorFilter,
+    cache: &mut StyleSharingCache<'a, ElementStyles>,
+) -> ElementStyles {
+    cache.statistics.elements += 1;
+    let key = parent.and_then(|parent| cascade.sharing.key(elem, parent.values, |selectors| {
+        selectors.iter().map(|selector| matches(element, selector)).collect()
+    }));
+    if let Some(styles) = key.as_ref().and_then(|key| cache.lookup(key)) {
+        return styles;
+    }
+    let styles = element_styles(elem, element, cascade, parent, ancestors);
+    if let Some(key) = key {
+        cache.insert(key, styles.clone());
+    }
+    styles
+}
 
-    rules.sort_by(|&(a, _), &(b, _)| a.cmp(&b));
-    for (_, rule) in rules {
-        for declaration in &rule.declarations {
-            values.insert(declaration.name.clone(), declaration.value.clone());
+/// Brings the styles of the last pass over `root` in `data` up to date:
+/// dirty elements are styled again, and so are the children of every
+/// element whose computed values changed, down to where the values stop
+/// changing. Subtrees without dirty nodes are skipped. The statistics count
+/// the elements styled again.
+pub fn restyle_tree(root: &Node, data: &mut NodeStyle, stylesheets: &[Stylesheet], media: &MediaEnvironment) -> StyleStatistics {
+    with_cascade(stylesheets, media, |cascade| {
+        let mut cache = StyleSharingCache::new();
+        restyle_subtree(root, &ElementRef::root(root), data, cascade, None, &AncestorFilter::new(), &mut cache);
+        cache.statistics
+    })
+}
+
+fn restyle_subtree<'a>(
+    node: &'a Node,
+    element: &ElementRef,
+    data: &mut NodeStyle,
+    cascade: &Cascade,
+    parent: Option<Inherited>,
+    ancestors: &AncestorFilter,
+    cache: &mut StyleSharingCache<'a, ElementStyles>,
+) {
+    let NodeType::Element(ref elem) = node.node_type else {
+        data.dirty = false;
+        return;
+    };
+    if data.dirty || data.styles.is_none() {
+        let styles = shared_element_styles(elem, element, cascade, parent, ancestors, cache);
+        let old = data.styles.replace(styles);
+        let values = &data.styles.as_ref().expect("styles were just set").values;
+        if old.as_ref().map(|old| &*old.values) != Some(&**values) {
+            data.children.iter_mut().for_each(|child| child.dirty = true);
+            data.dirty_descendants = !data.children.is_empty();
+            // `rem` lengths anywhere below the root depend on its font size.
+            let root_font_size = old.as_ref().map(|old| computed_font_size(&old.values));
+            if parent.is_none() && root_font_size != Some(computed_font_size(values)) {
+                data.mark_descendants_dirty();
+            }
+        }
+    }
+    data.dirty = false;
+    if !data.dirty_descendants {
+        return;
+    }
+    data.dirty_descendants = false;
+
+    let values = Arc::clone(&data.styles.as_ref().expect("elements are styled").values);
+    let inherited = Inherited {
+        values: &values,
+        root_font_size: parent.map_or_else(|| computed_font_size(&values), |parent| parent.root_font_size),
+    };
+    let mut child_ancestors = *ancestors;
+    child_ancestors.insert(elem);
+    for (index, (child, child_data)) in node.children.iter().zip(&mut data.children).enumerate() {
+        restyle_subtree(child, &element.child(index), child_data, cascade, Some(inherited), &child_ancestors, cache);
+    }
+}
+
+/// Subtrees smaller than this many nodes are not worth a thread.
+const MIN_PARALLEL_NODES: usize = 256;
+
+/// Styles the children of `node`. With more than one thread, the children
+/// are split into runs of about equal size, which are styled on threads of
+/// their own, each with its own style sharing cache and a share of the
+/// threads in proportion to its size.
+fn style_children<'a>(
+    node: &'a Node,
+    element: &ElementRef,
+    cascade: &Cascade,
+    inherited: Inherited,
+    ancestors: &AncestorFilter,
+    cache: &mut StyleSharingCache<'a, ElementStyles>,
+    threads: usize,
+) -> Vec<StyledNode<'a>> {
+    let style_run = |run: Range<usize>, cache: &mut StyleSharingCache<'a, ElementStyles>, threads: usize| {
+        run.map(|index| {
+            style_subtree(&node.children[index], &element.child(index), cascade, Some(inherited), ancestors, cache, threads)
+        }).collect::<Vec<_>>()
+    };
+    if threads <= 1 || node.children.len() < 2 {
+        return style_run(0..node.children.len(), cache, threads);
+    }
+    let sizes: Vec<usize> = node.children.iter().map(subtree_size).collect();
+    if sizes.iter().sum::<usize>() < MIN_PARALLEL_NODES {
+        return style_run(0..node.children.len(), cache, 1);
+    }
+
+    let runs = split_into_runs(&sizes, threads);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = runs[1..].iter()
+            .map(|(run, threads)| {
+                let (run, threads) = (run.clone(), *threads);
+                scope.spawn(move || {
+                    let mut cache = StyleSharingCache::new();
+                    let children = style_run(run, &mut cache, threads);
+                    (children, cache.statistics)
+                })
+            })
+            .collect();
+        let (first_run, first_threads) = runs[0].clone();
+        let mut children = style_run(first_run, cache, first_threads);
+        for handle in handles {
+            let (run_children, statistics) = handle.join().expect("style thread panicked");
+            children.extend(run_children);
+            cache.statistics += statistics;
+        }
+        children
+    })
+}
+
+/// Splits children with the given subtree sizes into consecutive runs of
+/// about `1 / threads` of the total each, paired with the number of threads
+/// each run gets. A child bigger than that makes a run of its own and gets
+/// several threads.
+fn split_into_runs(sizes: &[usize], threads: usize) -> Vec<(Range<usize>, usize)> {
+    let total: usize = sizes.iter().sum();
+    let target = total.div_ceil(threads);
+    let mut runs = Vec::new();
+    let (mut start, mut size) = (0, 0);
+    for (index, &child_size) in sizes.iter().enumerate() {
+        size += child_size;
+        if size >= target || index == sizes.len() - 1 {
+            runs.push((start..index + 1, (size * threads / total).max(1)));
+            start = index + 1;
+            size = 0;
         }
     }
+    runs
+}
+
+fn subtree_size(node: &Node) -> usize {
+    1 + node.children.iter().map(subtree_size).sum::<usize>()
+}
+
+/// Runs the cascade for an element and its pseudo-elements. A list item
+/// gets a `::marker` unless its `content` is `none`; the marker's text comes
+/// from `content` if set, otherwise from `list-style-type` and the
+/// `list-item` counter. `::before` and `::after` are only generated if
+/// `content` is set to something other than `none` or `normal`.
+fn element_styles(
+    elem: &ElementData,
+    element: &ElementRef,
+    cascade: &Cascade,
+    parent: Option<Inherited>,
+    ancestors: &AncestorFilter,
+) -> ElementStyles {
+    let values = Arc::new(specified_values(elem, element, cascade, None, parent, ancestors));
+    let originating = Inherited {
+        values: &values,
+        root_font_size: parent.map_or_else(|| computed_font_size(&values), |parent| parent.root_font_size),
+    };
+    let style = cascade.computed_style(&values, originating.root_font_size);
+    let pseudo_element_style = |pseudo_element| {
+        let values = specified_values(elem, element, cascade, Some(pseudo_element), Some(originating), ancestors);
+        cascade.computed_style(&values, originating.root_font_size)
+    };
+
+    let marker = match style.display {
+        Display::ListItem => Some(pseudo_element_style(PseudoElement::Marker))
+            .filter(|marker| marker.content != Content::None),
+        _ => None,
+    };
+    let [before, after] = [PseudoElement::Before, PseudoElement::After].map(|pseudo_element| {
+        Some(pseudo_element_style(pseudo_element)).filter(|style| matches!(style.content, Content::Items(_)))
+    });
+    ElementStyles {
+        values: Arc::clone(&values),
+        style: Arc::new(style),
+        marker: marker.map(Arc::new),
+        before: before.map(Arc::new),
+        after: after.map(Arc::new),
+    }
+}
+
+fn specified_values(
+    elem: &ElementData,
+    element: &ElementRef,
+    cascade: &Cascade,
+    pseudo_element: Option<PseudoElement>,
+    parent: Option<Inherited>,
+    ancestors: &AncestorFilter,
+) -> PropertyMap {
+    let inline = match pseudo_element {
+        None => inline_declarations(elem),
+        Some(_) => Vec::new(),
+    };
+    let candidates = cascaded_declarations(element, pseudo_element, cascade, &inline, ancestors);
+    let mut values: PropertyMap = winning_declarations(candidates).into_iter()
+        .map(|(name, winner)| (name.to_string(), winner.declaration.value.clone()))
+        .collect();
+
+    let parent_values = parent.map(|parent| &**parent.values);
+    custom_properties::compute(&mut values, parent_values, cascade.registry);
+    let context = LengthContext {
+        font_size: parent.map_or(DEFAULT_FONT_SIZE, |parent| computed_font_size(parent.values)),
+        root_font_size: parent.map_or(DEFAULT_FONT_SIZE, |parent| parent.root_font_size),
+        ..LengthContext::new(cascade.media.width, cascade.media.height)
+    };
+    properties::compute(&mut values, parent_values, &context);
     values
 }
 
-type MatchedRule<'a> = (Specificity, &'a Rule);
+/// The declaration that wins the cascade for each property declared among
+/// `candidates`.
+fn winning_declarations<'a>(mut candidates: Vec<Candidate<'a>>) -> HashMap<&'a str, Candidate<'a>> {
+    candidates.sort_by(|a, b| b.priority.cmp(&a.priority));
+    let mut by_property: HashMap<&str, Vec<Candidate>> = HashMap::new();
+    for candidate in candidates {
+        by_property.entry(candidate.declaration.name.as_str()).or_default().push(candidate);
+    }
+    by_property.into_iter()
+        .map(|(name, candidates)| (name, cascaded_value(candidates)))
+        .collect()
+}
+
+/// The declaration that wins the cascade among those of one property,
+/// given most important first. `revert` skips the remaining declarations of
+/// its origin and `revert-layer` those of its level and layer. If nothing is
+/// left to revert to, the last `revert` itself wins and computes like
+/// `unset`.
+fn cascaded_value(candidates: Vec<Candidate>) -> Candidate {
+    let mut reverted_origins: Vec<Origin> = Vec::new();
+    let mut reverted_layers: Vec<(CascadeLevel, Option<&LayerName>)> = Vec::new();
+    let mut last_revert = None;
+    for candidate in candidates {
+        let level = candidate.priority.0;
+        if reverted_origins.contains(&candidate.origin) || reverted_layers.contains(&(level, candidate.layer)) {
+            continue;
+        }
+        match CssWideKeyword::of(&candidate.declaration.value) {
+            Some(CssWideKeyword::Revert) => reverted_origins.push(candidate.origin),
+            Some(CssWideKeyword::RevertLayer) => reverted_layers.push((level, candidate.layer)),
+            _ => return candidate,
+        }
+        last_revert = Some(candidate);
+    }
+    last_revert.expect("a property is only cascaded if it is declared")
+}
+
+/// Every declaration of `property` that applies to `element` (or to one of
+/// its pseudo-elements), ordered from the one that wins the cascade to the
+/// one with the least precedence. Values are as declared, before `var()`
+/// substitution.
+pub fn cascade_candidates(
+    element: &ElementRef,
+    pseudo_element: Option<PseudoElement>,
+    stylesheets: &[Stylesheet],
+    media: &MediaEnvironment,
+    property: &str,
+) -> Vec<CandidateDeclaration> {
+    let inline = match (pseudo_element, element.element()) {
+        (None, Some(elem)) => inline_declarations(elem),
+        _ => Vec::new(),
+    };
+    let ancestors = AncestorFilter::for_element(element);
+    with_cascade(stylesheets, media, |cascade| {
+        let mut candidates: Vec<Candidate> = cascaded_declarations(element, pseudo_element, cascade, &inline, &ancestors)
+            .into_iter()
+            .filter(|candidate| candidate.declaration.name == property)
+            .collect();
+        candidates.sort_by(|a, b| b.priority.cmp(&a.priority));
+        candidates.iter().map(Candidate::to_declaration).collect()
+    })
+}
+
+/// The computed values of every element under `root`, in document order,
+/// each with the declaration it came from.
+pub fn inspect_tree<'a>(root: &'a Node, stylesheets: &[Stylesheet], media: &MediaEnvironment) -> Vec<ElementInspection<'a>> {
+    with_cascade(stylesheets, media, |cascade| {
+        let mut elements = Vec::new();
+        inspect_subtree(root, &ElementRef::root(root), cascade, None, &AncestorFilter::new(), "", &mut elements);
+        elements
+    })
+}
+
+fn inspect_subtree<'a>(
+    node: &'a Node,
+    element: &ElementRef,
+    cascade: &Cascade,
+    parent: Option<Inherited>,
+    ancestors: &AncestorFilter,
+    parent_label: &str,
+    elements: &mut Vec<ElementInspection<'a>>,
+) {
+    let NodeType::Element(ref elem) = node.node_type else {
+        return;
+    };
+    let values = Arc::new(specified_values(elem, element, cascade, None, parent, ancestors));
+    let inline = inline_declarations(elem);
+    let winners = winning_declarations(cascaded_declarations(element, None, cascade, &inline, ancestors));
+    let mut properties: Vec<InspectedProperty> = values.iter()
+        .map(|(name, value)| InspectedProperty {
+            name: name.clone(),
+            value: value.clone(),
+            source: match winners.get(name.as_str()) {
+                Some(winner) => ValueSource::Declared(Box::new(winner.to_declaration())),
+                None => ValueSource::Inherited,
+            },
+        })
+        .collect();
+    properties.sort_by(|a, b| a.name.cmp(&b.name));
+    let label = match parent_label {
+        "" => element_label(elem),
+        _ => format!("{} > {}", parent_label, element_label(elem)),
+    };
+    elements.push(ElementInspection { node, label: label.clone(), properties });
+
+    let inherited = Inherited {
+        values: &values,
+        root_font_size: parent.map_or_else(|| computed_font_size(&values), |parent| parent.root_font_size),
+    };
+    let mut child_ancestors = *ancestors;
+    child_ancestors.insert(elem);
+    for (index, child) in node.children.iter().enumerate() {
+        inspect_subtree(child, &element.child(index), cascade, Some(inherited), &child_ancestors, &label, elements);
+    }
+}
+
+/// `tag#id.class`, as in a selector for the element.
+fn element_label(elem: &ElementData) -> String {
+    let mut label = elem.tag_name.clone();
+    if let Some(id) = elem.id() {
+        label.push('#');
+        label.push_str(id);
+    }
+    for class in elem.classes() {
+        label.push('.');
+        label.push_str(class);
+    }
+    label
+}
+
+/// A declaration competing in the cascade for one property of an element,
+/// with everything that decides its precedence.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CandidateDeclaration {
+    pub declaration: Declaration,
+    pub level: CascadeLevel,
+    pub layer: Option<LayerName>,
+    /// Specificity of the most specific selector of the rule that matches;
+    /// zero for inline declarations.
+    pub specificity: Specificity,
+    /// That selector, or `None` for inline declarations.
+    pub selector: Option<Selector>,
+    pub source: DeclarationSource,
+}
+
+/// Where a declaration was written, which breaks ties between declarations
+/// of equal level, layer and specificity: the later one wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DeclarationSource {
+    /// Indices of the stylesheet among those passed to the cascade, of the
+    /// rule within it and of the declaration within the rule.
+    Stylesheet { stylesheet: usize, rule: usize, declaration: usize },
+    /// Index of the declaration in the element's `style` attribute.
+    Inline { declaration: usize },
+}
+
+/// Sort key for a declaration: cascade level, then layer, then specificity,
+/// then source order. No two declarations share a key, so the order does not
+/// depend on how they were collected.
+type CascadePriority = (CascadeLevel, LayerPriority, Specificity, DeclarationSource);
+
+struct Candidate<'a> {
+    priority: CascadePriority,
+    /// Inline declarations belong to the author origin.
+    origin: Origin,
+    declaration: &'a Declaration,
+    layer: Option<&'a LayerName>,
+    selector: Option<&'a Selector>,
+}
+
+impl Candidate<'_> {
+    fn to_declaration(&self) -> CandidateDeclaration {
+        CandidateDeclaration {
+            declaration: self.declaration.clone(),
+            level: self.priority.0,
+            layer: self.layer.cloned(),
+            specificity: self.priority.2,
+            selector: self.selector.cloned(),
+            source: self.priority.3,
+        }
+    }
+}
 
-fn matching_rules<'a>(elem: &ElementData, stylesheet: &'a Stylesheet) -> Vec<MatchedRule<'a>> {
+fn cascaded_declarations<'a>(
+    elem: &ElementRef,
+    pseudo_element: Option<PseudoElement>,
+    cascade: &Cascade<'a>,
+    inline: &'a [Declaration],
+    ancestors: &AncestorFilter,
+) -> Vec<Candidate<'a>> {
+    let mut candidates = Vec::new();
+    for (sheet_index, (stylesheet, rules)) in cascade.stylesheets.iter().zip(cascade.rule_maps).enumerate() {
+        for (rule_index, selector, rule) in indexed_matching_rules(elem, pseudo_element, rules, ancestors) {
+            for (index, declaration) in rule.declarations.iter().enumerate() {
+                let level = CascadeLevel::new(stylesheet.origin, declaration.important);
+                let layer = cascade.layers.priority(stylesheet.origin, rule.layer.as_ref(), declaration.important);
+                let source = DeclarationSource::Stylesheet { stylesheet: sheet_index, rule: rule_index, declaration: index };
+                candidates.push(Candidate {
+                    priority: (level, layer, selector.specificity(), source),
+                    origin: stylesheet.origin,
+                    declaration,
+                    layer: rule.layer.as_ref(),
+                    selector: Some(selector),
+                });
+            }
+        }
+    }
+    for (index, declaration) in inline.iter().enumerate() {
+        let level = CascadeLevel::inline(declaration.important);
+        let layer = cascade.layers.priority(Origin::Author, None, declaration.important);
+        let source = DeclarationSource::Inline { declaration: index };
+        candidates.push(Candidate {
+            priority: (level, layer, (0, 0, 0), source),
+            origin: Origin::Author,
+            declaration,
+            layer: None,
+            selector: None,
+        });
+    }
+    candidates
+}
+
+fn inline_declarations(elem: &ElementData) -> Vec<Declaration> {
+    match elem.get_attribute("style") {
+        Some(style) => Parser::new(style.clone()).parse_inline_declarations(),
+        None => Vec::new(),
+    }
+}
+
+/// A rule that matches an element: its index in the stylesheet, the
+/// selector it matches through and the rule.
+pub type MatchedRule<'a> = (usize, &'a Selector, &'a Rule);
+
+/// The rules of `stylesheet` that match `elem` or its `pseudo_element`, in
+/// stylesheet order. This tests every rule; `indexed_matching_rules` finds
+/// the same rules through a `RuleMap`.
+pub fn matching_rules<'a>(
+    elem: &ElementRef,
+    pseudo_element: Option<PseudoElement>,
+    stylesheet: &'a Stylesheet,
+    media: &MediaEnvironment,
+) -> Vec<MatchedRule<'a>> {
     stylesheet.rules.iter()
-        .filter_map(|rule| match_rule(elem, rule))
+        .enumerate()
+        .filter(|(_, rule)| rule.applies_in(media))
+        .filter_map(|(index, rule)| {
+            match_rule(elem, pseudo_element, rule).map(|selector| (index, selector, rule))
+        })
         .collect()
 }
 
-fn match_rule<'a>(elem: &ElementData, rule: &'a Rule) -> Option<MatchedRule<'a>> {
+/// Like `matching_rules`, but only tests the selectors `rules` offers as
+/// candidates for the element. `ancestors` must hold the ancestors of
+/// `element`.
+pub fn indexed_matching_rules<'a>(
+    element: &ElementRef,
+    pseudo_element: Option<PseudoElement>,
+    rules: &RuleMap<'a>,
+    ancestors: &AncestorFilter,
+) -> Vec<MatchedRule<'a>> {
+    let Some(elem) = element.element() else {
+        return Vec::new();
+    };
+    let mut matched: Vec<MatchedRule<'a>> = Vec::new();
+    for entry in rules.candidates(elem, ancestors) {
+        if entry.selector.pseudo_element() != pseudo_element || !matches(element, entry.selector) {
+            continue;
+        }
+        match matched.last_mut() {
+            Some(last) if last.0 == entry.rule_index => {
+                if entry.selector.specificity() > last.1.specificity() {
+                    last.1 = entry.selector;
+                }
+            }
+            _ => matched.push((entry.rule_index, entry.selector, entry.rule)),
+        }
+    }
+    matched
+}
+
+/// The most specific selector of `rule` that matches, which gives the rule
+/// its specificity for this element. Of equally specific selectors the first
+/// is taken.
+fn match_rule<'a>(
+    elem: &ElementRef,
+    pseudo_element: Option<PseudoElement>,
+    rule: &'a Rule,
+) -> Option<&'a Selector> {
     rule.selectors.iter()
-        .find(|selector| matches(elem, *selector))
-        .map(|selector| (selector.specificity(), rule))
+        .filter(|selector| selector.pseudo_element() == pseudo_element && matches(elem, selector))
+        .fold(None, |best: Option<&Selector>, selector| match best {
+            Some(best) if best.specificity() >= selector.specificity() => Some(best),
+            _ => Some(selector),
+        })
 }
 
-fn matches(elem: &ElementData, selector: &Selector) -> bool {
+/// Matches right to left: the rightmost simple selector is tested against the
+/// element first, then the combinator decides which ancestors or preceding
+/// siblings the rest of the selector is tried on.
+fn matches(elem: &ElementRef, selector: &Selector) -> bool {
+    matches_anchored(elem, selector, &|_| true)
+}
+
+/// Like `matches`, but the leftmost simple selector must also satisfy
+/// `anchor`. `:has()` uses this to tie a relative selector to its subject.
+fn matches_anchored(elem: &ElementRef, selector: &Selector, anchor: &dyn Fn(&ElementRef) -> bool) -> bool {
     match *selector {
-        Selector::Simple(ref simple_selector) => matches_simple_selector(elem, simple_selector),
+        Selector::Simple(ref simple_selector) => matches_simple_selector(elem, simple_selector) && anchor(elem),
+        Selector::Compound(ref left, combinator, ref right) => {
+            if !matches_simple_selector(elem, right) {
+                return false;
+            }
+            match combinator {
+                Combinator::Descendant => elem.ancestors()
+                    .any(|ancestor| matches_anchored(&ancestor, left, anchor)),
+                Combinator::Child => elem.parent_element()
+                    .is_some_and(|parent| matches_anchored(&parent, left, anchor)),
+                Combinator::NextSibling => elem.previous_sibling_element()
+                    .is_some_and(|sibling| matches_anchored(&sibling, left, anchor)),
+                Combinator::SubsequentSibling => elem.preceding_sibling_elements()
+                    .any(|sibling| matches_anchored(&sibling, left, anchor)),
+            }
+        }
     }
 }
 
-fn matches_simple_selector(elem: &ElementData, selector: &crate::css::SimpleSelector) -> bool {
+fn matches_any(elem: &ElementRef, selectors: &[Selector]) -> bool {
+    selectors.iter().any(|selector| matches(elem, selector))
+}
+
+fn matches_simple_selector(elem: &ElementRef, selector: &SimpleSelector) -> bool {
+    let Some(data) = elem.element() else {
+        return false;
+    };
+    matches_element_data(data, selector)
+        && selector.pseudo_classes.iter().all(|pseudo_class| matches_pseudo_class(elem, pseudo_class))
+}
+
+fn matches_pseudo_class(elem: &ElementRef, pseudo_class: &PseudoClass) -> bool {
+    let same_type = |other: &ElementRef| other.element().map(|e| &e.tag_name) == elem.element().map(|e| &e.tag_name);
+    let position = |siblings: &mut dyn Iterator<Item = ElementRef>| siblings.count() as i32 + 1;
+
+    match *pseudo_class {
+        PseudoClass::Root => elem.parent.is_none(),
+        PseudoClass::Empty => elem.node.children.iter().all(|child| match child.node_type {
+            NodeType::Element(_) => false,
+            NodeType::Text(ref text) => text.is_empty(),
+            NodeType::Comment(_) => true,
+        }),
+        PseudoClass::FirstChild => elem.previous_sibling_element().is_none(),
+        PseudoClass::LastChild => elem.following_sibling_elements().next().is_none(),
+        PseudoClass::OnlyChild => {
+            elem.previous_sibling_element().is_none() && elem.following_sibling_elements().next().is_none()
+        }
+        PseudoClass::FirstOfType => !elem.preceding_sibling_elements().any(|s| same_type(&s)),
+        PseudoClass::LastOfType => !elem.following_sibling_elements().any(|s| same_type(&s)),
+        PseudoClass::OnlyOfType => {
+            !elem.preceding_sibling_elements().any(|s| same_type(&s))
+                && !elem.following_sibling_elements().any(|s| same_type(&s))
+        }
+        PseudoClass::NthChild(nth, ref of) => {
+            (of.is_empty() || matches_any(elem, of))
+                && nth.matches(position(&mut elem.preceding_sibling_elements()
+                    .filter(|s| of.is_empty() || matches_any(s, of))))
+        }
+        PseudoClass::NthLastChild(nth, ref of) => {
+            (of.is_empty() || matches_any(elem, of))
+                && nth.matches(position(&mut elem.following_sibling_elements()
+                    .filter(|s| of.is_empty() || matches_any(s, of))))
+        }
+        PseudoClass::NthOfType(nth) => {
+            nth.matches(position(&mut elem.preceding_sibling_elements().filter(|s| same_type(s))))
+        }
+        PseudoClass::NthLastOfType(nth) => {
+            nth.matches(position(&mut elem.following_sibling_elements().filter(|s| same_type(s))))
+        }
+        PseudoClass::Not(ref selectors) => !matches_any(elem, selectors),
+        PseudoClass::Is(ref selectors) | PseudoClass::Where(ref selectors) => matches_any(elem, selectors),
+        PseudoClass::Has(ref relative) => relative.iter().any(|r| matches_relative(elem, r)),
+        PseudoClass::Hover => has_state(elem, ElementState::HOVER),
+        PseudoClass::Active => has_state(elem, ElementState::ACTIVE),
+        PseudoClass::Focus => has_state(elem, ElementState::FOCUS),
+        PseudoClass::FocusWithin => has_state(elem, ElementState::FOCUS_WITHIN),
+        PseudoClass::FocusVisible => has_state(elem, ElementState::FOCUS | ElementState::FOCUS_VISIBLE),
+        PseudoClass::Target => has_state(elem, ElementState::TARGET),
+        PseudoClass::Checked => elem.element().is_some_and(ElementData::is_checked),
+        PseudoClass::Disabled => elem.element().is_some_and(|e| is_form_control(e) && is_disabled(e)),
+        PseudoClass::Enabled => elem.element().is_some_and(|e| is_form_control(e) && !is_disabled(e)),
+        PseudoClass::Link => elem.element().is_some_and(|e| is_link(e) && !e.state.contains(ElementState::VISITED)),
+        PseudoClass::Visited => elem.element().is_some_and(|e| is_link(e) && e.state.contains(ElementState::VISITED)),
+    }
+}
+
+fn has_state(elem: &ElementRef, state: ElementState) -> bool {
+    elem.element().is_some_and(|e| e.state.contains(state))
+}
+
+/// The elements `:enabled` and `:disabled` apply to.
+fn is_form_control(elem: &ElementData) -> bool {
+    matches!(&*elem.tag_name, "button" | "input" | "select" | "textarea" | "optgroup" | "option" | "fieldset")
+}
+
+fn is_disabled(elem: &ElementData) -> bool {
+    elem.state.contains(ElementState::DISABLED) || elem.get_attribute("disabled").is_some()
+}
+
+/// `:link` and `:visited` apply to `a` and `area` elements with an `href`.
+fn is_link(elem: &ElementData) -> bool {
+    matches!(&*elem.tag_name, "a" | "area") && elem.get_attribute("href").is_some()
+}
+
+/// The paths, as child indices from `root`, of the elements that match any
+/// of `selectors`, in document order.
+pub fn matching_elements(root: &Node, selectors: &[Selector]) -> Vec<Vec<usize>> {
+    fn walk(element: &ElementRef, selectors: &[Selector], path: &mut Vec<usize>, found: &mut Vec<Vec<usize>>) {
+        if element.element().is_none() {
+            return;
+        }
+        if matches_any(element, selectors) {
+            found.push(path.clone());
+        }
+        for index in 0..element.node.children.len() {
+            path.push(index);
+            walk(&element.child(index), selectors, path, found);
+            path.pop();
+        }
+    }
+    let mut found = Vec::new();
+    walk(&ElementRef::root(root), selectors, &mut Vec::new(), &mut found);
+    found
+}
+
+/// Tests a `:has()` argument: some element after or below `subject` must
+/// match the selector with its leftmost part related to `subject` by the
+/// leading combinator.
+fn matches_relative(subject: &ElementRef, relative: &RelativeSelector) -> bool {
+    let anchor = |leftmost: &ElementRef| match relative.combinator {
+        Combinator::Descendant => leftmost.ancestors().any(|a| a.is_same_node(subject)),
+        Combinator::Child => leftmost.parent_element().is_some_and(|p| p.is_same_node(subject)),
+        Combinator::NextSibling => leftmost.previous_sibling_element().is_some_and(|s| s.is_same_node(subject)),
+        Combinator::SubsequentSibling => leftmost.preceding_sibling_elements().any(|s| s.is_same_node(subject)),
+    };
+    let test = |candidate: &ElementRef| matches_anchored(candidate, &relative.selector, &anchor);
+
+    match relative.combinator {
+        Combinator::Descendant | Combinator::Child => any_descendant(subject, &test),
+        Combinator::NextSibling | Combinator::SubsequentSibling => subject.following_sibling_elements()
+            .any(|sibling| test(&sibling) || any_descendant(&sibling, &test)),
+    }
+}
+
+fn any_descendant(elem: &ElementRef, test: &dyn Fn(&ElementRef) -> bool) -> bool {
+    (0..elem.node.children.len()).any(|index| {
+        let child = elem.child(index);
+        child.element().is_some() && (test(&child) || any_descendant(&child, test))
+    })
+}
+
+fn matches_element_data(elem: &ElementData, selector: &SimpleSelector) -> bool {
     if selector.tag_name.iter().any(|name| elem.tag_name != *name) {
         return false;
     }
@@ -110,7 +1007,58 @@ fn matches_simple_selector(elem: &ElementData, selector: &crate::css::SimpleSele
         return false;
     }
 
+    if selector.attributes.iter().any(|attribute| !matches_attribute(elem, attribute)) {
+        return false;
+    }
+
     true
 }
 
-type Specificity = (usize, usize, usize);
\ No newline at end of file
+fn matches_attribute(elem: &ElementData, selector: &AttributeSelector) -> bool {
+    let Some(actual) = elem.get_attribute(&selector.name) else {
+        return false;
+    };
+    let Some(ref matcher) = selector.matcher else {
+        return true;
+    };
+
+    let case_insensitive = match matcher.case {
+        AttributeCase::Insensitive => true,
+        AttributeCase::Sensitive => false,
+        AttributeCase::Default => CASE_INSENSITIVE_ATTRIBUTES.contains(&selector.name.as_str()),
+    };
+    let (actual, expected) = if case_insensitive {
+        (actual.to_ascii_lowercase(), matcher.value.to_ascii_lowercase())
+    } else {
+        (actual.clone(), matcher.value.clone())
+    };
+
+    match matcher.operator {
+        AttributeOperator::Equals => actual == expected,
+        AttributeOperator::Includes => {
+            !expected.is_empty()
+                && !expected.contains(char::is_whitespace)
+                && actual.split_whitespace().any(|word| word == expected)
+        }
+        AttributeOperator::DashMatch => {
+            actual == expected
+                || actual.strip_prefix(&expected).is_some_and(|rest| rest.starts_with('-'))
+        }
+        AttributeOperator::Prefix => !expected.is_empty() && actual.starts_with(&expected),
+        AttributeOperator::Suffix => !expected.is_empty() && actual.ends_with(&expected),
+        AttributeOperator::Substring => !expected.is_empty() && actual.contains(&expected),
+    }
+}
+
+/// Attributes whose values HTML compares ASCII case-insensitively when a
+/// selector has no `i` or `s` flag.
+const CASE_INSENSITIVE_ATTRIBUTES: &[&str] = &[
+    "accept", "accept-charset", "align", "alink", "axis", "bgcolor", "charset", "checked",
+    "clear", "codetype", "color", "compact", "declare", "defer", "dir", "direction",
+    "disabled", "enctype", "face", "frame", "hreflang", "http-equiv", "lang", "language",
+    "link", "media", "method", "multiple", "nohref", "noresize", "noshade", "nowrap",
+    "readonly", "rel", "rev", "rules", "scope", "scrolling", "selected", "shape", "target",
+    "text", "type", "valign", "valuetype", "vlink",
+];
+
+pub type Specificity = (usize, usize, usize);
\ No newline at end of file
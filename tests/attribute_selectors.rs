@@ -0,0 +1,70 @@
+mod common;
+
+use common::matching_ids;
+use ukechug::css::{Parser, Selector, AttributeSelector, AttributeMatcher, AttributeOperator, AttributeCase};
+
+const PAGE: &str = "
+    <div>
+        <a id=\"secure\" href=\"https://example.com/docs\" class=\"nav link\" lang=\"en-US\">Docs</a>
+        <a id=\"plain\" href=\"http://example.com/index.html\" lang=\"EN\">Home</a>
+        <a id=\"anchor\" name=\"top\">Top</a>
+        <input id=\"check\" type=\"CheckBox\" data-state=\"open\">
+        <input id=\"text\" type=\"text\" data-state=\"Open\" disabled>
+        <div id=\"menu\" data-state=\"open closed\" lang=\"fr\"></div>
+    </div>
+";
+
+#[test]
+fn presence() {
+    assert_eq!(matching_ids(PAGE, "[href]"), ["secure", "plain"]);
+    assert_eq!(matching_ids(PAGE, "a[name]"), ["anchor"]);
+    assert_eq!(matching_ids(PAGE, "input[disabled]"), ["text"]);
+}
+
+#[test]
+fn operators() {
+    assert_eq!(matching_ids(PAGE, "[data-state=\"open\"]"), ["check"]);
+    assert_eq!(matching_ids(PAGE, "[data-state~=closed]"), ["menu"]);
+    assert_eq!(matching_ids(PAGE, "[lang|=en]"), ["secure", "plain"]);
+    assert_eq!(matching_ids(PAGE, "a[href^=\"https\"]"), ["secure"]);
+    assert_eq!(matching_ids(PAGE, "[href$='.html']"), ["plain"]);
+    assert_eq!(matching_ids(PAGE, "[href*=example]"), ["secure", "plain"]);
+}
+
+#[test]
+fn empty_values_match_only_with_equals() {
+    let page = "<div><p id=\"empty\" title=\"\"></p><p id=\"full\" title=\"x\"></p></div>";
+    assert_eq!(matching_ids(page, "[title=\"\"]"), ["empty"]);
+    for operator in ["~=", "^=", "$=", "*="] {
+        assert!(matching_ids(page, &format!("[title{}\"\"]", operator)).is_empty(), "{}", operator);
+    }
+    assert!(matching_ids(PAGE, "[data-state~=\"open closed\"]").is_empty());
+}
+
+#[test]
+fn case_flags() {
+    assert_eq!(matching_ids(PAGE, "[data-state=open i]"), ["check", "text"]);
+    assert_eq!(matching_ids(PAGE, "[data-state^=OP i]"), ["check", "text", "menu"]);
+    // HTML compares `type` and `lang` case-insensitively unless `s` is given.
+    assert_eq!(matching_ids(PAGE, "input[type=checkbox]"), ["check"]);
+    assert!(matching_ids(PAGE, "input[type=checkbox s]").is_empty());
+    assert_eq!(matching_ids(PAGE, "[lang=en]"), ["plain"]);
+    assert!(matching_ids(PAGE, "[data-state=OPEN]").is_empty());
+}
+
+#[test]
+fn parsing() {
+    let selectors = Parser::new("a[href^=\"https\" i]".to_string()).parse_selector_list();
+    let Selector::Simple(ref simple) = selectors[0] else {
+        panic!("not a simple selector");
+    };
+    assert_eq!(simple.attributes, [AttributeSelector {
+        name: "href".to_string(),
+        matcher: Some(AttributeMatcher {
+            operator: AttributeOperator::Prefix,
+            value: "https".to_string(),
+            case: AttributeCase::Insensitive,
+        }),
+    }]);
+    assert_eq!(selectors[0].specificity(), (0, 1, 1));
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use common::{stylesheet, styles_with, color};
+use ukechug::css::{Parser, Stylesheet, Origin, Value, PseudoElement, MediaEnvironment, user_agent_stylesheet};
+use ukechug::dom::ElementRef;
+use ukechug::html::HTMLParser;
+use ukechug::style::{CandidateDeclaration, CascadeLevel, DeclarationSource, cascade_candidates};
+
+const PAGE: &str = "<html><body><p id=\"target\" class=\"note\" style=\"color: yellow; color: white\">Text</p></body></html>";
+
+/// The candidates for `property` on `#target` or its `pseudo_element`,
+/// winner first.
+fn pseudo_element_candidates(
+    stylesheets: &[Stylesheet],
+    pseudo_element: Option<PseudoElement>,
+    property: &str,
+) -> Vec<CandidateDeclaration> {
+    let root = HTMLParser::new().parse(PAGE);
+    let html = ElementRef::root(&root);
+    let body = html.child(0);
+    let paragraph = body.child(0);
+    cascade_candidates(&paragraph, pseudo_element, stylesheets, &MediaEnvironment::new(800.0, 600.0), property)
+}
+
+fn candidates(stylesheets: &[Stylesheet], property: &str) -> Vec<CandidateDeclaration> {
+    pseudo_element_candidates(stylesheets, None, property)
+}
+
+fn keyword(candidate: &CandidateDeclaration) -> &str {
+    match candidate.declaration.value {
+        Value::Keyword(ref keyword) => keyword,
+        ref value => panic!("not a keyword: {:?}", value),
+    }
+}
+
+#[test]
+fn candidates_are_ordered_from_the_winner() {
+    let stylesheets = [
+        stylesheet("p { color: red } .note { color: green } p { color: blue !important }")
+            .with_origin(Origin::User),
+        stylesheet("#target { color: black } p.note { color: gray; color: lightgray } p { color: darkgray !important }"),
+    ];
+    let candidates = candidates(&stylesheets, "color");
+    let keywords: Vec<&str> = candidates.iter().map(keyword).collect();
+    assert_eq!(keywords, ["blue", "darkgray", "white", "yellow", "black", "lightgray", "gray", "green", "red"]);
+
+    assert_eq!(candidates[0].level, CascadeLevel::UserImportant);
+    assert_eq!(candidates[1].level, CascadeLevel::AuthorImportant);
+    assert_eq!(candidates[2].level, CascadeLevel::InlineNormal);
+    assert_eq!(candidates[2].source, DeclarationSource::Inline { declaration: 1 });
+    assert_eq!(candidates[2].selector, None);
+    assert_eq!(candidates[4].specificity, (1, 0, 0));
+    assert_eq!(candidates[5].source, DeclarationSource::Stylesheet { stylesheet: 1, rule: 1, declaration: 1 });
+    assert_eq!(candidates[6].source, DeclarationSource::Stylesheet { stylesheet: 1, rule: 1, declaration: 0 });
+    assert_eq!(candidates[8].level, CascadeLevel::UserNormal);
+}
+
+#[test]
+fn the_most_specific_matching_selector_of_a_rule_counts() {
+    // Selectors keep their source order; the rule takes the specificity of
+    // the most specific one that matches.
+    let stylesheets = [stylesheet("div, p.note, #missing, p { color: red }")];
+    let rule = &stylesheets[0].rules[0];
+    let specificities: Vec<_> = rule.selectors.iter().map(|selector| selector.specificity()).collect();
+    assert_eq!(specificities, [(0, 0, 1), (0, 1, 1), (1, 0, 0), (0, 0, 1)]);
+
+    let candidates = candidates(&stylesheets, "color");
+    assert_eq!(candidates.last().unwrap().specificity, (0, 1, 1));
+    let selector = candidates.last().unwrap().selector.clone();
+    assert_eq!(selector, Some(Parser::new("p.note".to_string()).parse_selector_list().remove(0)));
+}
+
+#[test]
+fn later_stylesheets_win_ties() {
+    let html = "<html><body><p id=\"target\">Text</p></body></html>";
+    let media = MediaEnvironment::new(800.0, 600.0);
+    let user_agent = user_agent_stylesheet();
+    let first = stylesheet("p { color: red }");
+    let second = stylesheet("p { color: blue }");
+    let orders = [([user_agent.clone(), first.clone(), second.clone()], "blue"), ([user_agent, second, first], "red")];
+    for (stylesheets, expected) in orders {
+        assert_eq!(styles_with(html, &stylesheets, &media)["target"].color, color(expected));
+    }
+}
+
+#[test]
+fn properties_without_declarations_have_no_candidates() {
+    assert!(candidates(&[stylesheet("p { color: red }")], "width").is_empty());
+    // Declarations in rules that do not apply are not candidates.
+    assert!(candidates(&[stylesheet("@media print { p { width: 1px } } div { width: 2px }")], "width").is_empty());
+}
+
+#[test]
+fn pseudo_elements_have_their_own_candidates() {
+    let stylesheets = [stylesheet("p::before { content: \"a\" } .note::before { content: \"b\" } p { content: \"c\" }")];
+    let candidates = pseudo_element_candidates(&stylesheets, Some(PseudoElement::Before), "content");
+    let values: Vec<&Value> = candidates.iter().map(|candidate| &candidate.declaration.value).collect();
+    assert_eq!(values, [&Value::String("b".to_string()), &Value::String("a".to_string())]);
+}
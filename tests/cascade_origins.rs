@@ -0,0 +1,88 @@
+mod common;
+
+use common::{stylesheet, color, styles_with};
+use ukechug::css::{Parser, Stylesheet, Origin, Color, MediaEnvironment};
+
+fn user_agent(css: &str) -> Stylesheet {
+    stylesheet(css).with_origin(Origin::UserAgent)
+}
+
+fn user(css: &str) -> Stylesheet {
+    stylesheet(css).with_origin(Origin::User)
+}
+
+/// The color of the `#target` element of `html`.
+fn color_of(html: &str, stylesheets: &[Stylesheet]) -> Color {
+    styles_with(html, stylesheets, &MediaEnvironment::new(800.0, 600.0))["target"].color
+}
+
+const PARAGRAPH: &str = "<html><body><p id=\"target\">Text</p></body></html>";
+const INLINE: &str = "<html><body><p id=\"target\" style=\"color: yellow\">Text</p></body></html>";
+const INLINE_IMPORTANT: &str = "<html><body><p id=\"target\" style=\"color: yellow !important\">Text</p></body></html>";
+
+#[test]
+fn important_is_parsed() {
+    let stylesheet = stylesheet("p { color: red !important; margin: 0 !IMPORTANT; padding: 1px }");
+    let important: Vec<bool> = stylesheet.rules[0].declarations.iter().map(|declaration| declaration.important).collect();
+    assert_eq!(important, [true, true, false]);
+
+    let inline = Parser::new("color: red ! important; width: 10px".to_string()).parse_inline_declarations();
+    assert!(inline[0].important);
+    assert!(!inline[1].important);
+}
+
+#[test]
+fn normal_declarations_rank_user_agent_user_author_inline() {
+    let sheets = [user_agent("p { color: red }"), user("p { color: green }"), stylesheet("p { color: blue }")];
+    assert_eq!(color_of(PARAGRAPH, &sheets[..1]), color("red"));
+    assert_eq!(color_of(PARAGRAPH, &sheets[..2]), color("green"));
+    assert_eq!(color_of(PARAGRAPH, &sheets), color("blue"));
+    assert_eq!(color_of(INLINE, &sheets), color("yellow"));
+}
+
+#[test]
+fn origin_beats_specificity_and_source_order() {
+    // The author origin wins over a more specific user rule given later.
+    let sheets = [stylesheet("p { color: blue }"), user("body > p#target { color: green }")];
+    assert_eq!(color_of(PARAGRAPH, &sheets), color("blue"));
+}
+
+#[test]
+fn important_declarations_reverse_the_origin_order() {
+    let sheets = [
+        user_agent("p { color: red !important }"),
+        user("p { color: green !important }"),
+        stylesheet("p { color: blue !important }"),
+    ];
+    assert_eq!(color_of(INLINE, &sheets[2..]), color("blue"));
+    assert_eq!(color_of(INLINE_IMPORTANT, &sheets[2..]), color("yellow"));
+    assert_eq!(color_of(INLINE_IMPORTANT, &sheets[1..]), color("green"));
+    assert_eq!(color_of(INLINE_IMPORTANT, &sheets), color("red"));
+}
+
+#[test]
+fn important_user_agent_rules_beat_inline_styles() {
+    let sheets = [user_agent("p { color: red !important }"), stylesheet("#target { color: blue }")];
+    assert_eq!(color_of(INLINE, &sheets), color("red"));
+}
+
+#[test]
+fn source_order_breaks_ties() {
+    let sheets = [stylesheet("p { color: red } p { color: blue }")];
+    assert_eq!(color_of(PARAGRAPH, &sheets), color("blue"));
+
+    let sheets = [stylesheet("p { color: red }"), stylesheet("p { color: green }")];
+    assert_eq!(color_of(PARAGRAPH, &sheets), color("green"));
+
+    let sheets = [stylesheet("p { color: red !important; color: blue !important }")];
+    assert_eq!(color_of(PARAGRAPH, &sheets), color("blue"));
+
+    let inline = "<html><body><p id=\"target\" style=\"color: red; color: green\">Text</p></body></html>";
+    assert_eq!(color_of(inline, &[]), color("green"));
+}
+
+#[test]
+fn specificity_decides_within_an_origin() {
+    let sheets = [stylesheet("#target { color: red } body p { color: green } p { color: blue }")];
+    assert_eq!(color_of(PARAGRAPH, &sheets), color("red"));
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use common::{matching_ids, styles, color};
+use ukechug::css::Parser;
+
+const PAGE: &str = "
+    <html id=\"html\"><body id=\"body\">
+        <div id=\"d1\"><p id=\"p1\"></p><section id=\"s1\"><p id=\"p2\"></p></section></div>
+        <h1 id=\"h1\"></h1><p id=\"p3\"></p><h2 id=\"h2\"></h2><span id=\"sp\"></span><p id=\"p4\"></p><p id=\"p5\"></p>
+    </body></html>
+";
+
+#[test]
+fn descendant() {
+    assert_eq!(matching_ids(PAGE, "div p"), ["p1", "p2"]);
+    assert_eq!(matching_ids(PAGE, "html section p"), ["p2"]);
+    assert_eq!(matching_ids(PAGE, "section div"), Vec::<String>::new());
+}
+
+#[test]
+fn child() {
+    assert_eq!(matching_ids(PAGE, "div > p"), ["p1"]);
+    assert_eq!(matching_ids(PAGE, "body > p"), ["p3", "p4", "p5"]);
+    assert_eq!(matching_ids(PAGE, "div>section>p"), ["p2"]);
+}
+
+#[test]
+fn next_sibling() {
+    assert_eq!(matching_ids(PAGE, "h1 + p"), ["p3"]);
+    assert_eq!(matching_ids(PAGE, "p+p"), ["p5"]);
+    assert_eq!(matching_ids(PAGE, "div + p"), Vec::<String>::new());
+}
+
+#[test]
+fn subsequent_sibling() {
+    assert_eq!(matching_ids(PAGE, "h2 ~ p"), ["p4", "p5"]);
+    assert_eq!(matching_ids(PAGE, "h1~p"), ["p3", "p4", "p5"]);
+    assert_eq!(matching_ids(PAGE, "p ~ section"), ["s1"]);
+}
+
+#[test]
+fn chains_mix_combinators() {
+    assert_eq!(matching_ids(PAGE, "body > div + h1 ~ span"), ["sp"]);
+    assert_eq!(matching_ids(PAGE, "div p + section > p"), ["p2"]);
+    assert_eq!(matching_ids(PAGE, "h1 + p ~ p"), ["p4", "p5"]);
+}
+
+#[test]
+fn matching_backtracks_past_the_nearest_candidate() {
+    // The nearest `.b` ancestor is not a child of `.a`, but the outer one is.
+    let page = "<div class=\"a\"><div class=\"b\"><div class=\"b\"><p class=\"c\" id=\"target\"></p></div></div></div>";
+    assert_eq!(matching_ids(page, ".a > .b .c"), ["target"]);
+    // Likewise with siblings: the `h1` right before the nearest `h2` is not
+    // it, the one before the first `h2` is.
+    let page = "<div><h1></h1><h2></h2><h2></h2><p id=\"target\"></p></div>";
+    assert_eq!(matching_ids(page, "h1 + h2 ~ p"), ["target"]);
+}
+
+#[test]
+fn specificity_sums_the_compounds() {
+    let specificity = |selector: &str| Parser::new(selector.to_string()).parse_selector_list()[0].specificity();
+    assert_eq!(specificity("ul > li + li a"), (0, 0, 4));
+    assert_eq!(specificity("#nav .item > a.active ~ span"), (1, 2, 2));
+    assert_eq!(specificity("* > *"), (0, 0, 0));
+}
+
+#[test]
+fn more_specific_chains_win_the_cascade() {
+    let styles = styles(PAGE, "div p { color: red } p { color: blue } body > p { color: green }");
+    assert_eq!(styles["p1"].color, color("red"));
+    assert_eq!(styles["p3"].color, color("green"));
+}
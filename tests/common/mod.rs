@@ -0,0 +1,82 @@
+//! Helpers shared by the integration tests. Each test crate uses only some
+//! of them.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use ukechug::css::{Parser, Stylesheet, Color, PseudoElement, MediaEnvironment, user_agent_stylesheet};
+use ukechug::dom::{Node, NodeType};
+use ukechug::html::HTMLParser;
+use ukechug::style::{ComputedStyle, StyledNode, style_tree, matching_elements};
+
+pub fn stylesheet(css: &str) -> Stylesheet {
+    Parser::new(css.to_string()).parse_stylesheet()
+}
+
+pub fn color(name: &str) -> Color {
+    Color::from_keyword(name).expect("unknown color name")
+}
+
+/// Styles `html` with the user-agent stylesheet and `css` in an 800x600
+/// viewport, and returns the styles of the elements that have an id.
+pub fn styles(html: &str, css: &str) -> HashMap<String, ComputedStyle> {
+    styles_with(html, &[user_agent_stylesheet(), stylesheet(css)], &MediaEnvironment::new(800.0, 600.0))
+}
+
+pub fn styles_with(html: &str, stylesheets: &[Stylesheet], media: &MediaEnvironment) -> HashMap<String, ComputedStyle> {
+    let root = HTMLParser::new().parse(html);
+    let styled = style_tree(&root, stylesheets, media);
+    let mut styles = HashMap::new();
+    collect_styles(&styled, &mut styles);
+    styles
+}
+
+fn collect_styles(node: &StyledNode, styles: &mut HashMap<String, ComputedStyle>) {
+    if let (None, NodeType::Element(ref elem)) = (node.pseudo_element, &node.node.node_type) {
+        if let Some(id) = elem.id() {
+            styles.insert(id.clone(), (*node.style).clone());
+        }
+    }
+    for child in &node.children {
+        collect_styles(child, styles);
+    }
+}
+
+/// The text of the pseudo-elements of the elements of `html` that have an
+/// id, in document order, styled as by `styles`.
+pub fn generated_text(html: &str, css: &str) -> Vec<(String, PseudoElement, String)> {
+    fn collect(node: &StyledNode, texts: &mut Vec<(String, PseudoElement, String)>) {
+        if let (Some(pseudo_element), NodeType::Element(ref elem)) = (node.pseudo_element, &node.node.node_type) {
+            if let Some(id) = elem.id() {
+                texts.push((id.clone(), pseudo_element, node.generated_text.clone().unwrap_or_default()));
+            }
+        }
+        for child in &node.children {
+            collect(child, texts);
+        }
+    }
+    let root = HTMLParser::new().parse(html);
+    let stylesheets = [user_agent_stylesheet(), stylesheet(css)];
+    let styled = style_tree(&root, &stylesheets, &MediaEnvironment::new(800.0, 600.0));
+    let mut texts = Vec::new();
+    collect(&styled, &mut texts);
+    texts
+}
+
+/// The ids of the elements of `root` that match `selectors`, in document
+/// order; `-` for a matching element without an id.
+pub fn matching_ids_in(root: &Node, selectors: &str) -> Vec<String> {
+    let selectors = Parser::new(selectors.to_string()).parse_selector_list();
+    matching_elements(root, &selectors).iter()
+        .map(|path| {
+            let node = path.iter().fold(root, |node, &index| &node.children[index]);
+            match node.node_type {
+                NodeType::Element(ref elem) => elem.id().cloned().unwrap_or_else(|| "-".to_string()),
+                _ => unreachable!("only elements match selectors"),
+            }
+        })
+        .collect()
+}
+
+pub fn matching_ids(html: &str, selectors: &str) -> Vec<String> {
+    matching_ids_in(&HTMLParser::new().parse(html), selectors)
+}
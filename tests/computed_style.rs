@@ -0,0 +1,114 @@
+mod common;
+
+use common::{styles, color};
+use ukechug::css::{Parser, Color, LengthContext};
+use ukechug::style::{
+    ComputedStyle, PropertyMap, Display, LengthPercentage, LengthPercentageOrAuto, Sides, Content, ContentItem, Quotes,
+    ListStyleType,
+};
+
+/// The typed style of an element whose computed values are `declarations`,
+/// with 10px rems in an 800x600 viewport.
+fn computed(declarations: &str) -> ComputedStyle {
+    let values: PropertyMap = Parser::new(declarations.to_string())
+        .parse_inline_declarations()
+        .into_iter()
+        .map(|declaration| (declaration.name, declaration.value))
+        .collect();
+    let context = LengthContext { root_font_size: 10.0, ..LengthContext::new(800.0, 600.0) };
+    ComputedStyle::from_values(&values, &context)
+}
+
+fn px(value: f32) -> LengthPercentageOrAuto {
+    LengthPercentageOrAuto::LengthPercentage(LengthPercentage::Length(value))
+}
+
+#[test]
+fn initial_values() {
+    let style = computed("");
+    assert_eq!(style, ComputedStyle::default());
+    assert_eq!(style.display, Display::Inline);
+    assert_eq!(style.width, LengthPercentageOrAuto::Auto);
+    assert_eq!(style.margin, Sides::all(px(0.0)));
+    assert_eq!(style.border_width, Sides::all(0.0));
+    assert_eq!(style.color, Color::BLACK);
+    assert_eq!(style.background_color, Color::TRANSPARENT);
+    assert_eq!(style.font_size, 16.0);
+    assert_eq!(style.content, Content::Normal);
+    assert_eq!(style.quotes, Quotes::Auto);
+    assert_eq!(style.list_style_type, ListStyleType::Disc);
+}
+
+#[test]
+fn lengths_resolve_to_px() {
+    let style = computed("font-size: 20px; width: 2em; height: 3rem; padding-left: 10vw");
+    assert_eq!(style.width, px(40.0));
+    assert_eq!(style.height, px(30.0));
+    assert_eq!(style.padding.left, LengthPercentage::Length(80.0));
+    // Percentages and mixed math wait for the containing block.
+    let style = computed("width: 50%; margin-left: calc(100% - 10px)");
+    assert_eq!(style.width, LengthPercentageOrAuto::LengthPercentage(LengthPercentage::Percentage(50.0)));
+    assert_eq!(style.margin.left.resolve(200.0), 190.0);
+}
+
+#[test]
+fn box_shorthands_and_longhands() {
+    assert_eq!(computed("margin: 1px 2px 3px 4px").margin, Sides { top: px(1.0), right: px(2.0), bottom: px(3.0), left: px(4.0) });
+    assert_eq!(computed("margin: 1px 2px 3px").margin, Sides { top: px(1.0), right: px(2.0), bottom: px(3.0), left: px(2.0) });
+    assert_eq!(computed("margin: 1px auto").margin.right, LengthPercentageOrAuto::Auto);
+    // A longhand wins over the shorthand whatever their order.
+    let style = computed("padding-top: 9px; padding: 1px");
+    assert_eq!(style.padding.top, LengthPercentage::Length(9.0));
+    assert_eq!(style.padding.bottom, LengthPercentage::Length(1.0));
+}
+
+#[test]
+fn border_widths() {
+    assert_eq!(computed("border-width: thin medium thick 2px").border_width, Sides { top: 1.0, right: 3.0, bottom: 5.0, left: 2.0 });
+    // Borders have no percentages.
+    assert_eq!(computed("border-width: 10%").border_width, Sides::all(0.0));
+}
+
+#[test]
+fn colors() {
+    let style = computed("color: red; border-color: currentcolor; background: blue");
+    assert_eq!(style.border_color, color("red"));
+    assert_eq!(style.background_color, color("blue"));
+    // Border colors default to the text color.
+    assert_eq!(computed("color: green").border_color, color("green"));
+    assert_eq!(computed("color: #00ff00").color, Color::new(0, 255, 0, 255));
+}
+
+#[test]
+fn values_of_the_wrong_type_are_ignored() {
+    let style = computed("display: grid; width: red; color: 10px; list-style-type: hexagon; font-size: big");
+    assert_eq!(style.display, Display::Inline);
+    assert_eq!(style.width, LengthPercentageOrAuto::Auto);
+    assert_eq!(style.color, Color::BLACK);
+    assert_eq!(style.list_style_type, ListStyleType::Disc);
+    assert_eq!(style.font_size, 16.0);
+}
+
+#[test]
+fn generated_content_properties() {
+    let style = computed("content: \"#\" counter(item, upper-roman) attr(title) open-quote; quotes: \"<\" \">\"; counter-increment: item 2 other");
+    assert_eq!(style.content, Content::Items(vec![
+        ContentItem::String("#".to_string()),
+        ContentItem::Counter("item".to_string(), ListStyleType::UpperRoman),
+        ContentItem::Attr("title".to_string()),
+        ContentItem::OpenQuote,
+    ]));
+    assert_eq!(style.quotes, Quotes::Pairs(vec![("<".to_string(), ">".to_string())]));
+    assert_eq!(style.counter_increment, [("item".to_string(), 2), ("other".to_string(), 1)]);
+    assert_eq!(computed("content: none").content, Content::None);
+}
+
+#[test]
+fn styled_elements_get_typed_styles() {
+    let html = "<html><body><div id=\"outer\"><p id=\"inner\">Text</p></div></body></html>";
+    let styles = styles(html, "div { font-size: 10px; width: 50%; border-width: 1em } p { margin: 0 auto; border-color: blue }");
+    assert_eq!(styles["outer"].border_width, Sides::all(10.0));
+    assert_eq!(styles["outer"].width, LengthPercentageOrAuto::LengthPercentage(LengthPercentage::Percentage(50.0)));
+    assert_eq!(styles["inner"].margin.left, LengthPercentageOrAuto::Auto);
+    assert_eq!(styles["inner"].border_color, color("blue"));
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use common::generated_text;
+use ukechug::css::PseudoElement;
+use ukechug::style::ListStyleType;
+
+/// The text of every `::before` box, in document order.
+fn before_text(html: &str, css: &str) -> Vec<String> {
+    generated_text(html, css).into_iter()
+        .filter(|(_, pseudo_element, _)| *pseudo_element == PseudoElement::Before)
+        .map(|(_, _, text)| text)
+        .collect()
+}
+
+/// The text of every `::marker` box, in document order.
+fn markers(html: &str, css: &str) -> Vec<String> {
+    generated_text(html, css).into_iter()
+        .filter(|(_, pseudo_element, _)| *pseudo_element == PseudoElement::Marker)
+        .map(|(_, _, text)| text)
+        .collect()
+}
+
+const REPORT: &str = "
+    <body id=\"body\">
+        <h1 id=\"c1\">Intro</h1><h2 id=\"s11\">A</h2><h2 id=\"s12\">B</h2>
+        <h1 id=\"c2\">Method</h1><h2 id=\"s21\">C</h2>
+        <h1 id=\"c3\">Results</h1><h2 id=\"s31\">D</h2><h2 id=\"s32\">E</h2><h2 id=\"s33\">F</h2>
+    </body>
+";
+
+#[test]
+fn numbered_headings() {
+    let css = "
+        body { counter-reset: chapter }
+        h1 { counter-increment: chapter; counter-reset: section }
+        h2 { counter-increment: section }
+        h1::before { content: counter(chapter) \". \" }
+        h2::before { content: counter(chapter) \".\" counter(section, lower-alpha) \" \" }
+    ";
+    assert_eq!(before_text(REPORT, css), ["1. ", "1.a ", "1.b ", "2. ", "2.a ", "3. ", "3.a ", "3.b ", "3.c "]);
+}
+
+#[test]
+fn increment_by_and_set() {
+    let css = "
+        body { counter-reset: n 10 }
+        h1 { counter-increment: n -2 }
+        #c2 { counter-set: n 100 }
+        h2 { counter-increment: none }
+        h1::before { content: counter(n) }
+    ";
+    assert_eq!(before_text(REPORT, css), ["8", "100", "98"]);
+}
+
+#[test]
+fn counters_nest_with_their_elements() {
+    let html = "
+        <ol id=\"outer\">
+            <li id=\"a\">A<ol><li id=\"a1\">A1</li><li id=\"a2\">A2<ol><li id=\"a21\">A21</li></ol></li></ol></li>
+            <li id=\"b\">B</li>
+        </ol>
+    ";
+    let css = "
+        ol { counter-reset: item }
+        li { counter-increment: item }
+        li::before { content: counters(item, \".\") \" \" }
+    ";
+    assert_eq!(before_text(html, css), ["1 ", "1.1 ", "1.2 ", "1.2.1 ", "2 "]);
+}
+
+#[test]
+fn a_counter_is_visible_to_following_siblings() {
+    // The reset on `#c2` creates a counter its later siblings use instead of
+    // making a new one.
+    let css = "
+        #c2 { counter-reset: x 5 }
+        h2 { counter-increment: x }
+        h2::before { content: counter(x) }
+    ";
+    assert_eq!(before_text(REPORT, css), ["1", "2", "6", "7", "8", "9"]);
+}
+
+#[test]
+fn counter_styles() {
+    let css = "
+        h2 { counter-increment: n 3 }
+        h2::before { content: counter(n, upper-roman) counter(n, lower-greek) counter(n, decimal-leading-zero) counter(n, none) }
+    ";
+    assert_eq!(before_text(REPORT, css), ["IIIγ03", "VIζ06", "IXι09", "XIIμ12", "XVο15", "XVIIIσ18"]);
+
+    assert_eq!(ListStyleType::UpperAlpha.format(28), "AB");
+    assert_eq!(ListStyleType::LowerRoman.format(1994), "mcmxciv");
+    assert_eq!(ListStyleType::LowerRoman.format(0), "0");
+    assert_eq!(ListStyleType::DecimalLeadingZero.format(-3), "-03");
+    assert_eq!(ListStyleType::from_keyword("upper-latin"), Some(ListStyleType::UpperAlpha));
+}
+
+#[test]
+fn list_markers_use_the_list_item_counter() {
+    let html = "<ol><li id=\"a\">A</li><li id=\"b\">B<ul><li id=\"c\">C</li><li id=\"d\">D</li></ul></li><li id=\"e\">E</li></ol>";
+    assert_eq!(markers(html, ""), ["1. ", "2. ", "\u{25e6} ", "\u{25e6} ", "3. "]);
+    let css = "ol { list-style-type: upper-alpha } ol > li:nth-child(2) { counter-increment: list-item 5 }";
+    assert_eq!(markers(html, css), ["A. ", "F. ", "\u{25e6} ", "\u{25e6} ", "G. "]);
+}
+
+#[test]
+fn markers_can_show_generated_content() {
+    let html = "<ul><li id=\"a\">A</li><li id=\"b\">B</li></ul>";
+    let css = "li::marker { content: \"(\" counter(list-item, lower-roman) \") \" }";
+    assert_eq!(markers(html, css), ["(i) ", "(ii) "]);
+}
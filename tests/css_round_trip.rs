@@ -0,0 +1,161 @@
+use std::fs;
+use ukechug::css::{Parser, CssFormat, Stylesheet, Value, Unit, Color};
+use url::Url;
+
+/// Parses `css`, and checks that parsing its serialization in each format
+/// gives back an equal stylesheet.
+fn assert_round_trip(css: &str) -> Stylesheet {
+    let stylesheet = Parser::new(css.to_string()).parse_stylesheet();
+    assert_serialization_parses_back(&stylesheet);
+    stylesheet
+}
+
+fn assert_serialization_parses_back(stylesheet: &Stylesheet) {
+    for format in [CssFormat::Pretty, CssFormat::Minified] {
+        let text = stylesheet.to_css(format);
+        let parsed = Parser::new(text.clone()).parse_stylesheet();
+        assert_eq!(&parsed, stylesheet, "{:?} serialization did not parse back:\n{}", format, text);
+    }
+}
+
+#[test]
+fn every_value_variant_round_trips() {
+    let stylesheet = assert_round_trip("
+        p {
+            display: block;
+            width: 12.5px;
+            margin-left: -3em;
+            padding: 0 +2rem 10% 1e2px;
+            line-height: 1.4;
+            z-index: -3;
+            color: #336699;
+            content: \"a \\\"quoted\\\" string\" attr(title) counter(item, upper-roman) \")\";
+            background: url(\"images/bg.png\");
+            height: calc(100% - 2 * 20px);
+            font-size: clamp(14px, 2vw, 20px);
+            min-width: max(10vh, 5vmin + 1vmax);
+            --brand: { a: b } 10deg, #abc;
+            border-color: var(--brand, #000000);
+        }
+    ");
+
+    let values: Vec<&Value> = stylesheet.rules[0].declarations.iter().map(|declaration| &declaration.value).collect();
+    assert!(values.iter().any(|value| matches!(value, Value::Keyword(_))));
+    assert!(values.iter().any(|value| matches!(value, Value::Length(_, Unit::Percent) | Value::List(_))));
+    assert!(values.iter().any(|value| matches!(value, Value::Number(_))));
+    assert!(values.iter().any(|value| **value == Value::Color(Color { r: 0x33, g: 0x66, b: 0x99, a: 255 })));
+    assert!(values.iter().any(|value| matches!(value, Value::Url(_))));
+    assert!(values.iter().any(|value| matches!(value, Value::Calc(_))));
+    assert!(values.iter().any(|value| matches!(value, Value::Tokens(_))));
+    let Value::List(ref content) = stylesheet.rules[0].declarations[7].value else {
+        panic!("content is not a list");
+    };
+    assert!(matches!(content[0], Value::String(_)));
+    assert!(matches!(content[1], Value::Function(..)));
+}
+
+#[test]
+fn selectors_round_trip() {
+    assert_round_trip("
+        * { color: red; }
+        div#main.note.wide { color: red; }
+        a[href] { color: red; }
+        a[href=\"https://example.com\"], [lang|=en], [class~=x], [data-a^=b], [data-a$=c], [data-a*=d i], [data-a=e s] { color: red; }
+        ul > li + li ~ li span { color: red; }
+        li:first-child, li:last-child, li:only-child, p:empty, :root { color: red; }
+        li:nth-child(2n+1), li:nth-last-child(-n+3), p:nth-of-type(odd), p:nth-last-of-type(even) { color: red; }
+        li:nth-child(3 of .item:not(.hidden)) { color: red; }
+        :is(h1, h2) > :where(.a, .b) { color: red; }
+        div:has(> img), section:has(+ aside), article:has(~ footer p) { color: red; }
+        a:hover, a:active, input:focus, form:focus-within, input:focus-visible, :target { color: red; }
+        :link, :visited, :checked, :disabled, :enabled { color: red; }
+        p::before, p::after, li::marker { content: \"-\"; }
+    ");
+}
+
+#[test]
+fn important_declarations_round_trip() {
+    let stylesheet = assert_round_trip("p { color: red !important; margin: 0 ! important; padding: 1px; }");
+    let important: Vec<bool> = stylesheet.rules[0].declarations.iter().map(|declaration| declaration.important).collect();
+    assert_eq!(important, [true, true, false]);
+}
+
+#[test]
+fn conditional_rules_round_trip() {
+    assert_round_trip("
+        @media screen and (min-width: 600px), print and (orientation: landscape) {
+            p { color: red; }
+            @media (400px <= width < 800px) { p { color: blue; } }
+        }
+        @media not print and (prefers-color-scheme: dark) { p { color: white; } }
+        @media (aspect-ratio: 16/9) or (resolution >= 2dppx) { p { color: green; } }
+        @supports (display: grid) and (not (color: nonsense)) { p { display: block; } }
+        @supports (width: calc(100% - 1px)) or selector(a > b) { p { width: 1px; } }
+        @media screen { @supports (color: red) { p { color: red; } } }
+    ");
+}
+
+#[test]
+fn layers_round_trip() {
+    assert_round_trip("
+        @layer reset, base;
+        @layer base { p { color: red; } }
+        @layer components.buttons { .button { padding: 4px; } }
+        @layer reset { * { margin: 0; } }
+        @media print { @layer base { p { color: black; } } }
+        p { color: green; }
+        @layer { p { margin: 1px; } }
+    ");
+}
+
+#[test]
+fn property_registrations_round_trip() {
+    let stylesheet = assert_round_trip("
+        @property --gap { syntax: \"<length>\"; inherits: false; initial-value: 4px; }
+        @property --tint { syntax: \"<color> | none\"; inherits: true; initial-value: none; }
+        @property --any { syntax: \"*\"; inherits: false; }
+        p { padding: var(--gap); }
+    ");
+    assert_eq!(stylesheet.properties.len(), 3);
+}
+
+#[test]
+fn nested_rules_round_trip() {
+    assert_round_trip("
+        .card {
+            padding: 8px;
+            & h2 { color: red; }
+            &:hover { color: blue; }
+            > p { margin: 0; }
+            @media (min-width: 500px) { padding: 16px; }
+        }
+    ");
+}
+
+#[test]
+fn imported_rules_round_trip() {
+    let dir = std::env::temp_dir().join(format!("ukechug-round-trip-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("base.css"), "p { color: red; } @media print { p { color: black; } }").unwrap();
+    fs::write(dir.join("theme.css"), "@layer theme { a { color: blue !important; } }").unwrap();
+    let main = dir.join("main.css");
+    let css = "
+        @import \"base.css\" screen;
+        @import url(theme.css) layer(vendor);
+        h1 { font-size: 2em; }
+    ";
+    fs::write(&main, css).unwrap();
+
+    let stylesheet = Parser::with_base_url(css.to_string(), Url::from_file_path(&main).unwrap()).parse_stylesheet();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(stylesheet.rules.len(), 4);
+    assert_serialization_parses_back(&stylesheet);
+}
+
+#[test]
+fn source_locations_do_not_affect_equality() {
+    let compact = Parser::new("p { color: red; } a { color: blue; }".to_string()).parse_stylesheet();
+    let spread = Parser::new("\n\np {\n  color: red;\n}\n\n\na { color: blue; }".to_string()).parse_stylesheet();
+    assert_ne!(compact.rules[1].location, spread.rules[1].location);
+    assert_eq!(compact, spread);
+}
@@ -0,0 +1,139 @@
+mod common;
+
+use common::{styles, color};
+use ukechug::css::Color;
+use ukechug::style::{LengthPercentage, LengthPercentageOrAuto};
+
+const PAGE: &str = "
+    <html><body id=\"body\">
+        <div id=\"outer\"><p id=\"inner\">Text</p></div>
+    </body></html>
+";
+
+fn px(value: f32) -> LengthPercentageOrAuto {
+    LengthPercentageOrAuto::LengthPercentage(LengthPercentage::Length(value))
+}
+
+#[test]
+fn substitution_and_inheritance() {
+    let styles = styles(PAGE, "
+        body { --brand: #e74c3c; --gap: 12px }
+        #outer { color: var(--brand); width: calc(var(--gap) * 10) }
+        #inner { --gap: 2px; width: var(--gap) }
+    ");
+    let brand = Color { r: 0xe7, g: 0x4c, b: 0x3c, a: 255 };
+    assert_eq!(styles["outer"].color, brand);
+    assert_eq!(styles["outer"].width, px(120.0));
+    assert_eq!(styles["inner"].width, px(2.0));
+    assert_eq!(styles["inner"].color, brand);
+}
+
+#[test]
+fn fallbacks() {
+    let styles = styles(PAGE, "
+        body { --set: blue }
+        #outer { color: var(--missing, red); width: var(--missing, var(--also-missing, 30px)) }
+        #inner { color: var(--set, red); height: var(--missing,) 5px }
+    ");
+    assert_eq!(styles["outer"].color, color("red"));
+    assert_eq!(styles["outer"].width, px(30.0));
+    assert_eq!(styles["inner"].color, color("blue"));
+    assert_eq!(styles["inner"].height, px(5.0));
+}
+
+#[test]
+fn variables_can_reference_variables() {
+    let styles = styles(PAGE, "
+        body { --base: 4px; --double: calc(var(--base) * 2) }
+        #outer { --base: 10px; width: var(--double) }
+        #inner { --double: calc(var(--base) * 2); width: var(--double) }
+    ");
+    // `--double` is computed on the body and inherited as is.
+    assert_eq!(styles["outer"].width, px(8.0));
+    assert_eq!(styles["inner"].width, px(20.0));
+}
+
+#[test]
+fn cycles_make_properties_invalid_at_computed_value_time() {
+    let styles = styles(PAGE, "
+        body { color: green }
+        #outer { --a: var(--b); --b: var(--a); --c: var(--c, 1px); color: var(--a); width: var(--c, 40px) }
+        #inner { --a: 7px; height: var(--b, 9px); width: var(--a) }
+    ");
+    // Properties in a cycle have no value, so references to them take their
+    // fallback or leave the property unset.
+    assert_eq!(styles["outer"].color, color("green"));
+    assert_eq!(styles["outer"].width, px(40.0));
+    assert_eq!(styles["inner"].width, px(7.0));
+    // `--b` inherits the invalid value from `#outer`.
+    assert_eq!(styles["inner"].height, px(9.0));
+}
+
+#[test]
+fn missing_variables_without_fallback_unset_the_property() {
+    let styles = styles(PAGE, "
+        body { color: blue; width: 500px }
+        #outer { color: var(--missing); width: var(--missing) }
+    ");
+    assert_eq!(styles["outer"].color, color("blue"));
+    assert_eq!(styles["outer"].width, LengthPercentageOrAuto::Auto);
+}
+
+#[test]
+fn substituted_values_that_do_not_parse_are_unset() {
+    let styles = styles(PAGE, "
+        body { color: blue; --short: #12; --word: wide; --unit: 3 }
+        #outer { color: var(--short); width: calc(1px + var(--word)) }
+        #inner { color: red; height: var(--unit)furlongs }
+    ");
+    assert_eq!(styles["outer"].color, color("blue"));
+    assert_eq!(styles["outer"].width, LengthPercentageOrAuto::Auto);
+    assert_eq!(styles["inner"].color, color("red"));
+    assert_eq!(styles["inner"].height, LengthPercentageOrAuto::Auto);
+}
+
+#[test]
+fn css_wide_keywords() {
+    let inherited = styles(PAGE, "
+        body { --size: 30px }
+        #outer { --size: 10px; width: var(--size) }
+        #inner { --size: inherit; width: var(--size) }
+    ");
+    assert_eq!(inherited["inner"].width, px(10.0));
+
+    let reset = styles(PAGE, "
+        body { --size: 30px }
+        #outer { --size: initial; width: var(--size, 50px) }
+        #inner { --size: unset; width: var(--size, 60px) }
+    ");
+    assert_eq!(reset["outer"].width, px(50.0));
+    assert_eq!(reset["inner"].width, px(60.0));
+}
+
+#[test]
+fn registered_properties() {
+    let styles = styles(PAGE, "
+        @property --local { syntax: \"<length>\"; inherits: false; initial-value: 5px; }
+        @property --tint { syntax: \"<color>\"; inherits: true; initial-value: green; }
+        body { --local: 30px }
+        #outer { width: var(--local); color: var(--tint) }
+        #inner { --tint: 12px; --local: red; width: var(--local); color: var(--tint) }
+    ");
+    // Not inherited: the initial value.
+    assert_eq!(styles["outer"].width, px(5.0));
+    assert_eq!(styles["outer"].color, color("green"));
+    // Values that do not match the syntax fall back to the initial value.
+    assert_eq!(styles["inner"].width, px(5.0));
+    assert_eq!(styles["inner"].color, color("green"));
+}
+
+#[test]
+fn invalid_registrations_are_ignored() {
+    let styles = styles(PAGE, "
+        @property --size { syntax: \"<length>\"; inherits: false; initial-value: red; }
+        body { --size: 30px }
+        #outer { width: var(--size, 1px) }
+    ");
+    // Without a valid registration `--size` inherits like any custom property.
+    assert_eq!(styles["outer"].width, px(30.0));
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use common::{stylesheet, matching_ids, matching_ids_in, color};
+use ukechug::css::{Parser, Selector, Color, MediaEnvironment, user_agent_stylesheet};
+use ukechug::dom::{Node, NodeType, ElementState};
+use ukechug::html::HTMLParser;
+use ukechug::style::{StyledNode, add_state, matching_elements, style_tree};
+
+const PAGE: &str = "
+    <html id=\"html\"><body id=\"body\">
+        <nav id=\"nav\"><a id=\"home\" href=\"/\">Home</a><a id=\"anchor\">No link</a><a id=\"about\" href=\"/about\">About</a></nav>
+        <form id=\"form\">
+            <input id=\"name\" type=\"text\">
+            <input id=\"agree\" type=\"checkbox\" checked>
+            <input id=\"radio\" type=\"radio\">
+            <select id=\"select\"><option id=\"first\">One</option><option id=\"second\" selected>Two</option></select>
+            <button id=\"send\" disabled>Send</button>
+        </form>
+        <section id=\"details\">Details</section>
+    </body></html>
+";
+
+fn selectors(text: &str) -> Vec<Selector> {
+    Parser::new(text.to_string()).parse_selector_list()
+}
+
+/// `PAGE` with `state` added to the elements that match `selector`.
+fn page_with(selector: &str, state: ElementState) -> Node {
+    let mut root = HTMLParser::new().parse(PAGE);
+    add_state(&mut root, &selectors(selector), state);
+    root
+}
+
+#[test]
+fn parsed_documents_have_no_state() {
+    for pseudo_class in [":hover", ":active", ":focus", ":focus-within", ":focus-visible", ":target", ":visited"] {
+        assert!(matching_ids(PAGE, pseudo_class).is_empty(), "{}", pseudo_class);
+    }
+}
+
+#[test]
+fn hover_and_active_propagate_to_ancestors() {
+    let root = page_with("#home", ElementState::HOVER | ElementState::ACTIVE);
+    assert_eq!(matching_ids_in(&root, ":hover"), ["html", "body", "nav", "home"]);
+    assert_eq!(matching_ids_in(&root, ":active"), ["html", "body", "nav", "home"]);
+    assert_eq!(matching_ids_in(&root, "a:hover"), ["home"]);
+}
+
+#[test]
+fn focus_sets_focus_within_on_ancestors() {
+    let root = page_with("#name", ElementState::FOCUS);
+    assert_eq!(matching_ids_in(&root, ":focus"), ["name"]);
+    assert_eq!(matching_ids_in(&root, ":focus-within"), ["html", "body", "form", "name"]);
+    // Focus only shows when marked visible too.
+    assert!(matching_ids_in(&root, ":focus-visible").is_empty());
+    let root = page_with("#name", ElementState::FOCUS | ElementState::FOCUS_VISIBLE);
+    assert_eq!(matching_ids_in(&root, ":focus-visible"), ["name"]);
+}
+
+#[test]
+fn state_by_path() {
+    let mut root = HTMLParser::new().parse(PAGE);
+    let path = matching_elements(&root, &selectors("#about")).remove(0);
+    root.add_element_state(&path, ElementState::VISITED);
+    assert_eq!(matching_ids_in(&root, ":visited"), ["about"]);
+    // Visiting a link does not visit its ancestors.
+    assert_eq!(matching_ids_in(&root, "nav:visited, :visited :visited"), Vec::<String>::new());
+}
+
+#[test]
+fn add_state_counts_matches() {
+    let mut root = HTMLParser::new().parse(PAGE);
+    assert_eq!(add_state(&mut root, &selectors("input, button"), ElementState::DISABLED), 4);
+    assert_eq!(add_state(&mut root, &selectors("table"), ElementState::HOVER), 0);
+}
+
+#[test]
+fn links() {
+    assert_eq!(matching_ids(PAGE, ":link"), ["home", "about"]);
+    let root = page_with("#about", ElementState::VISITED);
+    assert_eq!(matching_ids_in(&root, ":link"), ["home"]);
+    assert_eq!(matching_ids_in(&root, ":visited"), ["about"]);
+    // Only links can be visited.
+    let root = page_with("#anchor, #nav", ElementState::VISITED);
+    assert!(matching_ids_in(&root, ":visited").is_empty());
+}
+
+#[test]
+fn checked() {
+    assert_eq!(matching_ids(PAGE, ":checked"), ["agree", "second"]);
+    let root = page_with("#radio", ElementState::CHECKED);
+    assert_eq!(matching_ids_in(&root, ":checked"), ["agree", "radio", "second"]);
+}
+
+#[test]
+fn enabled_and_disabled() {
+    assert_eq!(matching_ids(PAGE, ":disabled"), ["send"]);
+    assert_eq!(matching_ids(PAGE, ":enabled"), ["name", "agree", "radio", "select", "first", "second"]);
+    let root = page_with("#name", ElementState::DISABLED);
+    assert_eq!(matching_ids_in(&root, ":disabled"), ["name", "send"]);
+    // Elements that are not form controls are neither.
+    assert!(matching_ids(PAGE, "section:enabled, section:disabled").is_empty());
+}
+
+#[test]
+fn target() {
+    let mut root = HTMLParser::new().parse(PAGE);
+    assert!(root.set_target("details"));
+    assert!(!root.set_target("missing"));
+    assert_eq!(matching_ids_in(&root, ":target"), ["details"]);
+}
+
+#[test]
+fn states_restyle_elements() {
+    fn color_of(node: &StyledNode, id: &str) -> Option<Color> {
+        if let NodeType::Element(ref elem) = node.node.node_type {
+            if node.pseudo_element.is_none() && elem.id().is_some_and(|elem_id| elem_id == id) {
+                return Some(node.style.color);
+            }
+        }
+        node.children.iter().find_map(|child| color_of(child, id))
+    }
+    let css = "a:hover { color: red } nav:focus-within a { color: green } a:hover:focus { color: blue }";
+    let stylesheets = [user_agent_stylesheet(), stylesheet(css)];
+    let media = MediaEnvironment::new(800.0, 600.0);
+
+    let root = page_with("#home", ElementState::HOVER);
+    let styled = style_tree(&root, &stylesheets, &media);
+    assert_eq!(color_of(&styled, "home"), Some(color("red")));
+
+    let root = page_with("#home", ElementState::HOVER | ElementState::FOCUS);
+    let styled = style_tree(&root, &stylesheets, &media);
+    assert_eq!(color_of(&styled, "home"), Some(color("blue")));
+    assert_eq!(color_of(&styled, "about"), Some(color("green")));
+}
@@ -0,0 +1,168 @@
+mod common;
+
+use common::stylesheet;
+use ukechug::css::{MediaEnvironment, user_agent_stylesheet};
+use ukechug::dom::{Node, NodeType, ElementState};
+use ukechug::html::HTMLParser;
+use ukechug::layout::{FormControl, MeterLevel, LayoutBox, Dimensions, layout_tree};
+use ukechug::style::style_tree;
+
+fn control(html: &str) -> Option<FormControl> {
+    FormControl::from_node(&HTMLParser::new().parse(html))
+}
+
+fn text_field(text: &str, placeholder: bool, size: usize) -> Option<FormControl> {
+    Some(FormControl::TextField { text: text.to_string(), placeholder, size })
+}
+
+fn meter_level(attributes: &str) -> MeterLevel {
+    match control(&format!("<meter {}></meter>", attributes)) {
+        Some(FormControl::Meter { level, .. }) => level,
+        control => panic!("not a meter: {:?}", control),
+    }
+}
+
+#[test]
+fn text_fields() {
+    assert_eq!(control("<input value=\"hello\">"), text_field("hello", false, 20));
+    assert_eq!(control("<input type=\"EMAIL\" value=\"a@b.c\" size=\"30\">"), text_field("a@b.c", false, 30));
+    assert_eq!(control("<input placeholder=\"Search\" value=\"\" size=\"0\">"), text_field("Search", true, 20));
+    assert_eq!(control("<input>"), text_field("", true, 20));
+    assert_eq!(control("<input type=\"password\" value=\"secret\">"), text_field("\u{2022}\u{2022}\u{2022}\u{2022}\u{2022}\u{2022}", false, 20));
+}
+
+#[test]
+fn checkboxes_and_radios() {
+    assert_eq!(control("<input type=\"checkbox\">"), Some(FormControl::Checkbox { checked: false }));
+    assert_eq!(control("<input type=\"radio\" checked>"), Some(FormControl::Radio { checked: true }));
+    // Unquoted and boolean attributes, in any case.
+    assert_eq!(control("<input type=Checkbox checked name=agree>"), Some(FormControl::Checkbox { checked: true }));
+
+    let mut root = HTMLParser::new().parse("<input type=\"checkbox\">");
+    root.add_element_state(&[], ElementState::CHECKED);
+    assert_eq!(FormControl::from_node(&root), Some(FormControl::Checkbox { checked: true }));
+}
+
+#[test]
+fn buttons() {
+    let button = |label: &str| Some(FormControl::Button { label: label.to_string() });
+    assert_eq!(control("<button>  Send <b>now</b>\n</button>"), button("Send now"));
+    assert_eq!(control("<input type=\"submit\">"), button("Submit"));
+    assert_eq!(control("<input type=\"reset\">"), button("Reset"));
+    assert_eq!(control("<input type=\"button\">"), button(""));
+    assert_eq!(control("<input type=\"submit\" value=\"Go\">"), button("Go"));
+}
+
+#[test]
+fn text_areas() {
+    assert_eq!(
+        control("<textarea rows=\"4\" cols=\"x\">\nFirst line\nSecond</textarea>"),
+        Some(FormControl::TextArea { text: "First line\nSecond".to_string(), rows: 4, cols: 20 })
+    );
+    assert_eq!(control("<textarea>Text</textarea>"), Some(FormControl::TextArea { text: "Text".to_string(), rows: 2, cols: 20 }));
+}
+
+#[test]
+fn selects_show_the_selected_option() {
+    let html = "<select><option>Small</option><optgroup><option selected>Medium size</option></optgroup><option label=\"L\">Large</option></select>";
+    let labels = vec!["Small".to_string(), "Medium size".to_string(), "L".to_string()];
+    assert_eq!(control(html), Some(FormControl::Select { label: "Medium size".to_string(), labels }));
+    // Without a selected option the first one shows.
+    let html = "<select><option>One</option><option>Two</option></select>";
+    assert!(matches!(control(html), Some(FormControl::Select { label, .. }) if label == "One"));
+    assert_eq!(control("<select></select>"), Some(FormControl::Select { label: String::new(), labels: Vec::new() }));
+}
+
+#[test]
+fn progress_bars() {
+    let progress = |position| Some(FormControl::Progress { position });
+    assert_eq!(control("<progress value=\"30\" max=\"120\"></progress>"), progress(Some(0.25)));
+    assert_eq!(control("<progress value=\"0.5\"></progress>"), progress(Some(0.5)));
+    assert_eq!(control("<progress value=\"5\" max=\"-1\"></progress>"), progress(Some(1.0)));
+    assert_eq!(control("<progress></progress>"), progress(None));
+}
+
+#[test]
+fn meters() {
+    assert!(matches!(control("<meter value=\"3\" min=\"2\" max=\"6\"></meter>"), Some(FormControl::Meter { position, .. }) if position == 0.25));
+    assert_eq!(meter_level("value=\"0.5\""), MeterLevel::Optimum);
+    // The optimum in the middle: outside low..high is suboptimal.
+    assert_eq!(meter_level("value=\"0.9\" low=\"0.2\" high=\"0.8\""), MeterLevel::Suboptimal);
+    // Low values are best.
+    assert_eq!(meter_level("value=\"0.1\" low=\"0.2\" high=\"0.8\" optimum=\"0\""), MeterLevel::Optimum);
+    assert_eq!(meter_level("value=\"0.5\" low=\"0.2\" high=\"0.8\" optimum=\"0\""), MeterLevel::Suboptimal);
+    assert_eq!(meter_level("value=\"0.9\" low=\"0.2\" high=\"0.8\" optimum=\"0\""), MeterLevel::EvenLessGood);
+    // High values are best.
+    assert_eq!(meter_level("value=\"90\" max=\"100\" low=\"20\" high=\"80\" optimum=\"100\""), MeterLevel::Optimum);
+    assert_eq!(meter_level("value=\"10\" max=\"100\" low=\"20\" high=\"80\" optimum=\"100\""), MeterLevel::EvenLessGood);
+}
+
+#[test]
+fn other_elements_are_not_controls() {
+    for html in ["<input type=\"hidden\" value=\"x\">", "<div>Text</div>", "<option>One</option>", "<form></form>"] {
+        assert_eq!(control(html), None, "{}", html);
+    }
+}
+
+#[test]
+fn intrinsic_sizes() {
+    let size = |html: &str, font_size| control(html).unwrap().intrinsic_size(font_size);
+    assert_eq!(size("<input size=\"10\">", 10.0), (55.0, 12.0));
+    assert_eq!(size("<textarea rows=\"3\" cols=\"10\"></textarea>", 10.0), (55.0, 36.0));
+    assert_eq!(size("<input type=\"checkbox\">", 40.0), (13.0, 13.0));
+    assert_eq!(size("<button>Four</button>", 10.0), (22.0, 12.0));
+    assert_eq!(size("<select><option>ab</option><option selected>a</option></select>", 10.0), (11.0 + 16.0, 12.0));
+    assert_eq!(size("<progress></progress>", 10.0), (100.0, 10.0));
+    assert_eq!(size("<meter></meter>", 10.0), (50.0, 10.0));
+}
+
+/// The boxes of the form controls under `layout`, in document order.
+fn control_boxes<'a>(layout: &'a LayoutBox<'a>, boxes: &mut Vec<&'a LayoutBox<'a>>) {
+    if layout.form_control.is_some() {
+        boxes.push(layout);
+    }
+    for child in &layout.children {
+        control_boxes(child, boxes);
+    }
+}
+
+/// Lays out `root` with the user-agent stylesheet and `css` in an 800px
+/// wide viewport and passes the boxes of its form controls to `check`.
+fn with_layout(root: &Node, css: &str, check: impl FnOnce(&[&LayoutBox])) {
+    let stylesheets = [user_agent_stylesheet(), stylesheet(css)];
+    let styled = style_tree(root, &stylesheets, &MediaEnvironment::new(800.0, 600.0));
+    let mut viewport: Dimensions = Default::default();
+    viewport.content.width = 800.0;
+    let layout = layout_tree(&styled, viewport);
+    let mut boxes = Vec::new();
+    control_boxes(&layout, &mut boxes);
+    check(&boxes);
+}
+
+#[test]
+fn controls_are_laid_out_side_by_side() {
+    let root = HTMLParser::new().parse("<html><body><form><input size=\"10\"><input type=\"checkbox\"><button style=\"width: 50px\">Go</button><input type=\"hidden\"></form></body></html>");
+    with_layout(&root, "body { margin: 0 } form { width: 200px }", |boxes| {
+        assert_eq!(boxes.len(), 3);
+        let margin_boxes: Vec<_> = boxes.iter().map(|layout_box| layout_box.dimensions.margin_box()).collect();
+        // Each starts where the previous one ends, on the same line.
+        assert_eq!(margin_boxes[0].x, 0.0);
+        assert_eq!(margin_boxes[1].x, margin_boxes[0].width);
+        assert_eq!(margin_boxes[2].x, margin_boxes[1].x + margin_boxes[1].width);
+        assert!(margin_boxes.iter().all(|margin_box| margin_box.y == 0.0));
+        // Explicit sizes win over intrinsic ones.
+        assert_eq!(boxes[2].dimensions.content.width, 50.0);
+        assert_eq!(boxes[1].dimensions.content.width, 13.0);
+    });
+}
+
+#[test]
+fn controls_wrap_onto_new_lines() {
+    let root = HTMLParser::new().parse("<html><body><div><input id=\"a\"><input id=\"b\"><input id=\"c\"></div></body></html>");
+    with_layout(&root, "body { margin: 0 } div { width: 250px } input { width: 100px; margin: 0; padding: 0; border-width: 0 }", |boxes| {
+        let positions: Vec<_> = boxes.iter().map(|layout_box| (layout_box.dimensions.content.x, layout_box.dimensions.content.y)).collect();
+        let line = boxes[0].dimensions.content.height;
+        assert_eq!(positions, [(0.0, 0.0), (100.0, 0.0), (0.0, line)]);
+        assert!(matches!(boxes[0].get_style_node().node.node_type, NodeType::Element(ref elem) if elem.id().is_some_and(|id| id == "a")));
+    });
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use common::{generated_text, stylesheet, color};
+use ukechug::css::{PseudoElement, MediaEnvironment, user_agent_stylesheet};
+use ukechug::html::HTMLParser;
+use ukechug::layout::{layout_tree, BoxType, Dimensions};
+use ukechug::style::{style_tree, Content, ContentItem};
+
+/// The generated text of the `#target` element's pseudo-elements.
+fn target_text(html: &str, css: &str) -> Vec<(PseudoElement, String)> {
+    generated_text(html, css).into_iter()
+        .filter(|(id, _, _)| id == "target")
+        .map(|(_, pseudo_element, text)| (pseudo_element, text))
+        .collect()
+}
+
+const PARAGRAPH: &str = "<html><body><p id=\"target\" title=\"Note\" data-empty=\"\">Text</p></body></html>";
+
+#[test]
+fn before_and_after_strings() {
+    assert_eq!(
+        target_text(PARAGRAPH, "p::before { content: \"[\" } p::after { content: \"]\" \"!\" }"),
+        [(PseudoElement::Before, "[".to_string()), (PseudoElement::After, "]!".to_string())]
+    );
+}
+
+#[test]
+fn normal_and_none_generate_nothing() {
+    assert!(target_text(PARAGRAPH, "p::before { color: red } p::after { content: none }").is_empty());
+    assert!(target_text(PARAGRAPH, "p::before { content: \"x\" } p::before { content: normal }").is_empty());
+    // An empty string still generates a box.
+    assert_eq!(target_text(PARAGRAPH, "p::after { content: \"\" }"), [(PseudoElement::After, String::new())]);
+}
+
+#[test]
+fn attr_reads_the_originating_element() {
+    assert_eq!(
+        target_text(PARAGRAPH, "p::before { content: attr(title) \": \" attr(missing) attr(data-empty) }"),
+        [(PseudoElement::Before, "Note: ".to_string())]
+    );
+}
+
+#[test]
+fn quotes_nest() {
+    let html = "<div><q id=\"outer\">a <q id=\"target\">b</q></q></div>";
+    let css = "q::before { content: open-quote } q::after { content: close-quote }";
+    let texts: Vec<String> = generated_text(html, css).into_iter().map(|(_, _, text)| text).collect();
+    assert_eq!(texts, ["\u{201c}", "\u{2018}", "\u{2019}", "\u{201d}"]);
+
+    let css = "q { quotes: \"<<\" \">>\" \"<\" \">\" } q::before { content: open-quote } q::after { content: close-quote }";
+    let texts: Vec<String> = generated_text(html, css).into_iter().map(|(_, _, text)| text).collect();
+    assert_eq!(texts, ["<<", "<", ">", ">>"]);
+}
+
+#[test]
+fn no_open_quote_still_counts_nesting() {
+    let html = "<div><q id=\"outer\"><q id=\"target\">b</q></q></div>";
+    let css = "
+        q::before { content: open-quote } q::after { content: close-quote }
+        #outer::before { content: no-open-quote } #outer::after { content: no-close-quote }
+    ";
+    assert_eq!(
+        target_text(html, css),
+        [(PseudoElement::Before, "\u{2018}".to_string()), (PseudoElement::After, "\u{2019}".to_string())]
+    );
+}
+
+#[test]
+fn pseudo_elements_have_their_own_style_and_inherit_from_their_element() {
+    let root = HTMLParser::new().parse(PARAGRAPH);
+    let css = "
+        p { color: blue; font-size: 20px }
+        p::before { content: \"*\" attr(title); color: red }
+        p::after { content: \")\"; display: block }
+    ";
+    let stylesheets = [user_agent_stylesheet(), stylesheet(css)];
+    let styled = style_tree(&root, &stylesheets, &MediaEnvironment::new(800.0, 600.0));
+    let paragraph = &styled.children[0].children[0];
+    let before = paragraph.children.first().unwrap();
+    let after = paragraph.children.last().unwrap();
+
+    assert_eq!(before.pseudo_element, Some(PseudoElement::Before));
+    assert_eq!(before.style.color, color("red"));
+    assert_eq!(before.style.font_size, 20.0);
+    assert_eq!(
+        before.style.content,
+        Content::Items(vec![ContentItem::String("*".to_string()), ContentItem::Attr("title".to_string())])
+    );
+    assert_eq!(after.pseudo_element, Some(PseudoElement::After));
+    assert_eq!(after.style.color, color("blue"));
+    assert_eq!(after.generated_text.as_deref(), Some(")"));
+
+    // The block `::after` gets its own box after the paragraph's text.
+    let mut viewport: Dimensions = Default::default();
+    viewport.content.width = 800.0;
+    let layout = layout_tree(&styled, viewport);
+    let paragraph_box = &layout.children[0].children[0];
+    let BoxType::BlockNode(last) = paragraph_box.children.last().unwrap().box_type else {
+        panic!("::after is not a block");
+    };
+    assert_eq!(last.pseudo_element, Some(PseudoElement::After));
+    assert!(matches!(paragraph_box.children[0].box_type, BoxType::AnonymousBlock));
+}
@@ -0,0 +1,154 @@
+mod common;
+
+use common::{styles_with, color};
+use std::fs;
+use std::path::PathBuf;
+use ukechug::css::{Parser, Stylesheet, Selector, RuleCondition, LayerName, LayerSegment, MediaEnvironment, user_agent_stylesheet};
+use url::Url;
+
+/// A directory of stylesheets, removed when dropped.
+struct Files {
+    dir: PathBuf,
+}
+
+impl Files {
+    fn new(name: &str, files: &[(&str, &str)]) -> Files {
+        let dir = std::env::temp_dir().join(format!("ukechug-imports-{}-{}", name, std::process::id()));
+        for (path, css) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, css).unwrap();
+        }
+        Files { dir }
+    }
+
+    fn parse(&self, path: &str) -> Stylesheet {
+        let path = self.dir.join(path);
+        let css = fs::read_to_string(&path).unwrap();
+        Parser::with_base_url(css, Url::from_file_path(&path).unwrap()).parse_stylesheet()
+    }
+}
+
+impl Drop for Files {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// The tag or class of each rule's first selector, in order.
+fn rule_names(stylesheet: &Stylesheet) -> Vec<String> {
+    stylesheet.rules.iter()
+        .map(|rule| match rule.selectors[0] {
+            Selector::Simple(ref simple) => simple.tag_name.clone()
+                .or_else(|| simple.classes.first().cloned())
+                .unwrap_or_default(),
+            Selector::Compound(..) => "compound".to_string(),
+        })
+        .collect()
+}
+
+fn layer(name: &str) -> LayerName {
+    LayerName(name.split('.').map(|segment| LayerSegment::Named(segment.to_string())).collect())
+}
+
+#[test]
+fn imports_are_inlined_in_place() {
+    let files = Files::new("inline", &[
+        ("main.css", "@import url(\"partials/base.css\"); @import 'theme.css'; main { color: red }"),
+        ("partials/base.css", "@import \"../reset.css\"; base { color: red }"),
+        ("reset.css", "reset { margin: 0 }"),
+        ("theme.css", "theme { color: blue }"),
+    ]);
+    let stylesheet = files.parse("main.css");
+    assert_eq!(rule_names(&stylesheet), ["reset", "base", "theme", "main"]);
+    // Imported rules remember the file they were written in.
+    let url = stylesheet.rules[1].location.url.as_ref().unwrap();
+    assert!(url.path().ends_with("partials/base.css"), "{}", url);
+}
+
+#[test]
+fn media_conditions_apply_to_imported_rules() {
+    let files = Files::new("media", &[
+        ("main.css", "@import \"print.css\" print; @import \"wide.css\" screen and (min-width: 1000px);"),
+        ("print.css", "print { color: black } @media (orientation: portrait) { portrait { color: red } }"),
+        ("wide.css", "wide { color: blue }"),
+    ]);
+    let stylesheet = files.parse("main.css");
+    assert_eq!(rule_names(&stylesheet), ["print", "portrait", "wide"]);
+    assert!(matches!(stylesheet.rules[1].conditions[..], [RuleCondition::Media(_), RuleCondition::Media(_)]));
+
+    let screen = MediaEnvironment::new(1280.0, 800.0);
+    let mut print = MediaEnvironment::new(600.0, 800.0);
+    print.media_type = "print".to_string();
+    let applies = |env: &MediaEnvironment| stylesheet.rules.iter().map(|rule| rule.applies_in(env)).collect::<Vec<_>>();
+    assert_eq!(applies(&screen), [false, false, true]);
+    assert_eq!(applies(&print), [true, true, false]);
+}
+
+#[test]
+fn layers_apply_to_imported_rules() {
+    let files = Files::new("layers", &[
+        ("main.css", "@import \"vendor.css\" layer(vendor); @import \"anonymous.css\" layer; main { color: red }"),
+        ("vendor.css", "vendor { color: red } @layer buttons { buttons { color: red } }"),
+        ("anonymous.css", "anonymous { color: red }"),
+    ]);
+    let stylesheet = files.parse("main.css");
+    assert_eq!(stylesheet.rules[0].layer, Some(layer("vendor")));
+    assert_eq!(stylesheet.rules[1].layer, Some(layer("vendor.buttons")));
+    assert!(stylesheet.rules[2].layer.is_some());
+    assert_eq!(stylesheet.rules[3].layer, None);
+    assert!(stylesheet.layers.starts_with(&[layer("vendor"), layer("vendor.buttons")]));
+}
+
+#[test]
+fn cycles_are_skipped() {
+    let files = Files::new("cycle", &[
+        ("a.css", "@import \"b.css\"; a { color: red }"),
+        ("b.css", "@import \"c.css\"; b { color: red }"),
+        ("c.css", "@import \"a.css\"; @import \"c.css\"; c { color: red }"),
+    ]);
+    assert_eq!(rule_names(&files.parse("a.css")), ["c", "b", "a"]);
+    assert_eq!(rule_names(&files.parse("b.css")), ["a", "c", "b"]);
+}
+
+#[test]
+fn the_same_file_may_be_imported_twice() {
+    let files = Files::new("twice", &[
+        ("main.css", "@import \"shared.css\"; @import \"other.css\"; main { color: red }"),
+        ("other.css", "@import \"shared.css\"; other { color: red }"),
+        ("shared.css", "shared { color: red }"),
+    ]);
+    assert_eq!(rule_names(&files.parse("main.css")), ["shared", "shared", "other", "main"]);
+}
+
+#[test]
+fn missing_files_and_late_imports_are_skipped() {
+    let files = Files::new("skipped", &[
+        ("main.css", "@import \"missing.css\"; main { color: red } @import \"late.css\";"),
+        ("late.css", "late { color: red }"),
+    ]);
+    assert_eq!(rule_names(&files.parse("main.css")), ["main"]);
+}
+
+#[test]
+fn unsupported_imports_are_not_loaded() {
+    let files = Files::new("supports", &[
+        ("main.css", "@import \"grid.css\" supports(display: grid); @import \"block.css\" supports(display: block);"),
+        ("grid.css", "grid { color: red }"),
+        ("block.css", "block { color: red }"),
+    ]);
+    assert_eq!(rule_names(&files.parse("main.css")), ["block"]);
+}
+
+#[test]
+fn cascade_order_is_as_if_inlined() {
+    let files = Files::new("cascade", &[
+        ("main.css", "@import \"base.css\"; p { color: blue }"),
+        ("base.css", "p { color: red } p.note { color: green }"),
+    ]);
+    let stylesheets = [user_agent_stylesheet(), files.parse("main.css")];
+    let html = "<html><body><p id=\"plain\">A</p><p id=\"note\" class=\"note\">B</p></body></html>";
+    let styles = styles_with(html, &stylesheets, &MediaEnvironment::new(800.0, 600.0));
+    assert_eq!(styles["plain"].color, color("blue"));
+    assert_eq!(styles["note"].color, color("green"));
+}
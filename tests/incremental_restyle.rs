@@ -0,0 +1,283 @@
+use ukechug::css::{Parser, MediaEnvironment, Stylesheet, user_agent_stylesheet};
+use ukechug::dom::{Node, NodeType};
+use ukechug::html::HTMLParser;
+use ukechug::style::{StyledNode, StyledDocument, style_tree};
+
+const CSS: &str = "
+    html { font-size: 16px; }
+    html.large { font-size: 24px; }
+    .section { margin: 1em; padding: 0.5rem; counter-reset: item; }
+    .section:nth-child(2n) { background: lightgray; }
+    .highlight li { color: red; }
+    .highlight + .section h2 { font-size: 2em; }
+    .section > h2::before { content: counter(chapter) \". \"; counter-increment: chapter; }
+    #main .item { font-weight: bold; }
+    [data-state=open] > ul { padding: 8px; }
+    [data-state] { border-top-width: 2px; }
+    li { counter-increment: item; }
+    li:first-child, li:last-child { width: 50%; }
+    li.done { color: gray; }
+    li:nth-child(2 of .done) { height: 10px; }
+    li:not(.done) span { font-size: 1.2em; }
+    .note::after { content: \" [\" attr(data-id) \"]\"; }
+    ul:has(.flag) { margin-left: 3px; }
+    p:empty { display: none; }
+    p:empty + .tail { color: red; }
+    p:empty ~ .tail em { font-size: 2em; }
+    li:last-child ~ .tail { width: 10px; }
+    @media (max-width: 600px) { .section { margin: 0; } }
+";
+
+fn page() -> String {
+    let mut html = String::from("<html><body>");
+    for section in 0..8 {
+        html.push_str(&format!("<div class=\"section\" id=\"s{}\"><h2>Section {}</h2><ul>", section, section));
+        for item in 0..6 {
+            html.push_str(&format!(
+                "<li class=\"item note\" data-id=\"{}\"><span>Item {}</span></li>",
+                item, item
+            ));
+        }
+        html.push_str("</ul><p></p><span class=\"tail\"><em>Tail</em></span></div>");
+    }
+    html.push_str("</body></html>");
+    html
+}
+
+fn stylesheets(css: &str) -> Vec<Stylesheet> {
+    vec![user_agent_stylesheet(), Parser::new(css.to_string()).parse_stylesheet()]
+}
+
+fn document() -> StyledDocument {
+    document_with(CSS)
+}
+
+fn document_with(css: &str) -> StyledDocument {
+    let root = HTMLParser::new().parse(&page());
+    let mut document = StyledDocument::new(root, stylesheets(css), MediaEnvironment::new(800.0, 600.0));
+    document.restyle();
+    document
+}
+
+fn assert_same_tree(full: &StyledNode, incremental: &StyledNode) {
+    assert!(std::ptr::eq(full.node, incremental.node));
+    assert_eq!(full.pseudo_element, incremental.pseudo_element);
+    assert_eq!(*full.style, *incremental.style);
+    assert_eq!(full.generated_text, incremental.generated_text);
+    assert_eq!(full.children.len(), incremental.children.len());
+    for (full_child, incremental_child) in full.children.iter().zip(&incremental.children) {
+        assert_same_tree(full_child, incremental_child);
+    }
+}
+
+/// Restyles `document` and checks the result against a full style pass.
+fn assert_restyle_matches_full(document: &mut StyledDocument) -> usize {
+    let statistics = document.restyle();
+    assert!(!document.is_dirty());
+    let full = style_tree(document.root(), document.stylesheets(), document.media());
+    assert_same_tree(&full, &document.styled_tree());
+    statistics.elements
+}
+
+/// The path to the element with the given id.
+fn path_to(node: &Node, id: &str) -> Option<Vec<usize>> {
+    if let NodeType::Element(ref elem) = node.node_type {
+        if elem.id().map(String::as_str) == Some(id) {
+            return Some(Vec::new());
+        }
+    }
+    node.children.iter().enumerate().find_map(|(index, child)| {
+        path_to(child, id).map(|mut path| {
+            path.insert(0, index);
+            path
+        })
+    })
+}
+
+fn element(document: &StyledDocument, id: &str) -> Vec<usize> {
+    path_to(document.root(), id).expect("no element with that id")
+}
+
+fn element_count(node: &Node) -> usize {
+    let own = usize::from(matches!(node.node_type, NodeType::Element(_)));
+    own + node.children.iter().map(element_count).sum::<usize>()
+}
+
+#[test]
+fn first_restyle_styles_everything() {
+    let root = HTMLParser::new().parse(&page());
+    let elements = element_count(&root);
+    let mut document = StyledDocument::new(root, stylesheets(CSS), MediaEnvironment::new(800.0, 600.0));
+    assert!(document.is_dirty());
+    assert_eq!(assert_restyle_matches_full(&mut document), elements);
+    assert_eq!(document.restyle().elements, 0);
+}
+
+#[test]
+fn class_changes_restyle_affected_elements() {
+    let mut document = document();
+    let total = element_count(document.root());
+
+    let section = element(&document, "s5");
+    document.set_attribute(&section, "class", Some("section highlight"));
+    let restyled = assert_restyle_matches_full(&mut document);
+    assert!(restyled > 1 && restyled < total / 2, "restyled {} of {}", restyled, total);
+
+    document.set_attribute(&section, "class", Some("section"));
+    assert_restyle_matches_full(&mut document);
+
+    // No selector tests this class, so only the element itself is restyled.
+    let mut item = section.clone();
+    item.extend([1, 2]);
+    document.set_attribute(&item, "class", Some("item note unused"));
+    assert_eq!(assert_restyle_matches_full(&mut document), 1);
+
+    document.set_attribute(&item, "class", Some("item note done"));
+    assert_restyle_matches_full(&mut document);
+    item.pop();
+    item.push(4);
+    document.set_attribute(&item, "class", Some("item note done"));
+    assert_restyle_matches_full(&mut document);
+    item.pop();
+    item.push(0);
+    document.set_attribute(&item, "class", Some("item note flag"));
+    assert_restyle_matches_full(&mut document);
+}
+
+#[test]
+fn id_and_attribute_changes_match_full_restyle() {
+    let mut document = document();
+    let body = element(&document, "s0")[..1].to_vec();
+    document.set_attribute(&body, "id", Some("main"));
+    assert_restyle_matches_full(&mut document);
+
+    let section = element(&document, "s5");
+    document.set_attribute(&section, "data-state", Some("open"));
+    assert_restyle_matches_full(&mut document);
+    document.set_attribute(&section, "data-state", Some("closed"));
+    assert_restyle_matches_full(&mut document);
+    document.set_attribute(&section, "data-state", None);
+    assert_restyle_matches_full(&mut document);
+
+    let mut item = section.clone();
+    item.extend([1, 3]);
+    document.set_attribute(&item, "data-id", Some("changed"));
+    assert_restyle_matches_full(&mut document);
+    document.set_attribute(&item, "style", Some("color: blue; font-size: 3em"));
+    assert_restyle_matches_full(&mut document);
+
+    document.set_attribute(&body, "id", None);
+    assert_restyle_matches_full(&mut document);
+}
+
+#[test]
+fn root_font_size_changes_restyle_rem_lengths() {
+    let mut document = document();
+    document.set_attribute(&[], "class", Some("large"));
+    assert_restyle_matches_full(&mut document);
+    document.set_attribute(&[], "class", None);
+    assert_restyle_matches_full(&mut document);
+}
+
+#[test]
+fn child_list_changes_match_full_restyle() {
+    // `:has()` makes every child list change restyle the whole document, so
+    // also check without it.
+    for css in [CSS.to_string(), CSS.replace("ul:has(.flag) { margin-left: 3px; }", "")] {
+        child_list_changes(document_with(&css));
+    }
+}
+
+/// Inserts and removes children in `document`, checking each restyle
+/// against a full style pass.
+fn child_list_changes(mut document: StyledDocument) {
+    let mut list = element(&document, "s3");
+    list.push(1);
+
+    let item = HTMLParser::new().parse("<li class=\"item done flag\" data-id=\"new\"><span>New</span></li>");
+    document.insert_child(&list, 0, item);
+    assert_restyle_matches_full(&mut document);
+
+    let removed = document.remove_child(&list, 3);
+    assert_restyle_matches_full(&mut document);
+    let last = document.node(&list).children.len();
+    document.insert_child(&list, last, removed);
+    assert_restyle_matches_full(&mut document);
+
+    let removed = document.remove_child(&list, 0);
+    assert_restyle_matches_full(&mut document);
+
+    let mut paragraph = element(&document, "s3");
+    paragraph.push(2);
+    document.insert_child(&paragraph, 0, Node::text("Not empty".to_string()));
+    assert_restyle_matches_full(&mut document);
+    // The tail after the paragraph no longer follows an empty element, and
+    // then does again.
+    document.remove_child(&paragraph, 0);
+    assert_restyle_matches_full(&mut document);
+
+    let body = element(&document, "s0")[..1].to_vec();
+    let section = document.remove_child(&body, 1);
+    assert_restyle_matches_full(&mut document);
+    document.insert_child(&body, 4, section);
+    assert_restyle_matches_full(&mut document);
+    document.insert_child(&body, 0, removed);
+    assert_restyle_matches_full(&mut document);
+}
+
+#[test]
+fn stylesheet_and_media_changes_restyle_everything() {
+    let mut document = document();
+    let total = element_count(document.root());
+
+    document.set_stylesheets(stylesheets(&format!("{} li {{ color: green; }}", CSS)));
+    assert_eq!(assert_restyle_matches_full(&mut document), total);
+
+    document.set_media(MediaEnvironment::new(500.0, 600.0));
+    assert_eq!(assert_restyle_matches_full(&mut document), total);
+}
+
+/// A deterministic pseudo-random sequence of changes.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, bound: usize) -> usize {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((self.0 >> 33) as usize) % bound
+    }
+}
+
+#[test]
+fn random_changes_match_full_restyle() {
+    let mut document = document();
+    let mut random = Lcg(7);
+    let classes = ["section", "section highlight", "item note", "item done", "item note flag", "highlight", ""];
+    let states = [None, Some("open"), Some("closed")];
+    for step in 0..120 {
+        let section = random.next(8);
+        let mut path = element(&document, &format!("s{}", section));
+        let items = document.node(&path).children[1].children.len();
+        match random.next(5) {
+            0 => document.set_attribute(&path, "class", Some(classes[random.next(classes.len())])),
+            1 => document.set_attribute(&path, "data-state", states[random.next(states.len())]),
+            2 if items > 0 => {
+                path.extend([1, random.next(items)]);
+                document.set_attribute(&path, "class", Some(classes[random.next(classes.len())]));
+            }
+            3 if items > 1 => {
+                path.push(1);
+                document.remove_child(&path, random.next(items));
+            }
+            _ => {
+                path.push(1);
+                let item = HTMLParser::new().parse("<li class=\"item note\" data-id=\"7\"><span>Added</span></li>");
+                document.insert_child(&path, random.next(items + 1), item);
+            }
+        }
+        // Batch some changes into one restyle.
+        if step % 3 == 2 {
+            assert_restyle_matches_full(&mut document);
+        }
+    }
+    assert_restyle_matches_full(&mut document);
+}
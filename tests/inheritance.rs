@@ -0,0 +1,128 @@
+mod common;
+
+use common::{stylesheet, styles, styles_with, color};
+use ukechug::css::{Value, Origin, Color, MediaEnvironment};
+use ukechug::style::{Display, LengthPercentage, LengthPercentageOrAuto, Sides};
+use ukechug::style::properties::{is_inherited, initial_value};
+
+const PAGE: &str = "
+    <html><body id=\"body\">
+        <div id=\"outer\"><p id=\"inner\">Text <span id=\"span\">more</span></p></div>
+    </body></html>
+";
+
+fn px(value: f32) -> LengthPercentageOrAuto {
+    LengthPercentageOrAuto::LengthPercentage(LengthPercentage::Length(value))
+}
+
+#[test]
+fn inherited_properties_pass_down() {
+    let styles = styles(PAGE, "body { color: blue; font-size: 20px; list-style-type: square }");
+    assert_eq!(styles["inner"].color, color("blue"));
+    assert_eq!(styles["span"].color, color("blue"));
+    assert_eq!(styles["span"].font_size, 20.0);
+}
+
+#[test]
+fn other_properties_do_not() {
+    let styles = styles(PAGE, "
+        body { width: 300px; padding: 5px; border-width: 2px; background-color: red; margin: 3px }
+        #outer { margin: 0 }
+    ");
+    assert_eq!(styles["outer"].width, LengthPercentageOrAuto::Auto);
+    assert_eq!(styles["outer"].padding, Sides::all(LengthPercentage::Length(0.0)));
+    assert_eq!(styles["outer"].border_width, Sides::all(0.0));
+    assert_eq!(styles["outer"].background_color, Color::TRANSPARENT);
+}
+
+#[test]
+fn inherit() {
+    let styles = styles(PAGE, "
+        #outer { width: 300px; border-width: 4px; color: green; display: block }
+        #inner { width: inherit; border-width: inherit; color: red }
+        #span { color: inherit; display: inherit }
+    ");
+    assert_eq!(styles["inner"].width, px(300.0));
+    assert_eq!(styles["inner"].border_width, Sides::all(4.0));
+    assert_eq!(styles["span"].color, color("red"));
+    assert_eq!(styles["span"].display, Display::Block);
+}
+
+#[test]
+fn initial() {
+    let styles = styles(PAGE, "
+        body { color: blue; font-size: 30px }
+        #outer { color: initial; display: initial; font-size: initial; margin: 7px }
+        #inner { margin: initial }
+    ");
+    assert_eq!(styles["outer"].color, color("black"));
+    assert_eq!(styles["outer"].display, Display::Inline);
+    assert_eq!(styles["outer"].font_size, 16.0);
+    assert_eq!(styles["inner"].margin, Sides::all(px(0.0)));
+    // Descendants inherit the initial value.
+    assert_eq!(styles["span"].color, color("black"));
+}
+
+#[test]
+fn unset() {
+    let styles = styles(PAGE, "
+        body { color: blue; width: 500px }
+        #outer { color: red; width: 200px }
+        #outer { color: unset; width: unset }
+    ");
+    assert_eq!(styles["outer"].color, color("blue"));
+    assert_eq!(styles["outer"].width, LengthPercentageOrAuto::Auto);
+}
+
+#[test]
+fn revert_goes_back_to_the_previous_origin() {
+    let sheets = [
+        stylesheet("div { display: block; color: green }").with_origin(Origin::UserAgent),
+        stylesheet("p { color: yellow }").with_origin(Origin::User),
+        stylesheet("div { display: inline; color: red } div { display: revert; color: revert } p { color: revert }"),
+    ];
+    let styles = styles_with(PAGE, &sheets, &MediaEnvironment::new(800.0, 600.0));
+    assert_eq!(styles["outer"].display, Display::Block);
+    assert_eq!(styles["outer"].color, color("green"));
+    assert_eq!(styles["inner"].color, color("yellow"));
+}
+
+#[test]
+fn revert_layer_goes_back_to_the_previous_layer() {
+    let styles = styles(PAGE, "
+        @layer base, theme;
+        @layer base { p { color: green } }
+        @layer theme { p { color: red } p { color: revert-layer } }
+        span { color: blue } span { color: revert-layer }
+    ");
+    assert_eq!(styles["inner"].color, color("green"));
+    // Reverting the unlayered rules falls back to all the layers, and here
+    // to inheritance.
+    assert_eq!(styles["span"].color, color("green"));
+}
+
+#[test]
+fn relative_font_sizes_inherit_as_px() {
+    let styles = styles(PAGE, "
+        html { font-size: 10px }
+        body { font-size: 2em }
+        #outer { font-size: 150% }
+        #inner { font-size: 0.5rem; width: 10em }
+    ");
+    assert_eq!(styles["body"].font_size, 20.0);
+    assert_eq!(styles["outer"].font_size, 30.0);
+    assert_eq!(styles["inner"].font_size, 5.0);
+    assert_eq!(styles["inner"].width, px(50.0));
+    assert_eq!(styles["span"].font_size, 5.0);
+}
+
+#[test]
+fn property_registry() {
+    assert!(is_inherited("color"));
+    assert!(is_inherited("white-space"));
+    assert!(!is_inherited("margin-left"));
+    assert!(!is_inherited("not-a-property"));
+    assert_eq!(initial_value("width"), Some(Value::Keyword("auto".to_string())));
+    assert_eq!(initial_value("text-indent"), Some(Value::Number(0.0)));
+    assert_eq!(initial_value("not-a-property"), None);
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use common::{stylesheet, styles, color};
+use ukechug::css::{Color, MediaEnvironment};
+
+const PAGE: &str = "<html><body><p id=\"target\" class=\"note\">Text</p></body></html>";
+
+fn target_color(css: &str) -> Color {
+    styles(PAGE, css)["target"].color
+}
+
+/// Whether the rule inside `@supports <condition>` applies.
+fn supports(condition: &str) -> bool {
+    let stylesheet = stylesheet(&format!("@supports {} {{ p {{ color: red }} }}", condition));
+    stylesheet.rules[0].applies_in(&MediaEnvironment::new(800.0, 600.0))
+}
+
+#[test]
+fn later_layers_win() {
+    let css = "
+        @layer reset, base, components;
+        @layer components { p { color: blue } }
+        @layer reset { p { color: red } }
+        @layer base { p { color: green } }
+    ";
+    assert_eq!(target_color(css), color("blue"));
+    // The first declaration of a layer decides its place, not later blocks.
+    assert_eq!(target_color("@layer b { p { color: red } } @layer a { p { color: blue } } @layer b { p { color: green } }"), color("blue"));
+}
+
+#[test]
+fn layer_order_beats_specificity() {
+    let css = "
+        @layer low { #target.note { color: red } }
+        @layer high { p { color: green } }
+    ";
+    assert_eq!(target_color(css), color("green"));
+}
+
+#[test]
+fn unlayered_rules_beat_layers() {
+    let css = "
+        p { color: green }
+        @layer base { #target { color: red } }
+    ";
+    assert_eq!(target_color(css), color("green"));
+}
+
+#[test]
+fn important_declarations_reverse_layer_order() {
+    let css = "
+        @layer first, second;
+        @layer first { p { color: red !important } }
+        @layer second { p { color: blue !important } }
+        p { color: green !important }
+    ";
+    assert_eq!(target_color(css), color("red"));
+}
+
+#[test]
+fn nested_layers() {
+    let css = "
+        @layer framework { p { color: green } }
+        @layer framework.base { p { color: red } }
+        @layer framework { @layer theme { p { color: blue } } }
+    ";
+    // A layer's own rules beat those of its sublayers.
+    assert_eq!(target_color(css), color("green"));
+    let css = "
+        @layer framework.base { p { color: red } }
+        @layer framework.theme { p { color: blue } }
+        @layer other { p { color: yellow } }
+    ";
+    assert_eq!(target_color(css), color("yellow"));
+    assert_eq!(stylesheet(css).layers.len(), 4);
+}
+
+#[test]
+fn anonymous_layers_are_distinct() {
+    let css = "
+        @layer { p { color: red } }
+        @layer { p { color: blue } }
+    ";
+    assert_eq!(target_color(css), color("blue"));
+}
+
+#[test]
+fn supported_declarations() {
+    assert!(supports("(display: block)"));
+    assert!(supports("(display: inline-block)"));
+    assert!(!supports("(display: grid)"));
+    assert!(supports("(width: calc(100% - 10px))"));
+    assert!(supports("(margin: 0 auto)"));
+    assert!(!supports("(margin: 1px 2px 3px 4px 5px)"));
+    assert!(supports("(color: #ff0000)"));
+    assert!(!supports("(gap: 10px)"));
+    assert!(supports("(--anything: at all)"));
+    assert!(supports("(list-style-type: upper-roman)"));
+}
+
+#[test]
+fn css_wide_keywords_are_supported() {
+    for condition in ["(display: inherit)", "(margin-left: unset)", "(width: revert-layer)", "(color: initial)", "(margin: revert)"] {
+        assert!(supports(condition), "{}", condition);
+    }
+    assert!(!supports("(gap: inherit)"));
+}
+
+#[test]
+fn only_known_colors_are_supported() {
+    for condition in ["(color: red)", "(color: GRAY)", "(color: transparent)", "(border-color: currentcolor)", "(color: #00ff00)"] {
+        assert!(supports(condition), "{}", condition);
+    }
+    assert!(!supports("(color: notacolor)"));
+    assert!(!supports("(background-color: auto)"));
+    assert!(!supports("(color: 10px)"));
+}
+
+#[test]
+fn supports_operators() {
+    assert!(supports("not (display: grid)"));
+    assert!(supports("(display: block) and (width: 10px)"));
+    assert!(!supports("(display: block) and (display: grid)"));
+    assert!(supports("(display: grid) or (display: block)"));
+    assert!(supports("((display: grid) or (display: flex)) or (not (float: left))"));
+    // Unknown functions are never supported.
+    assert!(!supports("selector(a > b)"));
+    assert!(supports("not selector(a > b)"));
+}
+
+#[test]
+fn conditional_rules_in_the_cascade() {
+    let css = "
+        p { color: black }
+        @supports (display: grid) { p { color: red } }
+        @supports not (display: grid) { @layer fallback { p { color: blue } } p { color: green } }
+    ";
+    assert_eq!(target_color(css), color("green"));
+    assert_eq!(target_color("@supports (color: red) { @media (min-width: 100px) { p { color: blue } } }"), color("blue"));
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use common::{stylesheet, styles_with};
+use ukechug::css::{Parser, Value, Unit, LengthContext, MediaEnvironment, user_agent_stylesheet};
+use ukechug::html::HTMLParser;
+use ukechug::layout::{layout_tree, Dimensions};
+use ukechug::style::style_tree;
+
+fn value(text: &str) -> Value {
+    Parser::new(text.to_string()).try_parse_standalone_value().unwrap()
+}
+
+/// Resolves `text` with `%` against 500px, 20px ems, 10px rems and an
+/// 800x600 viewport.
+fn resolve(text: &str) -> f32 {
+    let context = LengthContext {
+        percentage_base: 500.0,
+        font_size: 20.0,
+        root_font_size: 10.0,
+        ..LengthContext::new(800.0, 600.0)
+    };
+    value(text).resolve(&context)
+}
+
+#[test]
+fn same_units_fold_at_parse_time() {
+    assert_eq!(value("calc(2 * 20px)"), Value::Length(40.0, Unit::Px));
+    assert_eq!(value("calc((1px + 2px) * 3)"), Value::Length(9.0, Unit::Px));
+    assert_eq!(value("calc(50% / 2 - 5%)"), Value::Length(20.0, Unit::Percent));
+    assert_eq!(value("calc(1.5em)"), Value::Length(1.5, Unit::Em));
+    assert_eq!(value("calc(3 * 4 - 2)"), Value::Number(10.0));
+    assert_eq!(value("min(10px, 20px, 5px)"), Value::Length(5.0, Unit::Px));
+    assert_eq!(value("clamp(1rem, 3rem, 2rem)"), Value::Length(2.0, Unit::Rem));
+}
+
+#[test]
+fn mixed_units_are_kept_for_layout() {
+    assert!(matches!(value("calc(100% - 2 * 20px)"), Value::Calc(_)));
+    assert!(matches!(value("max(10vw, 2em)"), Value::Calc(_)));
+    assert!(matches!(value("CALC(1px + 1em)"), Value::Calc(_)));
+}
+
+#[test]
+fn resolution_against_the_context() {
+    assert_eq!(resolve("calc(100% - 2 * 20px)"), 460.0);
+    assert_eq!(resolve("calc(1em + 1rem + 10vw + 10vh)"), 20.0 + 10.0 + 80.0 + 60.0);
+    assert_eq!(resolve("calc((100% - 20px) / 4)"), 120.0);
+    assert_eq!(resolve("min(50%, 2em)"), 40.0);
+    assert_eq!(resolve("max(50%, 2em)"), 250.0);
+    assert_eq!(resolve("max(10px, min(5em, 10%, 1000px))"), 50.0);
+    assert_eq!(resolve("calc(min(10px, 1em) * 2 + max(1px, 0.1%))"), 21.0);
+}
+
+#[test]
+fn clamp_keeps_values_in_range() {
+    assert_eq!(resolve("clamp(14px, 1vw, 20px)"), 14.0);
+    assert_eq!(resolve("clamp(14px, 2vw, 20px)"), 16.0);
+    assert_eq!(resolve("clamp(14px, 3vw, 20px)"), 20.0);
+    // The minimum wins over the maximum.
+    assert_eq!(resolve("clamp(30px, 1px, 20px)"), 30.0);
+}
+
+#[test]
+fn invalid_expressions_are_errors() {
+    for text in ["calc(1px * 2px)", "calc(1px / 2px)", "calc(1px + 2)", "calc(1px +2px)", "min()", "clamp(1px, 2px)"] {
+        assert!(Parser::new(text.to_string()).try_parse_standalone_value().is_err(), "{}", text);
+    }
+}
+
+#[test]
+fn fluid_font_sizes() {
+    let html = "<html><body><p id=\"target\">Text</p></body></html>";
+    let stylesheets = [user_agent_stylesheet(), stylesheet("p { font-size: clamp(14px, 2vw, 20px) }")];
+    let font_size = |width| styles_with(html, &stylesheets, &MediaEnvironment::new(width, 600.0))["target"].font_size;
+    assert_eq!(font_size(500.0), 14.0);
+    assert_eq!(font_size(800.0), 16.0);
+    assert_eq!(font_size(1200.0), 20.0);
+}
+
+#[test]
+fn widths_resolve_against_the_containing_block() {
+    let root = HTMLParser::new().parse("<html><body><div><p>Text</p></div></body></html>");
+    let css = "
+        body { margin: 0; font-size: 10px }
+        div { width: calc(100% - 2 * 20px); padding-left: calc(1em + 5px) }
+        p { width: max(50%, 100px); margin: 0 }
+    ";
+    let stylesheets = [user_agent_stylesheet(), stylesheet(css)];
+    let styled = style_tree(&root, &stylesheets, &MediaEnvironment::new(800.0, 600.0));
+    let mut viewport: Dimensions = Default::default();
+    viewport.content.width = 800.0;
+    let layout = layout_tree(&styled, viewport);
+
+    let div = &layout.children[0].children[0];
+    assert_eq!(div.dimensions.content.width, 760.0);
+    assert_eq!(div.dimensions.padding.left, 15.0);
+    let paragraph = &div.children[0];
+    assert_eq!(paragraph.dimensions.content.width, 380.0);
+}
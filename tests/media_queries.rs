@@ -0,0 +1,129 @@
+mod common;
+
+use common::{stylesheet, styles_with, color};
+use ukechug::css::{MediaEnvironment, ColorScheme, user_agent_stylesheet};
+
+fn phone() -> MediaEnvironment {
+    MediaEnvironment::new(375.0, 667.0)
+}
+
+fn tablet() -> MediaEnvironment {
+    MediaEnvironment::new(768.0, 1024.0)
+}
+
+fn desktop() -> MediaEnvironment {
+    MediaEnvironment::new(1280.0, 800.0)
+}
+
+/// Whether a rule inside `@media <query>` applies in `env`.
+fn applies(query: &str, env: &MediaEnvironment) -> bool {
+    let stylesheet = stylesheet(&format!("@media {} {{ p {{ color: red }} }}", query));
+    stylesheet.rules[0].applies_in(env)
+}
+
+#[test]
+fn width_and_height() {
+    assert!(applies("(min-width: 600px)", &tablet()));
+    assert!(!applies("(min-width: 600px)", &phone()));
+    assert!(applies("(max-width: 768px)", &tablet()));
+    assert!(applies("(width: 375px)", &phone()));
+    assert!(applies("(min-height: 50em)", &desktop()));
+    assert!(!applies("(max-height: 40em)", &tablet()));
+}
+
+#[test]
+fn range_syntax() {
+    assert!(applies("(width >= 600px)", &tablet()));
+    assert!(!applies("(width > 768px)", &tablet()));
+    assert!(applies("(600px <= width < 1024px)", &tablet()));
+    assert!(!applies("(600px <= width < 1024px)", &desktop()));
+    assert!(applies("(400px > width)", &phone()));
+    assert!(applies("(height <= 800px)", &desktop()));
+}
+
+#[test]
+fn orientation_and_aspect_ratio() {
+    assert!(applies("(orientation: portrait)", &phone()));
+    assert!(applies("(orientation: landscape)", &desktop()));
+    assert!(applies("(aspect-ratio: 16/10)", &desktop()));
+    assert!(applies("(min-aspect-ratio: 1/1)", &desktop()));
+    assert!(!applies("(min-aspect-ratio: 1/1)", &tablet()));
+}
+
+#[test]
+fn resolution() {
+    let mut retina = phone();
+    retina.resolution = 2.0;
+    assert!(applies("(min-resolution: 2dppx)", &retina));
+    assert!(applies("(resolution >= 192dpi)", &retina));
+    assert!(applies("(resolution: 2x)", &retina));
+    assert!(!applies("(min-resolution: 2dppx)", &phone()));
+}
+
+#[test]
+fn user_preferences() {
+    let mut dark = desktop();
+    dark.color_scheme = ColorScheme::Dark;
+    dark.reduced_motion = true;
+    assert!(applies("(prefers-color-scheme: dark)", &dark));
+    assert!(!applies("(prefers-color-scheme: dark)", &desktop()));
+    assert!(applies("(prefers-reduced-motion: reduce)", &dark));
+    assert!(applies("(prefers-reduced-motion)", &dark));
+    assert!(!applies("(prefers-reduced-motion)", &desktop()));
+    assert!(applies("(prefers-reduced-motion: no-preference)", &desktop()));
+}
+
+#[test]
+fn media_types() {
+    let mut print = desktop();
+    print.media_type = "print".to_string();
+    assert!(applies("screen", &desktop()));
+    assert!(!applies("screen", &print));
+    assert!(applies("print", &print));
+    assert!(applies("all", &print));
+    assert!(applies("only screen and (min-width: 1000px)", &desktop()));
+    assert!(applies("not print", &desktop()));
+    assert!(!applies("not print", &print));
+    assert!(applies("not screen and (max-width: 600px)", &desktop()));
+}
+
+#[test]
+fn and_or_not() {
+    assert!(applies("(min-width: 600px) and (orientation: portrait)", &tablet()));
+    assert!(!applies("(min-width: 600px) and (orientation: portrait)", &desktop()));
+    assert!(applies("(max-width: 400px) or (min-width: 1200px)", &desktop()));
+    assert!(!applies("(max-width: 400px) or (min-width: 1200px)", &tablet()));
+    assert!(applies("not (min-width: 600px)", &phone()));
+    assert!(applies("(not (orientation: landscape)) and ((width < 500px) or (height > 2000px))", &phone()));
+    // A list matches when any of its queries does.
+    assert!(applies("print, (max-width: 400px)", &phone()));
+}
+
+#[test]
+fn unknown_features_never_match() {
+    assert!(!applies("(pointer-depth: 3)", &desktop()));
+    assert!(applies("not (pointer-depth: 3)", &desktop()));
+    assert!(!applies("(min-width: 50%)", &desktop()));
+}
+
+#[test]
+fn one_page_at_three_widths() {
+    let html = "<html><body><p id=\"target\">Text</p></body></html>";
+    let css = "
+        p { color: black; font-size: 14px }
+        @media (min-width: 600px) { p { color: green } }
+        @media (min-width: 1024px) { p { color: blue; font-size: 18px } }
+        @media print { p { color: red } }
+    ";
+    let stylesheets = [user_agent_stylesheet(), stylesheet(css)];
+    let target = |env: &MediaEnvironment| styles_with(html, &stylesheets, env).remove("target").unwrap();
+    assert_eq!(target(&phone()).color, color("black"));
+    assert_eq!(target(&tablet()).color, color("green"));
+    assert_eq!(target(&desktop()).color, color("blue"));
+    assert_eq!(target(&desktop()).font_size, 18.0);
+    assert_eq!(target(&phone()).font_size, 14.0);
+
+    let mut print = desktop();
+    print.media_type = "print".to_string();
+    assert_eq!(target(&print).color, color("red"));
+}
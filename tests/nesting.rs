@@ -0,0 +1,100 @@
+mod common;
+
+use common::{stylesheet, styles, color};
+
+/// Checks that nested `css` parses to the same rules as `flat`.
+fn assert_desugars_to(css: &str, flat: &str) {
+    assert_eq!(stylesheet(css).rules, stylesheet(flat).rules, "{}", css);
+}
+
+#[test]
+fn nested_selectors_with_ampersand() {
+    assert_desugars_to(
+        ".card { padding: 8px; & h2 { color: red } &:hover { color: blue } &.wide { width: 100% } }",
+        ".card { padding: 8px } :is(.card) h2 { color: red } :is(.card):hover { color: blue } :is(.card).wide { width: 100% }",
+    );
+    assert_desugars_to(
+        ".card { .sidebar & { color: red } & + & { margin: 0 } }",
+        ".sidebar :is(.card) { color: red } :is(.card) + :is(.card) { margin: 0 }",
+    );
+}
+
+#[test]
+fn relative_selectors_get_an_implicit_ampersand() {
+    assert_desugars_to(
+        "ul { > li { color: red } + p { color: blue } ~ p { color: green } em { color: yellow } }",
+        ":is(ul) > li { color: red } :is(ul) + p { color: blue } :is(ul) ~ p { color: green } :is(ul) em { color: yellow }",
+    );
+}
+
+#[test]
+fn selector_lists_nest_through_is() {
+    assert_desugars_to(
+        "h1, .title { & span, & em { color: red } }",
+        ":is(h1, .title) span, :is(h1, .title) em { color: red }",
+    );
+    assert_desugars_to(
+        "nav { ul { li { color: red } } }",
+        ":is(:is(nav) ul) li { color: red }",
+    );
+}
+
+#[test]
+fn declarations_after_nested_rules_belong_to_the_parent() {
+    assert_desugars_to(
+        "p { color: red; & em { color: blue } margin: 0 }",
+        "p { color: red; margin: 0 } :is(p) em { color: blue }",
+    );
+}
+
+#[test]
+fn nested_conditional_rules() {
+    assert_desugars_to(
+        ".card { padding: 8px; @media (min-width: 500px) { padding: 16px; & h2 { color: red } } }",
+        ".card { padding: 8px } @media (min-width: 500px) { .card { padding: 16px } :is(.card) h2 { color: red } }",
+    );
+    assert_desugars_to(
+        "p { @supports (display: block) { @media print { color: black } } }",
+        "@supports (display: block) { @media print { p { color: black } } }",
+    );
+}
+
+#[test]
+fn nesting_inside_at_rules() {
+    assert_desugars_to(
+        "@media screen { .a { & .b { color: red } } }",
+        "@media screen { :is(.a) .b { color: red } }",
+    );
+    assert_desugars_to(
+        "@layer base { .a { .b & { color: red } } }",
+        "@layer base { .b :is(.a) { color: red } }",
+    );
+}
+
+#[test]
+fn specificity_is_that_of_is() {
+    let stylesheet = stylesheet("#main, p { & span { color: red } }");
+    assert_eq!(stylesheet.rules[0].selectors[0].specificity(), (1, 0, 1));
+}
+
+#[test]
+fn nested_rules_match() {
+    let html = "
+        <html><body>
+            <div class=\"card\" id=\"card\"><h2 id=\"title\">Title</h2><p id=\"text\">Text</p></div>
+            <p id=\"outside\">Text</p>
+        </body></html>
+    ";
+    let styles = styles(html, "
+        .card {
+            color: blue;
+            & h2 { color: red }
+            > p { color: green; @media (min-width: 600px) { color: yellow } }
+        }
+        p { color: black }
+    ");
+    assert_eq!(styles["card"].color, color("blue"));
+    assert_eq!(styles["title"].color, color("red"));
+    assert_eq!(styles["text"].color, color("yellow"));
+    assert_eq!(styles["outside"].color, color("black"));
+}
@@ -0,0 +1,82 @@
+use ukechug::css::{Parser, Value, Unit};
+
+/// The values of the declarations of a single rule.
+fn values(declarations: &str) -> Vec<Value> {
+    let stylesheet = Parser::new(format!("p {{ {} }}", declarations)).parse_stylesheet();
+    stylesheet.rules[0].declarations.iter().map(|declaration| declaration.value.clone()).collect()
+}
+
+#[test]
+fn signed_lengths() {
+    assert_eq!(
+        values("margin-left: -10px; margin-right: +3px; margin-top: -0.5em; text-indent: -1rem"),
+        [
+            Value::Length(-10.0, Unit::Px),
+            Value::Length(3.0, Unit::Px),
+            Value::Length(-0.5, Unit::Em),
+            Value::Length(-1.0, Unit::Rem),
+        ]
+    );
+}
+
+#[test]
+fn fractions_without_a_leading_digit() {
+    assert_eq!(
+        values("width: .5em; height: -.25px; padding-left: +.75%"),
+        [Value::Length(0.5, Unit::Em), Value::Length(-0.25, Unit::Px), Value::Length(0.75, Unit::Percent)]
+    );
+}
+
+#[test]
+fn scientific_notation() {
+    assert_eq!(
+        values("width: 1e2px; height: 2.5E1px; margin-left: -1e-1em; padding-top: 3e+1%"),
+        [
+            Value::Length(100.0, Unit::Px),
+            Value::Length(25.0, Unit::Px),
+            Value::Length(-0.1, Unit::Em),
+            Value::Length(30.0, Unit::Percent),
+        ]
+    );
+}
+
+#[test]
+fn unitless_numbers() {
+    assert_eq!(
+        values("line-height: 1.4; z-index: 3; opacity: .5; order: -2; flex-grow: 1e1"),
+        [Value::Number(1.4), Value::Number(3.0), Value::Number(0.5), Value::Number(-2.0), Value::Number(10.0)]
+    );
+}
+
+#[test]
+fn signs_and_exponents_need_digits() {
+    // `-` starts an identifier and `e` a unit when no digits follow.
+    assert_eq!(
+        values("float: -moz-none; width: 2em"),
+        [Value::Keyword("-moz-none".to_string()), Value::Length(2.0, Unit::Em)]
+    );
+}
+
+#[test]
+fn numbers_in_lists() {
+    assert_eq!(
+        values("margin: -4px .5em 0 +1px"),
+        [Value::List(vec![
+            Value::Length(-4.0, Unit::Px),
+            Value::Length(0.5, Unit::Em),
+            Value::Number(0.0),
+            Value::Length(1.0, Unit::Px),
+        ])]
+    );
+}
+
+#[test]
+fn declarations_with_unknown_units_are_dropped() {
+    assert_eq!(
+        values("font-size: 12pt; width: 2ch; margin-left: 1px; transition-duration: .3s !important; height: 3em"),
+        [Value::Length(1.0, Unit::Px), Value::Length(3.0, Unit::Em)]
+    );
+    let declarations = Parser::new("width: 5furlongs; color: red".to_string()).parse_inline_declarations();
+    assert_eq!(declarations.len(), 1);
+    assert_eq!(declarations[0].name, "color");
+}
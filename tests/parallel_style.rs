@@ -0,0 +1,71 @@
+use ukechug::css::{Parser, MediaEnvironment, user_agent_stylesheet};
+use ukechug::html::HTMLParser;
+use ukechug::style::{StyledNode, style_tree_with_statistics};
+
+const CSS: &str = "
+    :root { --accent: #336699; font-size: 18px; }
+    .section { margin: 1em; padding: 2%; counter-reset: item; }
+    .section:nth-child(3n) { background: lightgray; }
+    .section > h2::before { content: counter(chapter, upper-roman) \". \"; counter-increment: chapter; }
+    li { counter-increment: item; }
+    li:first-child { color: var(--accent); }
+    li:nth-child(2n+1) span { font-size: 1.5em; }
+    li + li { border-top-width: 1px; }
+    li:has(em) { padding: 4px; }
+    p:empty { display: none; }
+    .note::after { content: \" (\" attr(data-id) \")\"; }
+    [data-id$=\"7\"] { font-weight: bold; }
+    .section:not(:first-child) h2 { width: calc(100% - 20px); }
+";
+
+fn page() -> String {
+    let mut html = String::from("<html><body>");
+    for section in 0..30 {
+        html.push_str(&format!("<div class=\"section\"><h2>Section {}</h2><ul>", section));
+        for item in 0..12 {
+            let emphasis = if item % 5 == 0 { "<em>!</em>" } else { "" };
+            html.push_str(&format!("<li class=\"note\" data-id=\"{}\"><span>Item {}</span>{}</li>", item, item, emphasis));
+        }
+        html.push_str("</ul><p></p><p class=\"note\" data-id=\"p\">Text</p></div>");
+    }
+    html.push_str("</body></html>");
+    html
+}
+
+fn assert_same_tree(serial: &StyledNode, parallel: &StyledNode) {
+    assert!(std::ptr::eq(serial.node, parallel.node));
+    assert_eq!(serial.pseudo_element, parallel.pseudo_element);
+    assert_eq!(*serial.style, *parallel.style);
+    assert_eq!(serial.generated_text, parallel.generated_text);
+    assert_eq!(serial.children.len(), parallel.children.len());
+    for (serial_child, parallel_child) in serial.children.iter().zip(&parallel.children) {
+        assert_same_tree(serial_child, parallel_child);
+    }
+}
+
+#[test]
+fn parallel_styling_matches_serial_styling() {
+    let root = HTMLParser::new().parse(&page());
+    let stylesheets = [user_agent_stylesheet(), Parser::new(CSS.to_string()).parse_stylesheet()];
+    let media = MediaEnvironment::new(800.0, 600.0);
+
+    let (serial, serial_statistics) = style_tree_with_statistics(&root, &stylesheets, &media, 1);
+    for threads in [2, 3, 4, 8, 16] {
+        let (parallel, statistics) = style_tree_with_statistics(&root, &stylesheets, &media, threads);
+        assert_same_tree(&serial, &parallel);
+        assert_eq!(statistics.elements, serial_statistics.elements);
+    }
+}
+
+#[test]
+fn parallel_styling_is_deterministic() {
+    let root = HTMLParser::new().parse(&page());
+    let stylesheets = [Parser::new(CSS.to_string()).parse_stylesheet()];
+    let media = MediaEnvironment::new(800.0, 600.0);
+
+    let (first, _) = style_tree_with_statistics(&root, &stylesheets, &media, 4);
+    for _ in 0..5 {
+        let (again, _) = style_tree_with_statistics(&root, &stylesheets, &media, 4);
+        assert_same_tree(&first, &again);
+    }
+}
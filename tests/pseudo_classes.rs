@@ -0,0 +1,117 @@
+mod common;
+
+use common::{matching_ids, matching_ids_in, styles, color};
+use std::collections::HashMap;
+use ukechug::css::Parser;
+use ukechug::dom::Node;
+
+const LIST: &str = "
+    <ul id=\"list\">
+        <li id=\"i1\" class=\"done\"></li>
+        <li id=\"i2\"></li>
+        <li id=\"i3\" class=\"done\"><em>!</em></li>
+        <li id=\"i4\" class=\"done\">4</li>
+        <li id=\"i5\"></li>
+        <li id=\"i6\" class=\"done\">Text</li>
+    </ul>
+";
+
+const MIXED: &str = "
+    <div id=\"root\">
+        <h2 id=\"h1\"></h2><p id=\"p1\"></p><p id=\"p2\"></p><h2 id=\"h2\"></h2><p id=\"p3\"></p>
+        <section id=\"only\"><span id=\"alone\"></span></section>
+    </div>
+";
+
+fn specificity(selector: &str) -> (usize, usize, usize) {
+    Parser::new(selector.to_string()).parse_selector_list()[0].specificity()
+}
+
+#[test]
+fn first_last_and_only_child() {
+    assert_eq!(matching_ids(LIST, "li:first-child"), ["i1"]);
+    assert_eq!(matching_ids(LIST, "li:last-child"), ["i6"]);
+    assert_eq!(matching_ids(MIXED, ":only-child"), ["root", "alone"]);
+    assert_eq!(matching_ids(MIXED, "section:only-child"), Vec::<String>::new());
+}
+
+#[test]
+fn nth_child() {
+    assert_eq!(matching_ids(LIST, "li:nth-child(2n+1)"), ["i1", "i3", "i5"]);
+    assert_eq!(matching_ids(LIST, "li:nth-child(even)"), ["i2", "i4", "i6"]);
+    assert_eq!(matching_ids(LIST, "li:nth-child(-n+2)"), ["i1", "i2"]);
+    assert_eq!(matching_ids(LIST, "li:nth-child(4)"), ["i4"]);
+    assert_eq!(matching_ids(LIST, "li:nth-child(n+5)"), ["i5", "i6"]);
+    assert_eq!(matching_ids(LIST, "li:nth-last-child(2)"), ["i5"]);
+    assert_eq!(matching_ids(LIST, "li:nth-last-child(odd)"), ["i2", "i4", "i6"]);
+}
+
+#[test]
+fn nth_child_of_a_selector_counts_only_matching_siblings() {
+    assert_eq!(matching_ids(LIST, "li:nth-child(2 of .done)"), ["i3"]);
+    assert_eq!(matching_ids(LIST, "li:nth-child(odd of .done)"), ["i1", "i4"]);
+    assert_eq!(matching_ids(LIST, ":nth-last-child(1 of .done)"), ["i6"]);
+    // The element itself must match `S` too.
+    assert!(matching_ids(LIST, "#i2:nth-child(1 of .done)").is_empty());
+}
+
+#[test]
+fn of_type() {
+    assert_eq!(matching_ids(MIXED, "p:first-of-type"), ["p1"]);
+    assert_eq!(matching_ids(MIXED, "h2:last-of-type"), ["h2"]);
+    assert_eq!(matching_ids(MIXED, "span:only-of-type, section:only-of-type"), ["only", "alone"]);
+    assert_eq!(matching_ids(MIXED, "p:nth-of-type(2n+1)"), ["p1", "p3"]);
+    assert_eq!(matching_ids(MIXED, "p:nth-last-of-type(2)"), ["p2"]);
+}
+
+#[test]
+fn empty_and_root() {
+    assert_eq!(matching_ids(LIST, "li:empty"), ["i1", "i2", "i5"]);
+    // Comments do not count as content, but whitespace does.
+    let id = |id: &str| HashMap::from([("id".to_string(), id.to_string())]);
+    let root = Node::element("div".to_string(), id("root"), vec![
+        Node::element("p".to_string(), id("comment"), vec![Node::comment(" note ".to_string())]),
+        Node::element("p".to_string(), id("space"), vec![Node::text(" ".to_string())]),
+    ]);
+    assert_eq!(matching_ids_in(&root, "p:empty"), ["comment"]);
+    assert_eq!(matching_ids(MIXED, ":root"), ["root"]);
+    assert!(matching_ids(MIXED, "p:root").is_empty());
+}
+
+#[test]
+fn logical_pseudo_classes() {
+    assert_eq!(matching_ids(LIST, "li:not(.done)"), ["i2", "i5"]);
+    assert_eq!(matching_ids(LIST, "li:not(.done, :first-child, :last-child)"), ["i2", "i5"]);
+    assert_eq!(matching_ids(MIXED, ":is(h2, section) + p"), ["p1", "p3"]);
+    assert_eq!(matching_ids(MIXED, ":where(#p1, #p3)"), ["p1", "p3"]);
+    assert_eq!(matching_ids(LIST, "li:is(:nth-child(2), .done:empty)"), ["i1", "i2"]);
+}
+
+#[test]
+fn has() {
+    assert_eq!(matching_ids(LIST, "li:has(em)"), ["i3"]);
+    assert_eq!(matching_ids(LIST, "ul:has(> li.done)"), ["list"]);
+    assert_eq!(matching_ids(MIXED, "h2:has(+ p)"), ["h1", "h2"]);
+    assert_eq!(matching_ids(MIXED, "p:has(~ section)"), ["p1", "p2", "p3"]);
+    assert_eq!(matching_ids(MIXED, ":has(> section > span)"), ["root"]);
+    assert!(matching_ids(MIXED, "section:has(> p)").is_empty());
+}
+
+#[test]
+fn specificity_of_pseudo_classes() {
+    assert_eq!(specificity("li:first-child"), (0, 1, 1));
+    assert_eq!(specificity("li:nth-child(2n+1)"), (0, 1, 1));
+    assert_eq!(specificity("li:nth-child(2 of .done, #x)"), (1, 1, 1));
+    assert_eq!(specificity(":where(#a, .b) p"), (0, 0, 1));
+    assert_eq!(specificity(":is(#a, .b) p"), (1, 0, 1));
+    assert_eq!(specificity("p:not(.a, .b.c)"), (0, 2, 1));
+    assert_eq!(specificity("div:has(> img, #hero)"), (1, 0, 1));
+}
+
+#[test]
+fn where_loses_to_any_other_selector() {
+    let css = "li { color: red } :where(#list) li:where(.done) { color: blue } :is(#list) li { color: green }";
+    assert_eq!(styles(LIST, css)["i1"].color, color("green"));
+    let css = ":where(#list > li) { color: blue } li { color: red }";
+    assert_eq!(styles(LIST, css)["i1"].color, color("red"));
+}
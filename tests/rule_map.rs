@@ -0,0 +1,106 @@
+mod common;
+
+use common::stylesheet;
+use ukechug::css::{PseudoElement, MediaEnvironment, user_agent_stylesheet};
+use ukechug::dom::ElementRef;
+use ukechug::html::HTMLParser;
+use ukechug::style::{RuleMap, AncestorFilter, matching_rules, indexed_matching_rules};
+use ukechug::style::bloom::{tag_hash, id_hash, class_hash};
+
+const CSS: &str = "
+    * { margin: 0 }
+    p, .note, #intro { color: red }
+    div p { color: blue }
+    .card > .title { color: green }
+    .card .missing span, #nowhere p { color: yellow }
+    ul li:nth-child(2n), li.active { color: gray }
+    [data-kind] em, a[href^=\"http\"] { color: white }
+    h2 + p, h2 ~ ul { margin: 1px }
+    .card.wide, div#main.card { width: 100px }
+    li::before, .note::after { content: \"*\" }
+    :not(p) > em, :is(.card, #main) h2 { color: black }
+    @media print { p { color: black } }
+    @media (min-width: 100px) { .title { color: blue } }
+";
+
+const PAGE: &str = "
+    <html><body>
+        <div id=\"main\" class=\"card wide\">
+            <h2 class=\"title\">Title</h2>
+            <p id=\"intro\" class=\"note\">Intro <em>now</em></p>
+            <ul><li>One</li><li class=\"active\">Two <span>x</span></li><li data-kind=\"x\">Three <em>!</em></li></ul>
+        </div>
+        <p>Outside <a href=\"http://example.com\">link</a></p>
+    </body></html>
+";
+
+/// Calls `visit` with every element under `element` and the filter of its
+/// ancestors, in document order.
+fn for_each_element(element: &ElementRef, ancestors: &AncestorFilter, visit: &mut dyn FnMut(&ElementRef, &AncestorFilter)) {
+    let Some(elem) = element.element() else {
+        return;
+    };
+    visit(element, ancestors);
+    let mut filter = *ancestors;
+    filter.insert(elem);
+    for index in 0..element.node.children.len() {
+        let child = element.child(index);
+        for_each_element(&child, &filter, visit);
+    }
+}
+
+#[test]
+fn indexed_matching_finds_the_same_rules() {
+    let root = HTMLParser::new().parse(PAGE);
+    let media = MediaEnvironment::new(800.0, 600.0);
+    for stylesheet in [stylesheet(CSS), user_agent_stylesheet()] {
+        let rules = RuleMap::new(&stylesheet, &media);
+        let mut elements = 0;
+        for_each_element(&ElementRef::root(&root), &AncestorFilter::new(), &mut |element, ancestors| {
+            elements += 1;
+            for pseudo_element in [None, Some(PseudoElement::Before), Some(PseudoElement::After)] {
+                let expected = matching_rules(element, pseudo_element, &stylesheet, &media);
+                let indexed = indexed_matching_rules(element, pseudo_element, &rules, ancestors);
+                let indices = |matched: &[(usize, _, _)]| matched.iter().map(|&(index, selector, _)| (index, selector)).collect::<Vec<_>>();
+                assert_eq!(indices(&indexed), indices(&expected), "{:?}", element.element());
+                // The filter built while walking down equals the one built by
+                // walking up.
+                assert_eq!(*ancestors, AncestorFilter::for_element(element));
+            }
+        });
+        assert_eq!(elements, 14);
+    }
+}
+
+#[test]
+fn selectors_are_bucketed_by_their_rightmost_compound() {
+    let stylesheet = stylesheet("
+        div { color: red } #other { color: red } .y { color: red } p { color: red } * { color: red }
+        .x { color: red } #para.x { color: red } div .x { color: red } @media print { p { color: red } }
+    ");
+    let rules = RuleMap::new(&stylesheet, &MediaEnvironment::new(800.0, 600.0));
+    let root = HTMLParser::new().parse("<div><p class=\"x\">Text</p></div>");
+    let div = ElementRef::root(&root);
+    let paragraph = div.child(0);
+    let candidates = |ancestors: &AncestorFilter| -> Vec<usize> {
+        rules.candidates(paragraph.element().unwrap(), ancestors).iter().map(|entry| entry.rule_index).collect()
+    };
+    // Class candidates are looked up before tag and universal ones, but are
+    // returned in rule order.
+    assert_eq!(candidates(&AncestorFilter::for_element(&paragraph)), [3, 4, 5, 7]);
+    // Without a div among the ancestors, `div .x` is left out.
+    assert_eq!(candidates(&AncestorFilter::new()), [3, 4, 5]);
+}
+
+#[test]
+fn the_ancestor_filter_has_no_false_negatives() {
+    let root = HTMLParser::new().parse("<section id=\"top\" class=\"a b\"><div class=\"c\"><p>Text</p></div></section>");
+    let section = ElementRef::root(&root);
+    let div = section.child(0);
+    let paragraph = div.child(0);
+    let filter = AncestorFilter::for_element(&paragraph);
+    for hash in [tag_hash("section"), tag_hash("div"), id_hash("top"), class_hash("a"), class_hash("b"), class_hash("c")] {
+        assert!(filter.might_contain(hash));
+    }
+    assert!(!AncestorFilter::new().might_contain(tag_hash("section")));
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use common::stylesheet;
+use ukechug::css::{Parser, Stylesheet, Origin, Value, Unit, MediaEnvironment};
+use ukechug::html::HTMLParser;
+use ukechug::style::{StyleReport, ValueSource, CascadeLevel};
+
+const PAGE: &str = "<html><body><div id=\"main\" class=\"a b\"><p style=\"color: red !important\">Text <em>more</em></p></div></body></html>";
+
+const CSS: &str = "div {
+    width: 10px;
+    color: blue
+}
+
+#main { margin: 0 }
+p { color: green }
+";
+
+fn page_stylesheet() -> Stylesheet {
+    Parser::with_base_url(CSS.to_string(), "https://example.com/page.css".parse().unwrap()).parse_stylesheet()
+}
+
+/// Builds the report of `PAGE` with `stylesheets` and passes it to `check`.
+fn with_report(stylesheets: &[Stylesheet], check: impl FnOnce(&StyleReport)) {
+    let root = HTMLParser::new().parse(PAGE);
+    let report = StyleReport::new(&root, stylesheets, &MediaEnvironment::new(800.0, 600.0));
+    check(&report);
+}
+
+#[test]
+fn elements_are_labelled_by_their_ancestry() {
+    with_report(&[page_stylesheet()], |report| {
+        let labels: Vec<&str> = report.elements.iter().map(|element| element.label.as_str()).collect();
+        assert_eq!(labels, ["html", "html > body", "html > body > div#main.a.b", "html > body > div#main.a.b > p", "html > body > div#main.a.b > p > em"]);
+    });
+}
+
+#[test]
+fn properties_name_their_winning_declaration() {
+    with_report(&[page_stylesheet()], |report| {
+        let div = &report.elements[2];
+        let names: Vec<&str> = div.properties.iter().map(|property| property.name.as_str()).collect();
+        assert_eq!(names, ["color", "margin", "width"]);
+        assert_eq!(div.properties[2].value, Value::Length(10.0, Unit::Px));
+
+        let paragraph = &report.elements[3];
+        let ValueSource::Declared(ref declared) = paragraph.properties[0].source else {
+            panic!("color is declared");
+        };
+        assert_eq!(declared.level, CascadeLevel::InlineImportant);
+        // Inherited values say so instead of naming a rule.
+        let em = &report.elements[4];
+        assert_eq!(em.properties[0].name, "color");
+        assert!(matches!(em.properties[0].source, ValueSource::Inherited));
+    });
+}
+
+#[test]
+fn text_report() {
+    with_report(&[page_stylesheet()], |report| {
+        let text = report.to_text();
+        let expected = "\
+html > body > div#main.a.b
+  color: blue;  div  (https://example.com/page.css:1)
+  margin: 0;    #main  (https://example.com/page.css:6)
+  width: 10px;  div  (https://example.com/page.css:1)
+
+html > body > div#main.a.b > p
+  color: red;  style attribute
+
+html > body > div#main.a.b > p > em
+  color: red;  inherited
+";
+        assert!(text.starts_with("html\n\nhtml > body\n\n"), "{}", text);
+        assert!(text.ends_with(&format!("{}\n", expected)), "{}", text);
+    });
+}
+
+#[test]
+fn json_report() {
+    let stylesheets = [
+        stylesheet("em { content: \"say \\\"hi\\\"\" !important }").with_origin(Origin::User),
+        page_stylesheet(),
+    ];
+    with_report(&stylesheets, |report| {
+        let json = report.to_json();
+        assert!(json.starts_with("[\n  {\"element\": \"html\", \"properties\": [\n  ]},"), "{}", json);
+        assert!(json.contains(
+            "{\"name\": \"margin\", \"value\": \"0\", \"source\": {\"type\": \"rule\", \"selector\": \"#main\", \
+             \"file\": \"https://example.com/page.css\", \"line\": 6, \"origin\": \"author\", \"important\": false}}"
+        ), "{}", json);
+        assert!(json.contains("{\"name\": \"color\", \"value\": \"red\", \"source\": {\"type\": \"style-attribute\", \"important\": true}}"), "{}", json);
+        assert!(json.contains(
+            "{\"name\": \"content\", \"value\": \"\\\"say \\\\\\\"hi\\\\\\\"\\\"\", \"source\": {\"type\": \"rule\", \"selector\": \"em\", \
+             \"file\": \"<stylesheet>\", \"line\": 1, \"origin\": \"user\", \"important\": true}}"
+        ), "{}", json);
+        assert!(json.contains("{\"name\": \"color\", \"value\": \"red\", \"source\": {\"type\": \"inherited\"}}"), "{}", json);
+        assert!(json.ends_with("\n  ]}\n]\n"));
+    });
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use std::sync::Arc;
+use common::{stylesheet, color};
+use ukechug::css::{MediaEnvironment, user_agent_stylesheet};
+use ukechug::dom::NodeType;
+use ukechug::html::HTMLParser;
+use ukechug::style::{StyledNode, StyleStatistics, style_tree_with_statistics};
+
+/// Styles `html` on one thread and calls `visit` with the styled `li`
+/// elements, in document order, and the statistics of the style pass.
+fn styled_items(html: &str, css: &str, visit: impl FnOnce(&[&StyledNode], StyleStatistics)) {
+    fn collect<'s, 'a>(node: &'s StyledNode<'a>, items: &mut Vec<&'s StyledNode<'a>>) {
+        if let (None, NodeType::Element(ref elem)) = (node.pseudo_element, &node.node.node_type) {
+            if elem.tag_name == "li" {
+                items.push(node);
+            }
+        }
+        for child in &node.children {
+            collect(child, items);
+        }
+    }
+    let root = HTMLParser::new().parse(html);
+    let stylesheets = [user_agent_stylesheet(), stylesheet(css)];
+    let (styled, statistics) = style_tree_with_statistics(&root, &stylesheets, &MediaEnvironment::new(800.0, 600.0), 1);
+    let mut items = Vec::new();
+    collect(&styled, &mut items);
+    visit(&items, statistics);
+}
+
+fn list(items: &[&str]) -> String {
+    let items: String = items.iter().map(|item| format!("<li{}>Item</li>", item)).collect();
+    format!("<html><body><ul>{}</ul></body></html>", items)
+}
+
+#[test]
+fn alike_siblings_share_one_style() {
+    let html = list(&[""; 20]);
+    styled_items(&html, "li { color: red; padding: 1em }", |items, statistics| {
+        assert_eq!(items.len(), 20);
+        assert!(items.iter().all(|item| Arc::ptr_eq(&item.style, &items[0].style)));
+        assert_eq!(items[0].style.color, color("red"));
+        assert!(statistics.sharing_hits >= 19);
+        assert!(statistics.hit_rate() > 0.5);
+    });
+}
+
+#[test]
+fn ids_and_inline_styles_are_styled_alone() {
+    let html = list(&["", " id=\"a\"", " style=\"color: blue\"", ""]);
+    styled_items(&html, "li { color: red } #a { color: green }", |items, _| {
+        let colors: Vec<_> = items.iter().map(|item| item.style.color).collect();
+        assert_eq!(colors, [color("red"), color("green"), color("blue"), color("red")]);
+        assert!(!Arc::ptr_eq(&items[1].style, &items[0].style));
+        assert!(!Arc::ptr_eq(&items[2].style, &items[0].style));
+    });
+}
+
+#[test]
+fn classes_and_attributes_tested_by_selectors_split_styles() {
+    let html = list(&[" class=\"a b\"", " class=\"b a\"", " class=\"a\"", " data-x=\"1\"", " data-x=\"2\"", " class=\"a\" title=\"t\""]);
+    let css = ".a.b { color: red } [data-x=\"1\"] { color: green } [data-x=\"2\"] { color: blue }";
+    styled_items(&html, css, |items, _| {
+        let colors: Vec<_> = items.iter().map(|item| item.style.color).collect();
+        assert_eq!(colors, [color("red"), color("red"), color("black"), color("green"), color("blue"), color("black")]);
+        // Class order does not matter, nor do attributes no selector tests.
+        assert!(Arc::ptr_eq(&items[0].style, &items[1].style));
+        assert!(Arc::ptr_eq(&items[2].style, &items[5].style));
+    });
+}
+
+#[test]
+fn structural_selectors_are_revalidated() {
+    let html = "<html><body><ul>
+        <li>Item</li><li>Item</li><li>Item <em>!</em></li><li>Item</li><li></li><li>Item</li>
+    </ul></body></html>";
+    let css = "
+        li:nth-child(2n) { color: red }
+        li:first-child { color: green }
+        li:has(em) { color: blue }
+        li:empty { color: yellow }
+        li:last-child { color: gray }
+    ";
+    styled_items(html, css, |items, _| {
+        let colors: Vec<_> = items.iter().map(|item| item.style.color).collect();
+        assert_eq!(colors, [color("green"), color("red"), color("blue"), color("red"), color("yellow"), color("gray")]);
+    });
+}
+
+#[test]
+fn sharing_follows_the_parent_style() {
+    let html = "<html><body>
+        <ul class=\"odd\"><li>Item</li><li>Item</li></ul>
+        <ul class=\"even\"><li>Item</li><li>Item</li></ul>
+        <ul class=\"odd\"><li>Item</li><li>Item</li></ul>
+    </body></html>";
+    styled_items(html, ".odd { color: red } .even { color: blue }", |items, _| {
+        let colors: Vec<_> = items.iter().map(|item| item.style.color).collect();
+        assert_eq!(colors, [color("red"), color("red"), color("blue"), color("blue"), color("red"), color("red")]);
+        assert!(Arc::ptr_eq(&items[0].style, &items[1].style));
+        assert!(!Arc::ptr_eq(&items[1].style, &items[2].style));
+    });
+}
+
+#[test]
+fn hit_rate() {
+    let statistics = StyleStatistics { elements: 10, sharing_hits: 3, sharing_misses: 1 };
+    assert_eq!(statistics.hit_rate(), 0.75);
+    assert_eq!(StyleStatistics::default().hit_rate(), 0.0);
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use common::{stylesheet, styles, styles_with, color};
+use ukechug::css::{Color, Origin, MediaEnvironment, user_agent_stylesheet};
+use ukechug::style::{Display, ListStyleType, LengthPercentage, LengthPercentageOrAuto, Sides};
+
+const PAGE: &str = "
+    <html id=\"html\"><head id=\"head\"><title id=\"title\">Title</title><style id=\"style\">p { }</style></head>
+    <body id=\"body\">
+        <h1 id=\"h1\">One</h1><h2 id=\"h2\">Two</h2><h6 id=\"h6\">Six</h6>
+        <div id=\"div\"><p id=\"p\">Text <b id=\"b\">bold</b> <a id=\"a\" href=\"/\">link</a></p></div>
+        <ul id=\"ul\"><li id=\"li\">Item<ul id=\"second\"><li>Item</li></ul><ol id=\"ol\"><li>Item<ul id=\"third\"><li>Item</li></ul></li></ol></li></ul>
+        <section id=\"section\" hidden>Hidden</section>
+        <input id=\"input\"><script id=\"script\">x</script>
+    </body></html>
+";
+
+fn px(value: f32) -> LengthPercentageOrAuto {
+    LengthPercentageOrAuto::LengthPercentage(LengthPercentage::Length(value))
+}
+
+/// Vertical margins of `value` px and no horizontal ones.
+fn vertical(value: f32) -> Sides<LengthPercentageOrAuto> {
+    Sides { top: px(value), right: px(0.0), bottom: px(value), left: px(0.0) }
+}
+
+#[test]
+fn display_types() {
+    let styles = styles(PAGE, "");
+    for id in ["html", "body", "h1", "div", "p", "ul", "ol", "section"] {
+        assert_eq!(styles[id].display, if id == "section" { Display::None } else { Display::Block }, "{}", id);
+    }
+    assert_eq!(styles["li"].display, Display::ListItem);
+    assert_eq!(styles["b"].display, Display::Inline);
+    assert_eq!(styles["input"].display, Display::InlineBlock);
+}
+
+#[test]
+fn metadata_and_scripts_are_hidden() {
+    let styles = styles(PAGE, "");
+    for id in ["head", "title", "style", "script"] {
+        assert!(styles.get(id).is_none_or(|style| style.display == Display::None), "{}", id);
+    }
+}
+
+#[test]
+fn headings_and_margins() {
+    let styles = styles(PAGE, "");
+    assert_eq!(styles["body"].margin, Sides::all(px(8.0)));
+    assert_eq!(styles["h1"].font_size, 32.0);
+    assert_eq!(styles["h1"].margin, vertical(32.0 * 0.67));
+    assert_eq!(styles["h2"].font_size, 24.0);
+    assert_eq!(styles["h6"].font_size, 16.0 * 0.67);
+    assert_eq!(styles["p"].margin, vertical(16.0));
+    assert_eq!(styles["div"].margin, Sides::all(px(0.0)));
+}
+
+#[test]
+fn lists() {
+    let styles = styles(PAGE, "");
+    assert_eq!(styles["ul"].padding.left, LengthPercentage::Length(40.0));
+    assert_eq!(styles["ul"].list_style_type, ListStyleType::Disc);
+    assert_eq!(styles["ol"].list_style_type, ListStyleType::Decimal);
+    assert_eq!(styles["ol"].margin, Sides::all(px(0.0)));
+    // Unordered lists nested in other lists use circles, then squares.
+    assert_eq!(styles["second"].list_style_type, ListStyleType::Circle);
+    assert_eq!(styles["third"].list_style_type, ListStyleType::Square);
+}
+
+#[test]
+fn links() {
+    let styles = styles(PAGE, "");
+    assert_eq!(styles["a"].color, Color { r: 0, g: 0, b: 0xee, a: 255 });
+}
+
+#[test]
+fn every_other_origin_overrides_it() {
+    let styles = styles(PAGE, "body { margin: 0 } h1 { font-size: 20px } section { display: block }");
+    assert_eq!(styles["body"].margin, Sides::all(px(0.0)));
+    assert_eq!(styles["h1"].font_size, 20.0);
+    assert_eq!(styles["section"].display, Display::Block);
+
+    let user = stylesheet("p { margin: 2px }").with_origin(Origin::User);
+    let styles = styles_with(PAGE, &[user_agent_stylesheet(), user], &MediaEnvironment::new(800.0, 600.0));
+    assert_eq!(styles["p"].margin, Sides::all(px(2.0)));
+}
+
+#[test]
+fn without_it_everything_is_inline() {
+    let styles = styles_with(PAGE, &[stylesheet("p { color: red }")], &MediaEnvironment::new(800.0, 600.0));
+    for id in ["html", "body", "div", "p", "li", "head", "script"] {
+        assert_eq!(styles[id].display, Display::Inline, "{}", id);
+    }
+    assert_eq!(styles["body"].margin, Sides::all(px(0.0)));
+    assert_eq!(styles["h1"].font_size, 16.0);
+    assert_eq!(styles["p"].color, color("red"));
+}
+
+#[test]
+fn it_has_user_agent_origin() {
+    assert_eq!(user_agent_stylesheet().origin, Origin::UserAgent);
+}